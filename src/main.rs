@@ -14,32 +14,72 @@
 //   - 要素削除機能追加
 
 use anyhow::Result;
-use chrono::{DateTime, Local};
-use headless_chrome::{Browser, LaunchOptions, Tab};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use headless_chrome::browser::{FetcherOptions, Revision};
+use headless_chrome::protocol::cdp::{Input, Performance};
+use headless_chrome::{Browser, Element, LaunchOptions, Tab};
 use nanorand::{Rng, WyRand};
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::ffi::OsStr;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// ============================================================
+// 表示言語
+// ============================================================
+// 起動時に設定から一度だけ決める（未設定なら日本語）
+static LANGUAGE: std::sync::OnceLock<Language> = std::sync::OnceLock::new();
+
+fn english() -> bool {
+    LANGUAGE.get() == Some(&Language::En)
+}
+
+// 日本語と英語の書式を並べて書き、表示言語の方で format! する
+macro_rules! tr {
+    ($ja:literal, $en:literal $(, $arg:expr)* $(,)?) => {
+        if english() {
+            format!($en $(, $arg)*)
+        } else {
+            format!($ja $(, $arg)*)
+        }
+    };
+}
 
 // ============================================================
 // 設定構造体（Config.toml用）
 // ============================================================
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct Config {
     #[serde(default = "default_profile_dir")]
     profile_dir: String,
 
+    // 指定すると profile_dir を毎回作り直す代わりに、使い込んだプロファイルを順に使う
+    #[serde(default)]
+    profile_pool: Option<ProfilePoolConfig>,
+
     #[serde(default = "default_chromium_path")]
     chromium_path: String,
 
+    // 指定するとこのリビジョンのChromiumを chromium_cache_dir にダウンロードして使う
+    // （chromium_path より優先。ペルソナごとに実在のバージョンを揃えたい場合に使う）
+    #[serde(default)]
+    chromium_revision: Option<String>,
+
+    // ダウンロードしたChromiumの置き場所（リビジョンごとに展開され、設定間で共有される）
+    #[serde(default = "default_chromium_cache_dir")]
+    chromium_cache_dir: String,
+
     #[serde(default = "default_result_dir")]
     result_dir: String,
 
@@ -49,8 +89,233 @@ struct Config {
     #[serde(default = "default_max_consecutive_no_next")]
     max_consecutive_no_next: u32,
 
+    // 文字列か、一部の設定を上書きする表（{ query = "...", max_pages = 3, ... }）
     #[serde(default = "default_search_queries")]
-    search_queries: Vec<String>,
+    search_queries: Vec<SearchQuery>,
+
+    // 1行1クエリのテキスト、または見出し行付きのCSV（query 列と上書き項目の列）。
+    // 起動時に読み込んで search_queries の後ろに加える
+    #[serde(default)]
+    queries_file: Option<String>,
+
+    // 各クエリに付ける検索演算子（[operators]）
+    #[serde(default)]
+    operators: SearchOperators,
+
+    #[serde(default)]
+    behavior: BehaviorConfig,
+
+    // 検索の前に関係のないサイトを見て回る（新しいセッションでいきなり検索しない）
+    #[serde(default)]
+    warm_up: Option<WarmUpConfig>,
+
+    // 各ページで結果をいくつか開いて読んでから検索結果に戻る
+    #[serde(default)]
+    click_through: Option<ClickThroughConfig>,
+
+    // ブラウザ起動ごとにランダムに選ぶ User-Agent（空なら device に応じた既定のUA）
+    #[serde(default)]
+    user_agents: Vec<String>,
+
+    // 指定すると位置情報を拒否する代わりにこの座標を返す（「近く」を含む検索など）
+    #[serde(default)]
+    geolocation: Option<GeolocationConfig>,
+
+    // mobile: スマートフォンの画面・タッチ・UAを再現し、モバイル版の検索結果を取得する
+    // （persona.platform は "Android" にする）
+    #[serde(default = "default_device")]
+    device: DeviceKind,
+
+    // 「続行する前に」の同意ダイアログ: reject=すべて拒否 / accept=すべて同意 / off=触らない
+    #[serde(default = "default_consent")]
+    consent: ConsentAction,
+
+    #[serde(default)]
+    proxy: Option<ProxyConfig>,
+
+    #[serde(default)]
+    proxies: Vec<ProxyConfig>,
+
+    #[serde(default = "default_proxy_rotation")]
+    proxy_rotation: ProxyRotation,
+
+    #[serde(default = "default_proxy_cooldown_secs")]
+    proxy_cooldown_secs: u64,
+
+    // 同時に動かすブラウザの数。2以上なら search_queries / flows を分け合い、
+    // ワーカーごとに profile_dir-N を使う（proxies が足りていればワーカー間で分け合う）
+    #[serde(default = "default_concurrency")]
+    concurrency: usize,
+
+    // 1つのブラウザで同時に開くタブ数。2以上ならタブごとに別のクエリを進める
+    // （profile は共有するのでクエリごとの再起動は行わず、タブを開き直す）
+    #[serde(default = "default_tabs")]
+    tabs: usize,
+
+    // クエリ間の profile リセット: relaunch=profile_dir を消して起動し直す /
+    // clear=起動中のブラウザの Cookie・ストレージを CDP で消して使い回す
+    #[serde(default = "default_restart_strategy")]
+    restart_strategy: RestartStrategy,
+
+    #[serde(default = "default_query_entry")]
+    query_entry: QueryEntryMode,
+
+    // 入力中に表示されたサジェストを suggestions.json に保存する
+    #[serde(default = "default_suggestion_capture")]
+    suggestion_capture: SuggestionCaptureMode,
+
+    #[serde(default)]
+    flows: Vec<QueryFlow>,
+
+    // search_queries の検索対象（flows は各フローで指定）
+    #[serde(default)]
+    search_type: SearchType,
+
+    #[serde(default = "default_engine")]
+    engine: EngineKind,
+
+    #[serde(default = "default_google_domain")]
+    google_domain: String,
+
+    #[serde(default)]
+    hl: Option<String>,
+
+    #[serde(default)]
+    gl: Option<String>,
+
+    // 期間の絞り込み（"past_week" など、または { from = "2024-01-01", to = "2024-06-30" }）
+    #[serde(default)]
+    date_range: Option<DateRange>,
+
+    // "off" / "moderate" / "strict"。未指定と "moderate" はエンジン（とプロファイル）の既定のまま
+    #[serde(default)]
+    safe_search: Option<SafeSearch>,
+
+    // Google の1ページあたりの件数（num パラメータ、10〜100）。未指定なら既定の10件
+    #[serde(default)]
+    results_per_page: Option<u32>,
+
+    // Google の結果抽出に使うセレクタ。マークアップ変更時に設定だけで直せるようにする
+    #[serde(default)]
+    selectors: SelectorsConfig,
+
+    // このドメイン（サブドメインを含む）の結果だけを残す。空なら絞り込まない
+    #[serde(default)]
+    include_domains: Vec<String>,
+
+    // このドメイン（サブドメインを含む）の結果を除く
+    #[serde(default)]
+    exclude_domains: Vec<String>,
+
+    // remove: 対象外の結果を消して順位を振り直す / flag: 消さずに extensions.domain_filter に印を付ける
+    #[serde(default = "default_domain_filter_mode")]
+    domain_filter_mode: DomainFilterMode,
+
+    #[serde(default = "default_sinks")]
+    sinks: Vec<SinkKind>,
+
+    #[serde(default)]
+    sink_options: SinkOptions,
+
+    #[serde(default)]
+    persona: PersonaConfig,
+
+    #[serde(default = "default_true")]
+    seed_preferences: bool,
+
+    // --daemon で使う定期実行スケジュール
+    #[serde(default)]
+    schedule: Option<ScheduleConfig>,
+
+    // --daemon で個別のスケジュールで回すクエリ集（指定時は schedule より優先）
+    #[serde(default)]
+    collections: Vec<CollectionConfig>,
+
+    // --daemon で enqueue されたクエリを順に処理するジョブキュー（指定時は collections より優先）
+    #[serde(default)]
+    queue: Option<QueueConfig>,
+
+    // 外部CAPTCHA解決サービス（captcha-solver フィーチャー付きビルドでのみ有効）
+    #[serde(default)]
+    captcha_solver: Option<CaptchaSolverConfig>,
+
+    // ステルス対策が効いているかをトップページ表示後に検証する
+    #[serde(default = "default_consistency_check")]
+    stealth_check: ConsistencyCheckMode,
+
+    // 結果ページごとにページ全体のスクリーンショット（N.png）を保存する
+    #[serde(default)]
+    screenshots: bool,
+
+    // 追加のフィンガープリント対策（Config.toml の [stealth]）
+    #[serde(default)]
+    stealth: StealthConfig,
+
+    // 組み込みスクリプトの後に注入する .js の置き場所（ファイル名順）
+    #[serde(default)]
+    stealth_scripts_dir: Option<String>,
+
+    // ステルス設定（CDP上書き・スクリプト注入）に失敗した場合の扱い
+    #[serde(default = "default_stealth_setup")]
+    stealth_setup: StealthSetupMode,
+
+    // 結果ページの生HTMLを N.html として保存する（セレクタ破損やCAPTCHAの切り分け用）
+    #[serde(default)]
+    save_html: bool,
+
+    // actions.jsonl とスクリーンショットから replay.html（操作の時系列）を作る
+    #[serde(default)]
+    session_replay: bool,
+
+    // 結果ページの表示時間を計測し、予算超過が続いたら警告する
+    #[serde(default)]
+    perf_budget: Option<PerfBudgetConfig>,
+
+    // 2GB程度のVPS向け。Chromiumのメモリを絞り、結果以外の解析とスクショ解像度を削る
+    #[serde(default)]
+    low_memory: bool,
+
+    // 画面のないサーバー向け。新しいヘッドレス（--headless=new）で起動する
+    #[serde(default)]
+    headless: bool,
+
+    // 進捗バー（クエリ X/Y・ページ N/M・取得件数・経過/残り時間）を表示する。
+    // 表示中のコンソールには警告以上だけを出す（ログファイルには全て残る）
+    #[serde(default)]
+    progress: bool,
+
+    // 実行後に人が読むためのレポート（report.html / report.md）を作る
+    #[serde(default = "default_report")]
+    report: ReportFormat,
+
+    // ログ出力（Config.toml の [log]）
+    #[serde(default)]
+    log: LogConfig,
+
+    // コンソール出力とレポートの言語（ja / en）
+    #[serde(default = "default_language")]
+    language: Language,
+
+    // 実行完了・ブロック時の通知（Config.toml の [notify]）
+    #[serde(default)]
+    notify: NotifyConfig,
+
+    // 実行開始・クエリ完了・CAPTCHA・実行終了を JSON で POST する先（Slack/Discord 互換。
+    // webhook フィーチャー付きビルドでのみ有効）
+    #[serde(default)]
+    webhook_url: Option<String>,
+
+    // 過去の実行履歴から、順位の安定したクエリほど間隔を空けて実行する（Config.toml の [smart_schedule]）
+    #[serde(default)]
+    smart_schedule: Option<SmartScheduleConfig>,
+
+    // クエリごとに最高順位を rankings.json / rankings.csv に集計するドメイン（サブドメインを含む）
+    #[serde(default)]
+    tracked_domains: Vec<String>,
+
+    // クエリ完了・実行完了時に外部コマンドを実行する（Config.toml の [hooks]）
+    #[serde(default)]
+    hooks: HooksConfig,
 }
 
 fn default_profile_dir() -> String {
@@ -59,6 +324,9 @@ fn default_profile_dir() -> String {
 fn default_chromium_path() -> String {
     "chromium/chrome.exe".to_string()
 }
+fn default_chromium_cache_dir() -> String {
+    "chromium-cache".to_string()
+}
 fn default_result_dir() -> String {
     "result".to_string()
 }
@@ -68,564 +336,7542 @@ fn default_max_pages() -> u32 {
 fn default_max_consecutive_no_next() -> u32 {
     2
 }
-fn default_search_queries() -> Vec<String> {
-    vec!["1".to_string(), "2".to_string(), "3".to_string()]
+fn default_search_queries() -> Vec<SearchQuery> {
+    ["1", "2", "3"]
+        .into_iter()
+        .map(|q| SearchQuery::Plain(q.to_string()))
+        .collect()
+}
+fn default_query_entry() -> QueryEntryMode {
+    QueryEntryMode::Enter
+}
+fn default_engine() -> EngineKind {
+    EngineKind::Google
+}
+fn default_google_domain() -> String {
+    "www.google.com".to_string()
+}
+fn default_sinks() -> Vec<SinkKind> {
+    vec![SinkKind::Json]
 }
 
-impl Default for Config {
+// 出力先（複数同時に有効化できる）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SinkKind {
+    Json,
+    Csv,
+    Sqlite,
+    Ndjson,
+    Query,
+}
+
+// Google（デスクトップ版）の結果セレクタ（Config.toml の [selectors]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SelectorsConfig {
+    // 自然検索結果のリンク
+    #[serde(default = "default_result_link_selector")]
+    result_link: String,
+
+    // リンク内のタイトル
+    #[serde(default = "default_result_title_selector")]
+    result_title: String,
+
+    // 「次へ」のリンク
+    #[serde(default = "default_next_page_selector")]
+    next_page: String,
+}
+
+fn default_result_link_selector() -> String {
+    r#"a[jsname="UWckNb"]"#.to_string()
+}
+fn default_result_title_selector() -> String {
+    "h3".to_string()
+}
+fn default_next_page_selector() -> String {
+    "#pnnext".to_string()
+}
+
+impl Default for SelectorsConfig {
     fn default() -> Self {
         Self {
-            profile_dir: default_profile_dir(),
-            chromium_path: default_chromium_path(),
-            result_dir: default_result_dir(),
-            max_pages: default_max_pages(),
-            max_consecutive_no_next: default_max_consecutive_no_next(),
-            search_queries: default_search_queries(),
+            result_link: default_result_link_selector(),
+            result_title: default_result_title_selector(),
+            next_page: default_next_page_selector(),
         }
     }
 }
 
-fn load_config() -> Config {
-    let config_path = get_base_path("Config.toml");
-    if config_path.exists() {
-        println!("設定ファイル読み込み: {:?}", config_path);
-        if let Ok(content) = fs::read_to_string(&config_path) {
-            if let Ok(cfg) = toml::from_str(&content) {
-                println!("設定ファイル読み込み成功");
-                return cfg;
+impl SelectorsConfig {
+    // 書き間違いは解析中に落ちる前に知らせる
+    fn validate(&self) -> Result<()> {
+        for (name, selector) in [
+            ("result_link", &self.result_link),
+            ("result_title", &self.result_title),
+            ("next_page", &self.next_page),
+        ] {
+            if Selector::parse(selector).is_err() {
+                anyhow::bail!(tr!(
+                    "selectors.{} が不正なセレクタです: {}",
+                    "selectors.{} is not a valid selector: {}",
+                    name,
+                    selector
+                ));
             }
         }
+        Ok(())
     }
-    println!("設定ファイル読み込み失敗。デフォルト使用。");
-    Config::default()
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct SearchResult {
-    rank: usize,
-    title: String,
-    url: String,
+// 出力先ごとのオプション（Config.toml の [sink_options.<名前>]）
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct SinkOptions {
+    #[serde(default)]
+    json: JsonSinkOptions,
+
+    #[serde(default)]
+    csv: CsvSinkOptions,
+
+    #[serde(default)]
+    sqlite: SqliteSinkOptions,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct PageResult {
-    query: String,
-    page: u32,
-    timestamp: String,
-    result_count: usize,
-    results: Vec<SearchResult>,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SqliteSinkOptions {
+    // result_dir からの相対パス。全実行で1つのDBを共有する
+    #[serde(default = "default_sqlite_path")]
+    path: String,
 }
 
-fn format_duration(start: DateTime<Local>, end: DateTime<Local>) -> String {
-    let duration = end.signed_duration_since(start);
-    let total_seconds = duration.num_seconds();
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let seconds = total_seconds % 60;
-    let millis = duration.num_milliseconds() % 1000;
-    if hours > 0 {
-        format!("{}時間{}分{}秒", hours, minutes, seconds)
-    } else if minutes > 0 {
-        format!("{}分{}秒", minutes, seconds)
-    } else {
-        format!("{}.{:03}秒", seconds, millis)
-    }
+fn default_sqlite_path() -> String {
+    "results.db".to_string()
 }
 
-fn get_base_path(relative: &str) -> PathBuf {
-    if cfg!(debug_assertions) {
-        let current_dir = env::current_dir().expect("カレントディレクトリ取得失敗");
-        current_dir.join(relative)
-    } else {
-        let exe_path = env::current_exe().expect("実行ファイルパス取得失敗");
-        exe_path.parent().unwrap().join(relative)
+impl Default for SqliteSinkOptions {
+    fn default() -> Self {
+        Self {
+            path: default_sqlite_path(),
+        }
     }
 }
 
-fn init_profile_dir(config: &Config) -> Result<PathBuf> {
-    let path = get_base_path(&config.profile_dir);
-    fs::create_dir_all(&path)?;
-    Ok(path)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CsvSinkOptions {
+    // ExcelでUTF-8として開けるよう先頭にBOMを付ける
+    #[serde(default = "default_true")]
+    bom: bool,
 }
 
-fn clear_profile_dir(config: &Config) -> Result<()> {
-    let path = get_base_path(&config.profile_dir);
-    if path.exists() {
-        fs::remove_dir_all(&path)?;
+impl Default for CsvSinkOptions {
+    fn default() -> Self {
+        Self {
+            bom: default_true(),
+        }
     }
-    Ok(())
-}
-
-fn init_result_dir(config: &Config, start_time: DateTime<Local>) -> Result<PathBuf> {
-    let time_str = start_time.format("%Y-%m-%d-%H-%M-%S").to_string();
-    let path = get_base_path(&config.result_dir).join(&time_str);
-    fs::create_dir_all(&path)?;
-    Ok(path)
 }
 
-fn init_query_result_dir(result_base: &PathBuf, query: &str) -> Result<PathBuf> {
-    let safe_query = query
-        .replace('/', "_")
-        .replace('\\', "_")
-        .replace(':', "_")
-        .replace('*', "_")
-        .replace('?', "_")
-        .replace('"', "_")
-        .replace('<', "_")
-        .replace('>', "_")
-        .replace('|', "_");
-    let path = result_base.join(&safe_query);
-    fs::create_dir_all(&path)?;
-    Ok(path)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct JsonSinkOptions {
+    #[serde(default = "default_true")]
+    pretty: bool,
 }
 
-fn save_search_results_json(
-    query_dir: &PathBuf,
-    query: &str,
-    page_num: u32,
-    results: &[(String, String)],
-) -> Result<()> {
-    let file_path = query_dir.join(format!("{}.json", page_num));
-    let search_results: Vec<SearchResult> = results
-        .iter()
-        .enumerate()
-        .map(|(i, (title, url))| SearchResult {
-            rank: i + 1,
-            title: title.clone(),
-            url: url.clone(),
-        })
-        .collect();
-    let page_result = PageResult {
-        query: query.to_string(),
-        page: page_num,
-        timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
-        result_count: search_results.len(),
-        results: search_results,
-    };
-    let json = serde_json::to_string_pretty(&page_result)?;
-    let mut file = fs::File::create(&file_path)?;
-    file.write_all(json.as_bytes())?;
-    Ok(())
+fn default_true() -> bool {
+    true
 }
 
-fn extract_search_results(html: &str) -> Vec<(String, String)> {
-    let document = Html::parse_document(html);
-    let selector = Selector::parse(r#"a[jsname="UWckNb"]"#).unwrap();
-    let mut results = Vec::new();
-    let mut seen_urls: HashSet<String> = HashSet::new();
-    
-    for element in document.select(&selector) {
-        let url = element.value().attr("href").unwrap_or("").to_string();
-        let title_selector = Selector::parse("h3").unwrap();
-        let title = element
-            .select(&title_selector)
-            .next()
-            .map(|h3| h3.text().collect::<String>())
-            .unwrap_or_default();
-        
-        // URL重複チェック（上位優先で残す）
-        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&url) {
-            seen_urls.insert(url.clone());
-            results.push((title, url));
+impl Default for JsonSinkOptions {
+    fn default() -> Self {
+        Self {
+            pretty: default_true(),
         }
     }
-    results
 }
 
-struct BrowserManager<'a> {
-    browser: Option<Browser>,
-    config: &'a Config,
+// 使用する検索エンジン
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum EngineKind {
+    Google,
+    Bing,
+    Duckduckgo,
+    Yahoo,
 }
 
-impl<'a> BrowserManager<'a> {
-    fn new(config: &'a Config) -> Self {
-        Self {
-            browser: None,
-            config,
-        }
-    }
+// 再現する端末の種類
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum DeviceKind {
+    Desktop,
+    Mobile,
+}
 
-    fn get_or_create(&mut self) -> Result<&Browser> {
-        if self.browser.is_none() {
-            self.browser = Some(launch_browser(self.config)?);
-        }
-        Ok(self.browser.as_ref().unwrap())
-    }
+fn default_device() -> DeviceKind {
+    DeviceKind::Desktop
+}
 
-    fn restart(&mut self) -> Result<&Browser> {
-        println!("ブラウザを再起動中（profileリセット）...");
-        self.browser = None;
-        thread::sleep(Duration::from_millis(2000));
-        self.browser = Some(launch_browser(self.config)?);
-        Ok(self.browser.as_ref().unwrap())
-    }
+// Google の Cookie 同意ダイアログ（EU 等で表示される）への応答
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ConsentAction {
+    Off,
+    Reject,
+    Accept,
 }
 
-// ============================================================
-// ブラウザ起動
-// ============================================================
-fn launch_browser(config: &Config) -> Result<Browser> {
-    println!("profile を強制リセット中...");
-    let _ = clear_profile_dir(config);
-    println!("profile 削除完了。新規作成中...");
-    let user_data_dir = init_profile_dir(config)?;
-    println!("新規 profile: {:?}", user_data_dir);
+fn default_consent() -> ConsentAction {
+    ConsentAction::Reject
+}
 
-    let chromium_path = get_base_path(&config.chromium_path);
-    println!("Chromium: {:?}", chromium_path);
+// クエリ送信方法（enter: Enterキー / suggestion: 一致するサジェストをクリック）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum QueryEntryMode {
+    Enter,
+    Suggestion,
+}
 
-    let args: Vec<&OsStr> = vec![
-        OsStr::new("--no-sandbox"),
-        OsStr::new("--disable-setuid-sandbox"),
-        OsStr::new("--disable-infobars"),
-        OsStr::new("--no-first-run"),
-        OsStr::new("--no-default-browser-check"),
-        OsStr::new("--window-size=1920,1080"),
-        OsStr::new("--start-maximized"),
-        OsStr::new("--disable-blink-features=AutomationControlled"),
-        OsStr::new("--webrtc-ip-handling-policy=default_public_interface_only"),
-        OsStr::new("--force-webrtc-ip-handling-policy"),
-        OsStr::new(
-            "--user-agent=Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.7499.110 Safari/537.36",
-        ),
-        OsStr::new("--lang=ja-JP,ja"),
-        OsStr::new("--use-angle=d3d11"),
-        OsStr::new("--enable-gpu-rasterization"),
-        OsStr::new("--enable-zero-copy"),
-        OsStr::new("--ignore-gpu-blocklist"),
-        OsStr::new("--disable-dev-shm-usage"),
-        OsStr::new("--disable-geolocation"),
-        OsStr::new("--disable-notifications"),
-        OsStr::new("--disable-popup-blocking"),
-    ];
+// off: 作らない / html: report.html / markdown: report.md
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ReportFormat {
+    Off,
+    Html,
+    Markdown,
+}
 
-    let ignore_default_args: Vec<&OsStr> = vec![OsStr::new("--enable-automation")];
+fn default_report() -> ReportFormat {
+    ReportFormat::Off
+}
 
-    let browser = Browser::new(LaunchOptions {
-        headless: false,
-        window_size: Some((1920, 1080)),
-        sandbox: false,
-        enable_gpu: true,
-        user_data_dir: Some(user_data_dir),
-        path: Some(chromium_path),
-        args,
-        ignore_default_args,
-        disable_default_args: false,
-        idle_browser_timeout: Duration::from_secs(600),
-        ..Default::default()
-    })?;
+// コンソール出力とレポートの言語
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Language {
+    Ja,
+    En,
+}
 
-    Ok(browser)
+fn default_language() -> Language {
+    Language::Ja
 }
 
-// ============================================================
-// CDP Stealth設定
-// ============================================================
-fn setup_stealth_cdp(tab: &Tab) -> Result<()> {
-    use headless_chrome::protocol::cdp::Emulation::{UserAgentBrandVersion, UserAgentMetadata};
-    use headless_chrome::protocol::cdp::Network::SetUserAgentOverride;
+// ログの形式（text: 人が読む形式 / json: 1行1イベントのJSON）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum LogFormat {
+    Text,
+    Json,
+}
 
-    tab.call_method(SetUserAgentOverride {
-        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36".to_string(),
-        accept_language: Some("ja-JP,ja;q=0.9,en-US;q=0.8,en;q=0.7".to_string()),
-        platform: Some("Win32".to_string()),
-        user_agent_metadata: Some(UserAgentMetadata {
-            platform: "Windows".to_string(),
-            platform_version: "19.0.0.0".to_string(),
-            architecture: "x86".to_string(),
-            model: "".to_string(),
-            mobile: false,
-            bitness: Some("64".to_string()),
-            wow_64: Some(false),
-            full_version_list: Some(vec![
-                UserAgentBrandVersion { brand: "Chromium".to_string(), version: "143.0.7499.41".to_string() },
-                UserAgentBrandVersion { brand: "Google Chrome".to_string(), version: "143.0.7499.41".to_string() },
-                UserAgentBrandVersion { brand: "Not/A)Brand".to_string(), version: "99.0.0.0".to_string() },
-            ]),
-            full_version: Some("143.0.7499.41".to_string()),
-            brands: Some(vec![
-                UserAgentBrandVersion { brand: "Chromium".to_string(), version: "143".to_string() },
-                UserAgentBrandVersion { brand: "Google Chrome".to_string(), version: "143".to_string() },
-                UserAgentBrandVersion { brand: "Not/A)Brand".to_string(), version: "99".to_string() },
-            ]),
-            form_factors: None,
-        }),
-    })?;
+fn default_log_format() -> LogFormat {
+    LogFormat::Text
+}
 
-    Ok(())
+// include_domains / exclude_domains に当たった結果の扱い
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum DomainFilterMode {
+    Remove,
+    Flag,
 }
 
-// ============================================================
-// JavaScript Stealth Injection（Ver 1.2 強化版）
-// ============================================================
-fn inject_stealth_scripts(tab: &Tab) -> Result<()> {
-    use headless_chrome::protocol::cdp::Page::AddScriptToEvaluateOnNewDocument;
+fn default_domain_filter_mode() -> DomainFilterMode {
+    DomainFilterMode::Remove
+}
 
-    let scripts = vec![
-        // ===== 基本Stealth =====
-        
-        // webdriver検出回避
-        r#"Object.defineProperty(navigator, 'webdriver', { get: () => undefined, configurable: true });"#,
+// ログファイルを切り替える間隔
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
 
-        // chrome オブジェクト偽装
-        r#"window.chrome = {
-            runtime: {
-                connect: function() {},
-                sendMessage: function() {},
-                onMessage: { addListener: function() {} },
-                onConnect: { addListener: function() {} },
-                PlatformOs: { MAC: 'mac', WIN: 'win', ANDROID: 'android', CROS: 'cros', LINUX: 'linux', OPENBSD: 'openbsd' },
-                PlatformArch: { ARM: 'arm', X86_32: 'x86-32', X86_64: 'x86-64', MIPS: 'mips', MIPS64: 'mips64' },
-                PlatformNaclArch: { ARM: 'arm', X86_32: 'x86-32', X86_64: 'x86-64', MIPS: 'mips', MIPS64: 'mips64' },
-                RequestUpdateCheckStatus: { THROTTLED: 'throttled', NO_UPDATE: 'no_update', UPDATE_AVAILABLE: 'update_available' },
-                OnInstalledReason: { INSTALL: 'install', UPDATE: 'update', CHROME_UPDATE: 'chrome_update', SHARED_MODULE_UPDATE: 'shared_module_update' },
-                OnRestartRequiredReason: { APP_UPDATE: 'app_update', OS_UPDATE: 'os_update', PERIODIC: 'periodic' }
-            },
-            csi: function() { return {}; },
-            loadTimes: function() { return {}; }
-        };"#,
+fn default_log_rotation() -> LogRotation {
+    LogRotation::Daily
+}
 
-        // permissions.query 偽装（通知用）
-        r#"const originalQuery = window.navigator.permissions.query;
-        window.navigator.permissions.query = (parameters) => (
-            parameters.name === 'notifications' ?
-                Promise.resolve({ state: Notification.permission }) :
-                originalQuery(parameters)
-        );"#,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LogConfig {
+    // error / warn / info / debug / trace（"gnezdot=debug" のような指定も可）
+    #[serde(default = "default_log_level")]
+    level: String,
 
-        // plugins/mimeTypes 偽装
-        r#"Object.defineProperty(navigator, 'plugins', {
-            get: () => {
-                const plugins = [
-                    { name: 'Chrome PDF Plugin', filename: 'internal-pdf-viewer', description: 'Portable Document Format' },
-                    { name: 'Chrome PDF Viewer', filename: 'mhjfbmdgcfjbbpaeojofohoefgiehjai', description: '' },
-                    { name: 'Native Client', filename: 'internal-nacl-plugin', description: '' }
-                ];
-                plugins.length = 3;
-                return plugins;
-            }
-        });
-        Object.defineProperty(navigator, 'mimeTypes', {
-            get: () => {
-                const mimeTypes = [
-                    { type: 'application/pdf', suffixes: 'pdf', description: 'Portable Document Format' },
-                    { type: 'application/x-google-chrome-pdf', suffixes: 'pdf', description: 'Portable Document Format' },
-                    { type: 'application/x-nacl', suffixes: '', description: 'Native Client Executable' },
-                    { type: 'application/x-pnacl', suffixes: '', description: 'Portable Native Client Executable' }
-                ];
-                mimeTypes.length = 4;
-                return mimeTypes;
-            }
-        });"#,
+    // コンソールとログファイルの両方に適用する
+    #[serde(default = "default_log_format")]
+    format: LogFormat,
 
-        // languages 偽装
-        r#"Object.defineProperty(navigator, 'languages', { get: () => ['ja-JP', 'ja', 'en-US', 'en'] });"#,
+    // result_dir/logs/ にログファイルを書く
+    #[serde(default = "default_true")]
+    file: bool,
 
-        // hardwareConcurrency 偽装
-        r#"Object.defineProperty(navigator, 'hardwareConcurrency', { get: () => 12 });"#,
+    #[serde(default = "default_log_rotation")]
+    rotation: LogRotation,
 
-        // deviceMemory 偽装
-        r#"Object.defineProperty(navigator, 'deviceMemory', { get: () => 8 });"#,
+    // 残すログファイルの数（古いものから削除）
+    #[serde(default = "default_log_max_files")]
+    max_files: usize,
+}
 
-        // WebGL 偽装
-        r#"const getParameterOriginal = WebGLRenderingContext.prototype.getParameter;
-        WebGLRenderingContext.prototype.getParameter = function(parameter) {
-            if (parameter === 37445) return 'Google Inc. (NVIDIA)';
-            if (parameter === 37446) return 'ANGLE (NVIDIA, NVIDIA GeForce RTX 2080 Ti Direct3D11 vs_5_0 ps_5_0, D3D11)';
-            return getParameterOriginal.call(this, parameter);
-        };
-        const getParameterOriginal2 = WebGL2RenderingContext.prototype.getParameter;
-        WebGL2RenderingContext.prototype.getParameter = function(parameter) {
-            if (parameter === 37445) return 'Google Inc. (NVIDIA)';
-            if (parameter === 37446) return 'ANGLE (NVIDIA, NVIDIA GeForce RTX 2080 Ti Direct3D11 vs_5_0 ps_5_0, D3D11)';
-            return getParameterOriginal2.call(this, parameter);
-        };"#,
+fn default_log_level() -> String {
+    "info".to_string()
+}
 
-        // Brave/Firefox 検出回避
-        r#"Object.defineProperty(navigator, 'brave', { get: () => undefined });
-        delete window.InstallTrigger;"#,
+fn default_log_max_files() -> usize {
+    14
+}
 
-        // Function.prototype.toString 偽装
-        r#"const nativeToString = Function.prototype.toString;
-        const customFunctions = new WeakSet();
-        const proxyHandler = {
-            apply: function(target, thisArg, args) {
-                if (customFunctions.has(thisArg)) return 'function () { [native code] }';
-                return nativeToString.apply(thisArg, args);
-            }
-        };
-        Function.prototype.toString = new Proxy(nativeToString, proxyHandler);
-        customFunctions.add(Function.prototype.toString);"#,
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            format: default_log_format(),
+            file: true,
+            rotation: default_log_rotation(),
+            max_files: default_log_max_files(),
+        }
+    }
+}
 
-        // ===== 位置情報ポップアップ完全ブロック（Ver 1.2 新機能） =====
+// off: 保存しない / final: 入力し終えた時点のみ / keystroke: 1文字入力するごと
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SuggestionCaptureMode {
+    Off,
+    Final,
+    Keystroke,
+}
 
-        // A. CSS強制非表示
-        r#"
-        (function() {
-            const style = document.createElement('style');
-            style.textContent = `
-                /* 位置情報ダイアログ本体 */
-                div.gTMtLb[id="lb"],
-                div[role="dialog"][aria-labelledby="lcMwfd"],
-                div.qk7LXc.JHqNkc,
-                /* update-location コンポーネント全体 */
-                update-location,
-                /* 位置情報スナックバー */
-                location-snackbar-with-learn-more,
-                /* ライトボックス背景 */
-                div.kJFf0c.KUf18 {
-                    display: none !important;
-                    visibility: hidden !important;
-                    opacity: 0 !important;
-                    pointer-events: none !important;
-                }
-            `;
-            (document.head || document.documentElement).appendChild(style);
-        })();
-        "#,
+fn default_suggestion_capture() -> SuggestionCaptureMode {
+    SuggestionCaptureMode::Off
+}
 
-        // B. Geolocation API完全無効化
-        r#"
-        (function() {
-            // Geolocation API無効化
-            if (navigator.geolocation) {
-                navigator.geolocation.getCurrentPosition = function(success, error) {
-                    if (error) error({ code: 1, message: 'User denied Geolocation' });
-                };
-                navigator.geolocation.watchPosition = function(success, error) {
-                    if (error) error({ code: 1, message: 'User denied Geolocation' });
-                    return 0;
-                };
-                navigator.geolocation.clearWatch = function() {};
-            }
-            
-            // permissions.query偽装（geolocationを常にdenied）
-            const origPermQuery = navigator.permissions.query.bind(navigator.permissions);
-            navigator.permissions.query = function(descriptor) {
-                if (descriptor.name === 'geolocation') {
-                    return Promise.resolve({ 
-                        state: 'denied',
-                        onchange: null,
-                        addEventListener: function() {},
-                        removeEventListener: function() {}
-                    });
-                }
-                return origPermQuery(descriptor);
-            };
-        })();
-        "#,
+impl Config {
+    // 位置情報を上書きするクエリが1つでもあれば、ブラウザ側で位置情報を無効化しない
+    fn uses_geolocation(&self) -> bool {
+        self.geolocation.is_some() || self.flows.iter().any(|f| f.geolocation.is_some())
+    }
 
-        // C. 精密クリック + 常時監視（強化版）
-        r#"
-        (function() {
-            const SELECTORS = {
-                dialog: 'div[role="dialog"][aria-labelledby="lcMwfd"]',
-                dialogAlt: 'div.qk7LXc.JHqNkc[role="dialog"]',
-                lightbox: 'div.gTMtLb#lb',
-                laterButton: [
-                    'g-raised-button[jsaction="click:O6N1Pb"]',
-                    'div.mpQYc g-raised-button'
-                ],
-                closeButton: 'a[aria-label="閉じる"]'
-            };
-            
-            let lastDismissTime = 0;
-            const DEBOUNCE_MS = 100;
-            
-            const dismiss = () => {
-                const now = Date.now();
-                if (now - lastDismissTime < DEBOUNCE_MS) return false;
-                lastDismissTime = now;
-                
-                const dialog = document.querySelector(SELECTORS.dialog) ||
-                               document.querySelector(SELECTORS.dialogAlt);
-                if (!dialog) return false;
-                
-                const style = window.getComputedStyle(dialog);
-                if (style.display === 'none' || 
-                    style.visibility === 'hidden' || 
-                    parseFloat(style.opacity) === 0) {
-                    return false;
-                }
-                
-                // 「後で」ボタン検索・クリック
-                for (const sel of SELECTORS.laterButton) {
-                    const btn = dialog.querySelector(sel) || document.querySelector(sel);
-                    if (btn) {
-                        btn.click();
-                        btn.dispatchEvent(new MouseEvent('click', { bubbles: true, cancelable: true }));
-                        console.log('[Gnezdo] 「後で」クリック成功');
-                        setTimeout(remove, 50);
-                        return true;
-                    }
-                }
-                
-                // 閉じるボタン（フォールバック）
-                const close = document.querySelector(SELECTORS.closeButton);
-                if (close) { 
-                    close.click(); 
-                    console.log('[Gnezdo] 「閉じる」クリック');
-                    setTimeout(remove, 50);
-                    return true; 
-                }
-                
-                // テキストベース検索（最終手段）
-                const all = dialog.querySelectorAll('div[role="button"], button, g-raised-button');
-                for (const b of all) {
-                    const text = b.innerText?.trim();
-                    if (text === '後で' || text === 'Later' || text === 'Not now') {
-                        b.click();
-                        console.log('[Gnezdo] テキストマッチでクリック:', text);
-                        setTimeout(remove, 50);
-                        return true;
-                    }
-                }
-                return false;
-            };
-            
-            const remove = () => {
-                const lb = document.querySelector(SELECTORS.lightbox);
-                if (lb) { 
-                    lb.style.display = 'none'; 
-                    lb.remove(); 
-                    console.log('[Gnezdo] ライトボックス削除');
-                }
-            };
-            
-            // ===== 監視機構（多重化） =====
-            
-            // 1. 初回実行（ページロード後）
-            setTimeout(dismiss, 300);
-            setTimeout(dismiss, 600);
-            setTimeout(dismiss, 1000);
-            
-            // 2. MutationObserver（DOM変更検知）
-            const observer = new MutationObserver((mutations) => {
-                for (const m of mutations) {
-                    if (m.type === 'childList' && m.addedNodes.length > 0) {
-                        dismiss();
-                        return;
-                    }
-                    if (m.type === 'attributes') {
-                        const t = m.target;
-                        if (t.id === 'lb' || 
-                            t.matches?.('[role="dialog"]') ||
-                            t.classList?.contains('gTMtLb')) {
-                            dismiss();
-                            return;
-                        }
-                    }
-                }
-            });
-            
-            const startObserver = () => {
-                observer.observe(document.body, {
-                    childList: true,
-                    subtree: true,
-                    attributes: true,
-                    attributeFilter: ['style', 'class', 'aria-hidden', 'hidden']
-                });
-            };
+    // 過去の実行の集計（summary/trends/smart_schedule）は結果ディレクトリの JSON を読む
+    fn writes_result_json(&self) -> bool {
+        self.sinks.contains(&SinkKind::Json) || self.sinks.contains(&SinkKind::Query)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profile_dir: default_profile_dir(),
+            profile_pool: None,
+            chromium_path: default_chromium_path(),
+            chromium_revision: None,
+            chromium_cache_dir: default_chromium_cache_dir(),
+            result_dir: default_result_dir(),
+            max_pages: default_max_pages(),
+            max_consecutive_no_next: default_max_consecutive_no_next(),
+            search_queries: default_search_queries(),
+            queries_file: None,
+            operators: SearchOperators::default(),
+            behavior: BehaviorConfig::default(),
+            warm_up: None,
+            click_through: None,
+            proxy: None,
+            proxies: Vec::new(),
+            user_agents: Vec::new(),
+            geolocation: None,
+            device: default_device(),
+            consent: default_consent(),
+            proxy_rotation: default_proxy_rotation(),
+            proxy_cooldown_secs: default_proxy_cooldown_secs(),
+            concurrency: default_concurrency(),
+            tabs: default_tabs(),
+            restart_strategy: default_restart_strategy(),
+            query_entry: default_query_entry(),
+            suggestion_capture: default_suggestion_capture(),
+            flows: Vec::new(),
+            engine: default_engine(),
+            google_domain: default_google_domain(),
+            hl: None,
+            gl: None,
+            date_range: None,
+            safe_search: None,
+            results_per_page: None,
+            selectors: SelectorsConfig::default(),
+            include_domains: Vec::new(),
+            exclude_domains: Vec::new(),
+            domain_filter_mode: default_domain_filter_mode(),
+            sinks: default_sinks(),
+            sink_options: SinkOptions::default(),
+            persona: PersonaConfig::default(),
+            seed_preferences: default_true(),
+            schedule: None,
+            collections: Vec::new(),
+            queue: None,
+            search_type: SearchType::default(),
+            captcha_solver: None,
+            stealth_check: default_consistency_check(),
+            screenshots: false,
+            stealth: StealthConfig::default(),
+            stealth_scripts_dir: None,
+            stealth_setup: default_stealth_setup(),
+            save_html: false,
+            session_replay: false,
+            perf_budget: None,
+            low_memory: false,
+            headless: false,
+            progress: false,
+            report: default_report(),
+            log: LogConfig::default(),
+            language: default_language(),
+            notify: NotifyConfig::default(),
+            webhook_url: None,
+            smart_schedule: None,
+            tracked_domains: Vec::new(),
+            hooks: HooksConfig::default(),
+        }
+    }
+}
+
+// CAPTCHA解決サービス（Config.toml の [captcha_solver]）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+enum CaptchaProvider {
+    #[serde(rename = "2captcha")]
+    TwoCaptcha,
+    #[serde(rename = "anticaptcha")]
+    AntiCaptcha,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(not(feature = "captcha-solver"), allow(dead_code))]
+struct CaptchaSolverConfig {
+    provider: CaptchaProvider,
+    api_key: String,
+
+    // 解決待ちの上限（秒）
+    #[serde(default = "default_captcha_solver_timeout_secs")]
+    timeout_secs: u64,
+}
+
+fn default_captcha_solver_timeout_secs() -> u64 {
+    180
+}
+
+// 画面を見張らずに済むよう、完了や入力待ちを知らせる
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct NotifyConfig {
+    // Windowsのトースト通知
+    #[serde(default)]
+    toast: bool,
+
+    // コンソールのベル音
+    #[serde(default)]
+    beep: bool,
+}
+
+// 1日あたりのページ予算の中で、変動の大きいクエリを優先して実行する
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SmartScheduleConfig {
+    // 直近24時間に取得してよいページ数
+    pages_per_day: usize,
+
+    // 順位が毎回入れ替わるクエリの実行間隔（時間）
+    #[serde(default = "default_smart_min_interval_hours")]
+    min_interval_hours: u64,
+
+    // 順位がまったく変わらないクエリの実行間隔（時間）
+    #[serde(default = "default_smart_max_interval_hours")]
+    max_interval_hours: u64,
+}
+
+fn default_smart_min_interval_hours() -> u64 {
+    12
+}
+fn default_smart_max_interval_hours() -> u64 {
+    168
+}
+
+// 注入するステルススクリプトのモジュール単位の有効/無効
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct StealthConfig {
+    #[serde(default = "default_true")]
+    webdriver: bool,
+
+    #[serde(default = "default_true")]
+    chrome_object: bool,
+
+    #[serde(default = "default_true")]
+    permissions: bool,
+
+    #[serde(default = "default_true")]
+    plugins: bool,
+
+    #[serde(default = "default_true")]
+    languages: bool,
+
+    #[serde(default = "default_true")]
+    hardware_concurrency: bool,
+
+    #[serde(default = "default_true")]
+    device_memory: bool,
+
+    #[serde(default = "default_true")]
+    webgl: bool,
+
+    #[serde(default = "default_true")]
+    brave: bool,
+
+    #[serde(default = "default_true")]
+    function_to_string: bool,
+
+    #[serde(default = "default_true")]
+    popup_css: bool,
+
+    #[serde(default = "default_true")]
+    popup_dismiss: bool,
+
+    #[serde(default = "default_true")]
+    geolocation_block: bool,
+
+    // canvas / AudioContext の読み出し結果にセッションごとのノイズを混ぜる
+    #[serde(default)]
+    canvas_noise: bool,
+
+    #[serde(default)]
+    audio_noise: bool,
+}
+
+impl Default for StealthConfig {
+    fn default() -> Self {
+        Self {
+            webdriver: true,
+            chrome_object: true,
+            permissions: true,
+            plugins: true,
+            languages: true,
+            hardware_concurrency: true,
+            device_memory: true,
+            webgl: true,
+            brave: true,
+            function_to_string: true,
+            popup_css: true,
+            popup_dismiss: true,
+            geolocation_block: true,
+            canvas_noise: false,
+            audio_noise: false,
+        }
+    }
+}
+
+// 外部コマンドのフック。出力先は環境変数 GNEZDO_* で渡す
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct HooksConfig {
+    // 各クエリの完了後
+    #[serde(default)]
+    after_query: Option<String>,
+
+    // 実行全体の完了後（サマリー作成後）
+    #[serde(default)]
+    after_run: Option<String>,
+
+    // これを超えたらコマンドを打ち切る（秒）
+    #[serde(default = "default_hook_timeout_secs")]
+    timeout_secs: u64,
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    300
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            after_query: None,
+            after_run: None,
+            timeout_secs: default_hook_timeout_secs(),
+        }
+    }
+}
+
+// 表示時間の予算（Config.toml の [perf_budget]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PerfBudgetConfig {
+    // 遷移開始から DOMContentLoaded までの上限（ミリ秒）
+    #[serde(default = "default_perf_budget_max_ms")]
+    max_ms: u64,
+
+    // この回数だけ連続で超過したら警告する
+    #[serde(default = "default_perf_budget_consecutive_pages")]
+    consecutive_pages: u32,
+}
+
+fn default_perf_budget_max_ms() -> u64 {
+    4000
+}
+
+fn default_perf_budget_consecutive_pages() -> u32 {
+    3
+}
+
+// best_effort: ログを出して続行 / fail_query: そのクエリを失敗扱いにしてリトライへ回す
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum StealthSetupMode {
+    BestEffort,
+    FailQuery,
+}
+
+fn default_stealth_setup() -> StealthSetupMode {
+    StealthSetupMode::BestEffort
+}
+
+// 定期実行スケジュール（Config.toml の [schedule]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ScheduleConfig {
+    // cron式（秒 分 時 日 月 曜日）。interval_minutes より優先
+    #[serde(default)]
+    cron: Option<String>,
+
+    // 前回の開始時刻からの間隔（分）
+    #[serde(default)]
+    interval_minutes: Option<u64>,
+}
+
+impl ScheduleConfig {
+    fn next_run(&self, last_start: DateTime<Local>) -> Result<DateTime<Local>> {
+        if let Some(expr) = &self.cron {
+            let schedule = cron::Schedule::from_str(expr).map_err(|e| {
+                anyhow::anyhow!(tr!(
+                    "cron式が不正です（{}）: {}",
+                    "Invalid cron expression ({}): {}",
+                    expr,
+                    e
+                ))
+            })?;
+            return schedule.after(&Local::now()).next().ok_or_else(|| {
+                anyhow::anyhow!(tr!(
+                    "cron式 {} に次回実行時刻がありません",
+                    "Cron expression {} has no next run time",
+                    expr
+                ))
+            });
+        }
+        match self.interval_minutes {
+            Some(minutes) => Ok(last_start + chrono::Duration::minutes(minutes as i64)),
+            None => anyhow::bail!(tr!(
+                "schedule に cron か interval_minutes を指定してください",
+                "Set cron or interval_minutes in schedule"
+            )),
+        }
+    }
+}
+
+// ジョブキュー（Config.toml の [queue]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QueueConfig {
+    // ジョブファイルの置き場所（pending/ running/ done/ failed/）
+    #[serde(default = "default_queue_dir")]
+    dir: String,
+
+    // 同時に動かすブラウザの数。2以上ならワーカーごとに profile_dir-N を使い、
+    // proxies が足りていればワーカー間で分け合う
+    #[serde(default = "default_queue_workers")]
+    workers: usize,
+
+    // この回数失敗したジョブは failed/ へ移す
+    #[serde(default = "default_queue_max_attempts")]
+    max_attempts: u32,
+
+    // キューが空のときに新しいジョブを探す間隔（秒）
+    #[serde(default = "default_queue_poll_secs")]
+    poll_secs: u64,
+}
+
+fn default_queue_dir() -> String {
+    "queue".to_string()
+}
+
+fn default_queue_workers() -> usize {
+    1
+}
+
+fn default_queue_max_attempts() -> u32 {
+    3
+}
+
+fn default_queue_poll_secs() -> u64 {
+    10
+}
+
+// プロファイルプール（Config.toml の [profile_pool]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ProfilePoolConfig {
+    // プロファイルの置き場所（profile-1/ profile-2/ ...）
+    #[serde(default = "default_profile_pool_dir")]
+    dir: String,
+
+    // プロファイルの数。同時に動かすブラウザの数以上にする
+    #[serde(default = "default_profile_pool_size")]
+    size: usize,
+
+    // profiles warm / refresh で巡回するサイト
+    #[serde(default = "default_warm_urls")]
+    warm_urls: Vec<String>,
+
+    // profiles refresh でこの日数より前に作ったプロファイルを作り直す
+    #[serde(default = "default_profile_max_age_days")]
+    max_age_days: u32,
+}
+
+fn default_profile_pool_dir() -> String {
+    "chromium/profiles".to_string()
+}
+
+fn default_profile_pool_size() -> usize {
+    5
+}
+
+fn default_warm_urls() -> Vec<String> {
+    [
+        "https://news.yahoo.co.jp/",
+        "https://tenki.jp/",
+        "https://www3.nhk.or.jp/news/",
+        "https://ja.wikipedia.org/wiki/Special:Random",
+    ]
+    .iter()
+    .map(|url| url.to_string())
+    .collect()
+}
+
+fn default_profile_max_age_days() -> u32 {
+    30
+}
+
+// 検索前のウォームアップ（Config.toml の [warm_up]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WarmUpConfig {
+    // 候補のサイト（ニュース・天気など）
+    #[serde(default = "default_warm_urls")]
+    urls: Vec<String>,
+
+    // セッションごとに候補からランダムに選んで見るサイトの数
+    #[serde(default = "default_warm_up_sites")]
+    sites: usize,
+}
+
+fn default_warm_up_sites() -> usize {
+    2
+}
+
+// 検索結果のクリックと滞在（Config.toml の [click_through]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ClickThroughConfig {
+    // 1ページで開く結果の数（ページの結果からランダムに選ぶ）
+    #[serde(default = "default_clicks_per_page")]
+    clicks_per_page: usize,
+
+    // 開いたページに滞在する秒数（この範囲でランダム）
+    #[serde(default = "default_dwell_min_secs")]
+    dwell_min_secs: u64,
+
+    #[serde(default = "default_dwell_max_secs")]
+    dwell_max_secs: u64,
+
+    // 開いたページの HTML をクエリディレクトリの landing/<ページ>-<順位>.html に保存する
+    #[serde(default)]
+    save_html: bool,
+}
+
+fn default_clicks_per_page() -> usize {
+    1
+}
+
+fn default_dwell_min_secs() -> u64 {
+    10
+}
+
+fn default_dwell_max_secs() -> u64 {
+    40
+}
+
+// 名前付きのクエリ集（Config.toml の [[collections]]）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CollectionConfig {
+    // 出力先 result_dir/<name> の名前にもなる
+    name: String,
+
+    #[serde(default)]
+    search_queries: Vec<SearchQuery>,
+
+    #[serde(default)]
+    flows: Vec<QueryFlow>,
+
+    schedule: ScheduleConfig,
+
+    // 未指定なら全体設定を使う
+    #[serde(default)]
+    engine: Option<EngineKind>,
+
+    #[serde(default)]
+    locale: Option<String>,
+}
+
+impl CollectionConfig {
+    // 全体設定をこのクエリ集向けに上書きしたもの
+    fn apply(&self, base: &Config) -> Config {
+        let mut config = base.clone();
+        config.search_queries = self.search_queries.clone();
+        config.flows = self.flows.clone();
+        config.schedule = Some(self.schedule.clone());
+        config.collections = Vec::new();
+        config.result_dir = Path::new(&base.result_dir)
+            .join(&self.name)
+            .to_string_lossy()
+            .to_string();
+        if let Some(engine) = self.engine {
+            config.engine = engine;
+        }
+        // 言語設定とあわせて検索結果の言語・地域も変える（明示した hl/gl が優先）
+        if let Some(locale) = &self.locale {
+            config.persona.locale = locale.clone();
+            let (hl, gl) = locale_hl_gl(locale);
+            config.hl.get_or_insert(hl);
+            if config.gl.is_none() {
+                config.gl = gl;
+            }
+        }
+        config
+    }
+}
+
+// ============================================================
+// 行動ペルソナ設定（Config.toml の [behavior]）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BehaviorConfig {
+    // 検索ボックスへクリックではなくTabキーでフォーカスする確率（%）
+    #[serde(default = "default_keyboard_focus_rate")]
+    keyboard_focus_rate: u32,
+
+    // 入力後にサジェストを矢印キーで巡回する確率（%）
+    #[serde(default = "default_keyboard_suggest_rate")]
+    keyboard_suggest_rate: u32,
+
+    // スクロール1ステップを scrollBy ではなく PageDown で行う確率（%）
+    #[serde(default = "default_keyboard_scroll_rate")]
+    keyboard_scroll_rate: u32,
+
+    // 英数字1文字ごとに隣のキーを打ち間違えて Backspace で直す確率（%）。既定は0（打ち間違えない）
+    #[serde(default)]
+    typo_rate: u32,
+
+    // 日本語などの非ASCII文字を insertText ではなくIMEの変換入力（composition）で入れる
+    #[serde(default)]
+    ime_input: bool,
+
+    // タイミングプロファイル名（fast / normal / cautious か timing_profiles に定義した名前）
+    #[serde(default = "default_timing")]
+    timing: String,
+
+    // 独自のタイミングプロファイル（[behavior.timing_profiles.<名前>]、省略した項目は normal の値）
+    #[serde(default)]
+    timing_profiles: BTreeMap<String, TimingProfile>,
+}
+
+fn default_keyboard_focus_rate() -> u32 {
+    20
+}
+fn default_keyboard_suggest_rate() -> u32 {
+    15
+}
+fn default_keyboard_scroll_rate() -> u32 {
+    10
+}
+fn default_timing() -> String {
+    "normal".to_string()
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            keyboard_focus_rate: default_keyboard_focus_rate(),
+            keyboard_suggest_rate: default_keyboard_suggest_rate(),
+            keyboard_scroll_rate: default_keyboard_scroll_rate(),
+            typo_rate: 0,
+            ime_input: false,
+            timing: default_timing(),
+            timing_profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl BehaviorConfig {
+    // 選択中のタイミングプロファイル（独自定義が組み込みより優先）
+    fn timing(&self) -> Result<TimingProfile> {
+        if let Some(profile) = self.timing_profiles.get(&self.timing) {
+            return Ok(profile.clone());
+        }
+        match self.timing.as_str() {
+            "fast" => Ok(TimingProfile::fast()),
+            "normal" => Ok(TimingProfile::default()),
+            "cautious" => Ok(TimingProfile::cautious()),
+            other => anyhow::bail!(tr!(
+                "behavior.timing {:?} は fast / normal / cautious か timing_profiles に定義した名前にしてください",
+                "behavior.timing {:?} must be fast / normal / cautious or a name defined in timing_profiles",
+                other
+            )),
+        }
+    }
+}
+
+// 操作の速さと間の取り方。範囲は [最小, 最大] でこの間からランダムに選ぶ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct TimingProfile {
+    // 1文字打つごとの待ち（ミリ秒）
+    type_delay_ms: [u64; 2],
+
+    // 数文字ずつ打つまとまりの間の待ち（ミリ秒）
+    burst_pause_ms: [u64; 2],
+
+    // スクロール1ステップの量（px）と、その後の待ち（ミリ秒）
+    scroll_step_px: [u64; 2],
+    scroll_delay_ms: [u64; 2],
+
+    // スクロール1ステップごとに短く止まる確率（%）と長さ
+    short_pause_rate: u32,
+    short_pause_ms: [u64; 2],
+
+    // スクロール1ステップごとに読み込むように長く止まる確率（%）と長さ
+    long_pause_rate: u32,
+    long_pause_ms: [u64; 2],
+
+    // 少し上に戻る確率（%）と、戻った後の待ち
+    scroll_back_rate: u32,
+    scroll_back_pause_ms: [u64; 2],
+
+    // クエリとクエリの間の休憩（ミリ秒）
+    query_rest_ms: [u64; 2],
+}
+
+impl Default for TimingProfile {
+    fn default() -> Self {
+        Self {
+            type_delay_ms: [60, 180],
+            burst_pause_ms: [250, 700],
+            scroll_step_px: [250, 325],
+            scroll_delay_ms: [10, 25],
+            short_pause_rate: 30,
+            short_pause_ms: [240, 720],
+            long_pause_rate: 20,
+            long_pause_ms: [1200, 2400],
+            scroll_back_rate: 10,
+            scroll_back_pause_ms: [360, 960],
+            query_rest_ms: [3600, 7200],
+        }
+    }
+}
+
+impl TimingProfile {
+    fn fast() -> Self {
+        Self {
+            type_delay_ms: [30, 90],
+            burst_pause_ms: [100, 300],
+            scroll_step_px: [350, 450],
+            scroll_delay_ms: [5, 10],
+            short_pause_rate: 15,
+            short_pause_ms: [120, 360],
+            long_pause_rate: 5,
+            long_pause_ms: [600, 1200],
+            scroll_back_rate: 5,
+            scroll_back_pause_ms: [200, 500],
+            query_rest_ms: [1000, 2500],
+        }
+    }
+
+    fn cautious() -> Self {
+        Self {
+            type_delay_ms: [100, 320],
+            burst_pause_ms: [400, 1200],
+            scroll_step_px: [180, 260],
+            scroll_delay_ms: [25, 45],
+            short_pause_rate: 40,
+            short_pause_ms: [400, 1200],
+            long_pause_rate: 30,
+            long_pause_ms: [2000, 5000],
+            scroll_back_rate: 15,
+            scroll_back_pause_ms: [500, 1500],
+            query_rest_ms: [15000, 45000],
+        }
+    }
+}
+
+// [最小, 最大] の範囲からランダムに選ぶ（逆順に書かれていても最小値を使う）
+fn random_in(rng: &mut WyRand, range: [u64; 2]) -> u64 {
+    rng.generate_range(range[0]..=range[1].max(range[0]))
+}
+
+// 範囲を3等分した part 番目（0〜2）
+fn range_third(range: [u64; 2], part: u64) -> [u64; 2] {
+    let span = range[1].saturating_sub(range[0]);
+    [range[0] + span * part / 3, range[0] + span * (part + 1) / 3]
+}
+
+// ============================================================
+// フィンガープリント・ペルソナ（Config.toml の [persona]）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PersonaConfig {
+    // ブラウザが名乗る言語（Accept-Language / navigator.languages / --lang の先頭）
+    #[serde(default = "default_persona_locale")]
+    locale: String,
+
+    // IANAタイムゾーン名。CDPでブラウザのタイムゾーンを上書きする
+    #[serde(default = "default_persona_timezone")]
+    timezone: String,
+
+    // UA-CHのplatform値（Windows / macOS / Linux）
+    #[serde(default = "default_persona_platform")]
+    platform: String,
+
+    #[serde(default = "default_consistency_check")]
+    consistency_check: ConsistencyCheckMode,
+}
+
+// 整合性チェックで不一致があった場合の扱い
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ConsistencyCheckMode {
+    Off,
+    Warn,
+    Strict,
+}
+
+fn default_persona_locale() -> String {
+    "ja-JP".to_string()
+}
+fn default_persona_timezone() -> String {
+    "Asia/Tokyo".to_string()
+}
+fn default_persona_platform() -> String {
+    "Windows".to_string()
+}
+fn default_consistency_check() -> ConsistencyCheckMode {
+    ConsistencyCheckMode::Warn
+}
+
+impl Default for PersonaConfig {
+    fn default() -> Self {
+        Self {
+            locale: default_persona_locale(),
+            timezone: default_persona_timezone(),
+            platform: default_persona_platform(),
+            consistency_check: default_consistency_check(),
+        }
+    }
+}
+
+// ============================================================
+// 絞り込みフロー（Config.toml の [[flows]]）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QueryFlow {
+    query: String,
+
+    #[serde(default)]
+    steps: Vec<FlowStep>,
+
+    // 未指定なら全体の search_type
+    #[serde(default)]
+    search_type: Option<SearchType>,
+
+    // 未指定なら全体の geolocation（クエリごとに位置を変えたい場合はフローで指定する）
+    #[serde(default)]
+    geolocation: Option<GeolocationConfig>,
+}
+
+// 位置情報の上書き（Config.toml の [geolocation] / フローの geolocation）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct GeolocationConfig {
+    latitude: f64,
+    longitude: f64,
+    // 精度（メートル）
+    #[serde(default = "default_geolocation_accuracy")]
+    accuracy: f64,
+}
+
+fn default_geolocation_accuracy() -> f64 {
+    50.0
+}
+
+// 検索対象の種類（Googleの tbm パラメータに相当）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+enum SearchType {
+    #[default]
+    Web,
+    News,
+    Images,
+    Videos,
+}
+
+impl SearchType {
+    fn is_web(&self) -> bool {
+        *self == SearchType::Web
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            SearchType::Web => "web",
+            SearchType::News => "news",
+            SearchType::Images => "images",
+            SearchType::Videos => "videos",
+        }
+    }
+}
+
+// 同一セッション内で行う絞り込み操作
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum FlowStep {
+    // 検索ボックス末尾に語を追加して再検索
+    AddTerms { terms: String },
+    // 表示テキストが一致するフィルタタブ（ニュース、画像など）をクリック
+    ClickTab { label: String },
+}
+
+impl FlowStep {
+    fn describe(&self) -> String {
+        match self {
+            FlowStep::AddTerms { terms } => format!("add_terms:{}", terms),
+            FlowStep::ClickTab { label } => format!("click_tab:{}", label),
+        }
+    }
+}
+
+// search_queries の要素
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum SearchQuery {
+    Plain(String),
+    Overridden(Box<QueryOverrides>),
+}
+
+impl SearchQuery {
+    fn query(&self) -> &str {
+        match self {
+            SearchQuery::Plain(query) => query,
+            SearchQuery::Overridden(overrides) => &overrides.query,
+        }
+    }
+}
+
+// queries_file を読む。.csv は見出し行の列名を QueryOverrides の項目として扱う
+fn load_queries_file(path: &Path) -> Result<Vec<SearchQuery>> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+    {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+        let mut queries = Vec::new();
+        for (i, row) in reader.deserialize::<QueryOverrides>().enumerate() {
+            let overrides =
+                row.map_err(|e| anyhow::anyhow!(tr!("{} 行目: {}", "line {}: {}", i + 2, e)))?;
+            if overrides.query.is_empty() {
+                continue;
+            }
+            // 上書き項目が空の行は文字列のクエリと同じ扱いにする
+            queries.push(if overrides.has_overrides() {
+                SearchQuery::Overridden(Box::new(overrides))
+            } else {
+                SearchQuery::Plain(overrides.query)
+            });
+        }
+        return Ok(queries);
+    }
+    // 空行と # で始まる行は読み飛ばす
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| SearchQuery::Plain(line.to_string()))
+        .collect())
+}
+
+// このクエリだけ全体設定を上書きする項目（未指定なら全体設定を使う）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QueryOverrides {
+    query: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_pages: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    engine: Option<EngineKind>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    search_type: Option<SearchType>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    google_domain: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hl: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gl: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    results_per_page: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_range: Option<DateRange>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    safe_search: Option<SafeSearch>,
+
+    // 指定すると [operators] の代わりに使う（CSV では指定できない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    operators: Option<SearchOperators>,
+}
+
+impl QueryOverrides {
+    fn has_overrides(&self) -> bool {
+        self.max_pages.is_some()
+            || self.engine.is_some()
+            || self.search_type.is_some()
+            || self.google_domain.is_some()
+            || self.hl.is_some()
+            || self.gl.is_some()
+            || self.results_per_page.is_some()
+            || self.date_range.is_some()
+            || self.safe_search.is_some()
+            || self.operators.is_some()
+    }
+
+    fn apply(&self, base: &Config) -> Config {
+        let mut config = base.clone();
+        if let Some(max_pages) = self.max_pages {
+            config.max_pages = max_pages;
+        }
+        if let Some(engine) = self.engine {
+            config.engine = engine;
+        }
+        if let Some(search_type) = self.search_type {
+            config.search_type = search_type;
+        }
+        if let Some(domain) = &self.google_domain {
+            config.google_domain = domain.clone();
+        }
+        if self.hl.is_some() {
+            config.hl = self.hl.clone();
+        }
+        if self.gl.is_some() {
+            config.gl = self.gl.clone();
+        }
+        if self.results_per_page.is_some() {
+            config.results_per_page = self.results_per_page;
+        }
+        if self.date_range.is_some() {
+            config.date_range = self.date_range.clone();
+        }
+        if self.safe_search.is_some() {
+            config.safe_search = self.safe_search;
+        }
+        if let Some(operators) = &self.operators {
+            config.operators = operators.clone();
+        }
+        config
+    }
+
+    // 結果が変わる上書き（エンジン・地域・言語）を「bing, gl=us」のように並べる。
+    // 同じ検索語を市場違いで並べてもチェックポイントと出力先が衝突しないようにする
+    fn variant(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(engine) = self.engine {
+            parts.push(format!("{:?}", engine).to_lowercase());
+        }
+        if let Some(domain) = &self.google_domain {
+            parts.push(domain.clone());
+        }
+        if let Some(hl) = &self.hl {
+            parts.push(format!("hl={}", hl));
+        }
+        if let Some(gl) = &self.gl {
+            parts.push(format!("gl={}", gl));
+        }
+        if let Some(range) = &self.date_range {
+            parts.push(range.label());
+        }
+        if let Some(safe) = self.safe_search {
+            parts.push(format!("safe={:?}", safe).to_lowercase());
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+// セーフサーチ。Google では safe パラメータになる
+// Google には中間レベルを指定するパラメータが無いので、moderate は safe を送らず既定に任せる
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SafeSearch {
+    Off,
+    Moderate,
+    Strict,
+}
+
+impl SafeSearch {
+    fn google_param(self) -> Option<&'static str> {
+        match self {
+            SafeSearch::Off => Some("off"),
+            SafeSearch::Moderate => None,
+            SafeSearch::Strict => Some("active"),
+        }
+    }
+}
+
+// 期間の絞り込み。Google では tbs パラメータになる
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum DateRange {
+    Preset(DatePreset),
+    // YYYY-MM-DD。片方だけでもよい
+    Custom {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        to: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+enum DatePreset {
+    #[serde(rename = "past_hour")]
+    PastHour,
+    #[serde(rename = "past_24h")]
+    Past24h,
+    #[serde(rename = "past_week")]
+    PastWeek,
+    #[serde(rename = "past_month")]
+    PastMonth,
+    #[serde(rename = "past_year")]
+    PastYear,
+}
+
+impl DateRange {
+    fn tbs(&self) -> Result<String> {
+        let (from, to) = match self {
+            DateRange::Preset(preset) => {
+                let qdr = match preset {
+                    DatePreset::PastHour => "h",
+                    DatePreset::Past24h => "d",
+                    DatePreset::PastWeek => "w",
+                    DatePreset::PastMonth => "m",
+                    DatePreset::PastYear => "y",
+                };
+                return Ok(format!("qdr:{}", qdr));
+            }
+            DateRange::Custom { from, to } => (parse_range_date(from)?, parse_range_date(to)?),
+        };
+        if let (Some(from), Some(to)) = (from, to)
+            && from > to
+        {
+            anyhow::bail!(tr!(
+                "date_range の from が to より後です: {} > {}",
+                "date_range from is after to: {} > {}",
+                from,
+                to
+            ));
+        }
+        if from.is_none() && to.is_none() {
+            anyhow::bail!(tr!(
+                "date_range には from か to を指定してください",
+                "Set from or to in date_range"
+            ));
+        }
+        // Google は月/日/年で受け取る
+        let mut tbs = "cdr:1".to_string();
+        if let Some(from) = from {
+            tbs.push_str(&from.format(",cd_min:%-m/%-d/%Y").to_string());
+        }
+        if let Some(to) = to {
+            tbs.push_str(&to.format(",cd_max:%-m/%-d/%Y").to_string());
+        }
+        Ok(tbs)
+    }
+
+    // チェックポイント・出力先の区別用
+    fn label(&self) -> String {
+        match self {
+            DateRange::Preset(preset) => serde_json::to_value(preset)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default(),
+            DateRange::Custom { from, to } => format!(
+                "{}~{}",
+                from.as_deref().unwrap_or(""),
+                to.as_deref().unwrap_or("")
+            ),
+        }
+    }
+}
+
+fn parse_range_date(date: &Option<String>) -> Result<Option<NaiveDate>> {
+    date.as_deref()
+        .map(|d| {
+            NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|e| {
+                anyhow::anyhow!(tr!(
+                    "date_range の日付が不正です（{}）: {}",
+                    "Invalid date_range date ({}): {}",
+                    d,
+                    e
+                ))
+            })
+        })
+        .transpose()
+}
+
+// 検索語の後ろに付ける演算子。引用符や - はここで付ける
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct SearchOperators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    site: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    intitle: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filetype: Option<String>,
+
+    // 完全一致で含める語句
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exact_phrases: Vec<String>,
+
+    // 除外する語句
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude_terms: Vec<String>,
+}
+
+impl SearchOperators {
+    fn is_empty(&self) -> bool {
+        self.site.is_none()
+            && self.intitle.is_none()
+            && self.filetype.is_none()
+            && self.exact_phrases.is_empty()
+            && self.exclude_terms.is_empty()
+    }
+
+    // 「rust async "tokio runtime" site:example.com -java」のように組み立てる
+    fn apply(&self, query: &str) -> String {
+        let mut parts = vec![query.trim().to_string()];
+        for phrase in &self.exact_phrases {
+            parts.push(format!("\"{}\"", phrase.replace('"', "").trim()));
+        }
+        if let Some(site) = &self.site {
+            parts.push(format!("site:{}", site.trim()));
+        }
+        if let Some(intitle) = &self.intitle {
+            parts.push(format!("intitle:{}", quote_operand(intitle)));
+        }
+        if let Some(filetype) = &self.filetype {
+            parts.push(format!(
+                "filetype:{}",
+                filetype.trim().trim_start_matches('.')
+            ));
+        }
+        for term in &self.exclude_terms {
+            parts.push(format!("-{}", quote_operand(term)));
+        }
+        parts.retain(|part| !part.is_empty());
+        parts.join(" ")
+    }
+}
+
+// 空白を含む語句だけ引用符で囲む
+fn quote_operand(text: &str) -> String {
+    let text = text.replace('"', "");
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        format!("\"{}\"", text)
+    } else {
+        text.to_string()
+    }
+}
+
+// 実行単位（通常クエリは steps が空）
+struct QueryJob {
+    query: String,
+    steps: Vec<FlowStep>,
+    search_type: SearchType,
+    geolocation: Option<GeolocationConfig>,
+    // search_queries の表で上書きされた設定と、その区別用の表記
+    config: Option<Box<Config>>,
+    variant: Option<String>,
+}
+
+impl QueryJob {
+    // チェックポイント上の識別子（同じ検索語のフローは別ジョブとして扱う）
+    fn key(&self) -> String {
+        let mut key = self.query.clone();
+        if let Some(variant) = &self.variant {
+            key.push_str(&format!(" ({})", variant));
+        }
+        if !self.search_type.is_web() {
+            key.push_str(&format!(" [{}]", self.search_type.label()));
+        }
+        for step in &self.steps {
+            key.push_str(" > ");
+            key.push_str(&step.describe());
+        }
+        key
+    }
+
+    // 同じ検索語のウェブ検索と結果が混ざらないよう、種類ごとに別ディレクトリにする
+    fn dir_name(&self) -> String {
+        let mut labels: Vec<&str> = self.variant.iter().map(String::as_str).collect();
+        if !self.search_type.is_web() {
+            labels.push(self.search_type.label());
+        }
+        if labels.is_empty() {
+            self.query.clone()
+        } else {
+            format!("{} ({})", self.query, labels.join(", "))
+        }
+    }
+}
+
+fn build_query_jobs(config: &Config) -> Vec<QueryJob> {
+    let plain = config.search_queries.iter().map(|entry| match entry {
+        SearchQuery::Plain(query) => QueryJob {
+            query: config.operators.apply(query),
+            steps: Vec::new(),
+            search_type: config.search_type,
+            geolocation: config.geolocation,
+            config: None,
+            variant: None,
+        },
+        SearchQuery::Overridden(overrides) => {
+            let job_config = overrides.apply(config);
+            QueryJob {
+                query: job_config.operators.apply(&overrides.query),
+                steps: Vec::new(),
+                search_type: job_config.search_type,
+                geolocation: config.geolocation,
+                config: Some(Box::new(job_config)),
+                variant: overrides.variant(),
+            }
+        }
+    });
+    let flows = config.flows.iter().map(|f| QueryJob {
+        query: config.operators.apply(&f.query),
+        steps: f.steps.clone(),
+        search_type: f.search_type.unwrap_or(config.search_type),
+        geolocation: f.geolocation.or(config.geolocation),
+        config: None,
+        variant: None,
+    });
+    plain.chain(flows).collect()
+}
+
+// ============================================================
+// プロキシ設定（Config.toml の [proxy]）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ProxyScheme {
+    Http,
+    Https,
+    Socks5,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ProxyConfig {
+    #[serde(default = "default_proxy_scheme")]
+    scheme: ProxyScheme,
+
+    host: String,
+
+    port: u16,
+
+    #[serde(default)]
+    username: Option<String>,
+
+    #[serde(default)]
+    password: Option<String>,
+}
+
+fn default_proxy_scheme() -> ProxyScheme {
+    ProxyScheme::Http
+}
+fn default_proxy_rotation() -> ProxyRotation {
+    ProxyRotation::RoundRobin
+}
+fn default_proxy_cooldown_secs() -> u64 {
+    600
+}
+fn default_concurrency() -> usize {
+    1
+}
+fn default_tabs() -> usize {
+    1
+}
+
+// clear は再起動より速いが、User-Agent とプロキシは次に起動し直すまで変わらない。
+// CAPTCHA やエラーでプロキシを失敗扱いにした後は clear でも起動し直す
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum RestartStrategy {
+    Relaunch,
+    Clear,
+}
+
+fn default_restart_strategy() -> RestartStrategy {
+    RestartStrategy::Relaunch
+}
+
+// プロキシプールの選択方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ProxyRotation {
+    RoundRobin,
+    Random,
+}
+
+impl ProxyConfig {
+    fn server_arg(&self) -> String {
+        let scheme = match self.scheme {
+            ProxyScheme::Http => "http",
+            ProxyScheme::Https => "https",
+            ProxyScheme::Socks5 => "socks5",
+        };
+        format!("{}://{}:{}", scheme, self.host, self.port)
+    }
+
+    fn has_auth(&self) -> bool {
+        self.username.is_some() || self.password.is_some()
+    }
+}
+
+// ============================================================
+// プロキシプール（[proxy] + [[proxies]]、失敗時はクールダウン）
+// ============================================================
+struct ProxyPool {
+    entries: Vec<ProxyConfig>,
+    failed_until: Vec<Option<Instant>>,
+    rotation: ProxyRotation,
+    cooldown: Duration,
+    cursor: usize,
+    rng: WyRand,
+}
+
+impl ProxyPool {
+    fn from_config(config: &Config) -> Self {
+        let entries: Vec<ProxyConfig> = config
+            .proxy
+            .iter()
+            .chain(config.proxies.iter())
+            .cloned()
+            .collect();
+        Self {
+            failed_until: vec![None; entries.len()],
+            entries,
+            rotation: config.proxy_rotation,
+            cooldown: Duration::from_secs(config.proxy_cooldown_secs),
+            cursor: 0,
+            rng: WyRand::new(),
+        }
+    }
+
+    fn is_available(&self, index: usize, now: Instant) -> bool {
+        self.failed_until[index].is_none_or(|until| until <= now)
+    }
+
+    // 次に使うプロキシのインデックス。全てクールダウン中なら解除が最も早いものを使う
+    fn next(&mut self) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        let len = self.entries.len();
+        let available: Vec<usize> = (0..len).filter(|&i| self.is_available(i, now)).collect();
+
+        let chosen = if available.is_empty() {
+            warn!(
+                "{}",
+                tr!(
+                    "警告: 全プロキシがクールダウン中。解除が最も早いものを使用。",
+                    "Warning: all proxies are cooling down. Using the one that recovers first."
+                )
+            );
+            (0..len).min_by_key(|&i| self.failed_until[i]).unwrap()
+        } else {
+            match self.rotation {
+                ProxyRotation::RoundRobin => (0..len)
+                    .map(|offset| (self.cursor + offset) % len)
+                    .find(|i| available.contains(i))
+                    .unwrap(),
+                ProxyRotation::Random => available[self.rng.generate_range(0..available.len())],
+            }
+        };
+
+        self.cursor = (chosen + 1) % len;
+        Some(chosen)
+    }
+
+    fn mark_failed(&mut self, index: usize) {
+        self.failed_until[index] = Some(Instant::now() + self.cooldown);
+        warn!(
+            "{}",
+            tr!(
+                "プロキシ {} を失敗としてマーク（{}秒クールダウン）",
+                "Marked proxy {} as failed ({}s cooldown)",
+                self.entries[index].server_arg(),
+                self.cooldown.as_secs()
+            )
+        );
+    }
+}
+
+// ============================================================
+// コマンドライン引数
+// ============================================================
+#[derive(Debug, Default)]
+struct CliArgs {
+    // 中断した実行ディレクトリを指定して続きから再開する
+    resume: Option<PathBuf>,
+    // schedule に従って繰り返し実行する
+    daemon: bool,
+    // 実行後にこの実行ディレクトリと比較して diff.json を書く
+    diff: Option<PathBuf>,
+    // サブコマンド（無ければ通常の検索実行）
+    command: Option<Command>,
+}
+
+#[derive(Debug)]
+enum Command {
+    // 保存済みHTMLから結果JSONを作り直す
+    Reparse(PathBuf),
+    // HTML（ファイルか標準入力）を解析して結果JSONを標準出力へ書く
+    Parse {
+        input: Option<PathBuf>,
+        search_type: SearchType,
+    },
+    // 保存済みHTML（ファイルかディレクトリ）を解析し、<名前>.expected.json と突き合わせる
+    ParseFile {
+        inputs: Vec<PathBuf>,
+        // 突き合わせずに今の解析結果を期待値として書き込む
+        bless: bool,
+    },
+    // 不具合報告用に設定・ログ・直近の実行結果を zip にまとめる
+    SupportBundle {
+        output: Option<PathBuf>,
+    },
+    // ジョブキューにクエリを追加する（--daemon 実行中でもよい）
+    Enqueue(Vec<String>),
+    // プロファイルプールを作る・温める・作り直す
+    Profiles(ProfilesAction),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ProfilesAction {
+    // 足りないプロファイルを作る
+    Create,
+    // 全プロファイルで warm_urls を巡回する
+    Warm,
+    // max_age_days を過ぎたものを作り直し、未巡回のものと合わせて巡回する
+    Refresh,
+}
+
+fn parse_args() -> Result<CliArgs> {
+    let mut cli = CliArgs::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--resume" => {
+                let Some(dir) = args.next() else {
+                    anyhow::bail!(tr!(
+                        "--resume には実行ディレクトリを指定してください",
+                        "--resume needs a run directory"
+                    ));
+                };
+                cli.resume = Some(PathBuf::from(dir));
+            }
+            "--daemon" => cli.daemon = true,
+            "--diff" => {
+                let Some(dir) = args.next() else {
+                    anyhow::bail!(tr!(
+                        "--diff には比較する実行ディレクトリを指定してください",
+                        "--diff needs a run directory to compare with"
+                    ));
+                };
+                cli.diff = Some(PathBuf::from(dir));
+            }
+            "reparse" => {
+                let Some(dir) = args.next() else {
+                    anyhow::bail!(tr!(
+                        "reparse には実行ディレクトリかクエリディレクトリを指定してください",
+                        "reparse needs a run directory or a query directory"
+                    ));
+                };
+                cli.command = Some(Command::Reparse(PathBuf::from(dir)));
+            }
+            "parse" => {
+                cli.command = Some(Command::Parse {
+                    input: None,
+                    search_type: SearchType::Web,
+                });
+            }
+            "parse-file" => {
+                cli.command = Some(Command::ParseFile {
+                    inputs: Vec::new(),
+                    bless: false,
+                });
+            }
+            "--bless" if matches!(cli.command, Some(Command::ParseFile { .. })) => {
+                if let Some(Command::ParseFile { bless, .. }) = &mut cli.command {
+                    *bless = true;
+                }
+            }
+            "support-bundle" => {
+                cli.command = Some(Command::SupportBundle { output: None });
+            }
+            "enqueue" => {
+                cli.command = Some(Command::Enqueue(Vec::new()));
+            }
+            "profiles" => {
+                let action = match args.next().as_deref() {
+                    Some("create") => ProfilesAction::Create,
+                    Some("warm") => ProfilesAction::Warm,
+                    Some("refresh") => ProfilesAction::Refresh,
+                    _ => anyhow::bail!(tr!(
+                        "profiles には create / warm / refresh を指定してください",
+                        "profiles needs create / warm / refresh"
+                    )),
+                };
+                cli.command = Some(Command::Profiles(action));
+            }
+            "--search-type" => {
+                let Some(Command::Parse { search_type, .. }) = &mut cli.command else {
+                    anyhow::bail!(tr!(
+                        "--search-type は parse の後に指定してください",
+                        "--search-type must come after parse"
+                    ));
+                };
+                let Some(value) = args.next() else {
+                    anyhow::bail!(tr!(
+                        "--search-type には web / news / images / videos を指定してください",
+                        "--search-type needs web / news / images / videos"
+                    ));
+                };
+                *search_type = serde_json::from_value(serde_json::Value::String(value.clone()))
+                    .map_err(|_| {
+                        anyhow::anyhow!(tr!(
+                            "不明な search_type: {}",
+                            "Unknown search_type: {}",
+                            value
+                        ))
+                    })?;
+            }
+            other
+                if !other.starts_with("--")
+                    && matches!(cli.command, Some(Command::SupportBundle { output: None })) =>
+            {
+                cli.command = Some(Command::SupportBundle {
+                    output: Some(PathBuf::from(other)),
+                });
+            }
+            other
+                if !other.starts_with("--") && matches!(cli.command, Some(Command::Enqueue(_))) =>
+            {
+                if let Some(Command::Enqueue(queries)) = &mut cli.command {
+                    queries.push(other.to_string());
+                }
+            }
+            other
+                if !other.starts_with("--")
+                    && matches!(cli.command, Some(Command::Parse { input: None, .. })) =>
+            {
+                if let Some(Command::Parse { input, .. }) = &mut cli.command {
+                    *input = Some(PathBuf::from(other));
+                }
+            }
+            other
+                if !other.starts_with("--")
+                    && matches!(cli.command, Some(Command::ParseFile { .. })) =>
+            {
+                if let Some(Command::ParseFile { inputs, .. }) = &mut cli.command {
+                    inputs.push(PathBuf::from(other));
+                }
+            }
+            other => anyhow::bail!(tr!("不明な引数: {}", "Unknown argument: {}", other)),
+        }
+    }
+    Ok(cli)
+}
+
+// 標準出力を汚さないよう何も表示せずに読む（parse 用）
+// ファイルが無ければ既定値、壊れていれば既定値で黙って続けずにエラーで終える
+fn load_config_quiet() -> Result<Config> {
+    let config_path = get_base_path("Config.toml");
+    let Ok(content) = fs::read_to_string(&config_path) else {
+        return Ok(Config::default());
+    };
+    toml::from_str(&content).map_err(|e| {
+        anyhow::anyhow!(tr!(
+            "設定ファイルの解析に失敗しました: {:?}\n{}",
+            "Failed to parse config file: {:?}\n{}",
+            config_path,
+            e
+        ))
+    })
+}
+
+// ログの初期化に設定が要るので、結果の表示は呼び出し側で行う（読めたら true）
+fn load_config() -> (Config, bool) {
+    let config_path = get_base_path("Config.toml");
+    if let Ok(content) = fs::read_to_string(&config_path)
+        && let Ok(cfg) = toml::from_str(&content)
+    {
+        return (cfg, true);
+    }
+    (Config::default(), false)
+}
+
+// コンソールと result_dir/logs/ へのログ出力を設定する
+// 戻り値のガードを落とすとログファイルへの書き込みが止まるので main で保持する
+fn init_logging(config: &Config) -> Result<Option<tracing_appender::non_blocking::WorkerGuard>> {
+    use tracing_appender::rolling::{Builder, Rotation};
+    use tracing_subscriber::filter::LevelFilter;
+    use tracing_subscriber::fmt::time::ChronoLocal;
+    use tracing_subscriber::{
+        EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt,
+    };
+
+    let log = &config.log;
+    let filter = EnvFilter::try_new(&log.level).map_err(|e| {
+        anyhow::anyhow!(tr!(
+            "log.level が不正です（{}）: {}",
+            "Invalid log.level ({}): {}",
+            log.level,
+            e
+        ))
+    })?;
+    let json = log.format == LogFormat::Json;
+
+    let console = fmt::layer().with_timer(ChronoLocal::new("%H:%M:%S".to_string()));
+    let console = match PROGRESS.get() {
+        // 進捗表示中は警告以上だけをバーの上に出す
+        Some(multi) => {
+            let multi = multi.clone();
+            let console = console.with_writer(move || ProgressWriter(multi.clone()));
+            if json {
+                console.json().with_filter(LevelFilter::WARN).boxed()
+            } else {
+                console
+                    .with_target(false)
+                    .with_filter(LevelFilter::WARN)
+                    .boxed()
+            }
+        }
+        None if json => console.json().boxed(),
+        None => console.with_target(false).boxed(),
+    };
+
+    let mut guard = None;
+    let file = if log.file {
+        let rotation = match log.rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        };
+        let dir = get_base_path(&config.result_dir).join("logs");
+        fs::create_dir_all(&dir)?;
+        let appender = Builder::new()
+            .rotation(rotation)
+            .filename_prefix("gnezdo")
+            .filename_suffix("log")
+            .max_log_files(log.max_files)
+            .build(&dir)?;
+        let (writer, worker) = tracing_appender::non_blocking(appender);
+        guard = Some(worker);
+        let layer = fmt::layer()
+            .with_writer(writer)
+            .with_ansi(false)
+            .with_timer(ChronoLocal::rfc_3339());
+        Some(if json {
+            layer.json().boxed()
+        } else {
+            layer.with_target(false).boxed()
+        })
+    } else {
+        None
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(console)
+        .with(file)
+        .try_init()?;
+    Ok(guard)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SearchResult {
+    rank: usize,
+    title: String,
+    url: String,
+    // リダイレクト（/url?q=... 等）で包まれていた場合の元のhref
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_url: Option<String>,
+    // 追跡パラメータ・フラグメント・末尾スラッシュを除いたURL（重複判定に使う）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    normalized_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
+    // 結果に表示されているURL（"example.com › docs › page" 等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_url: Option<String>,
+    // 表示URLのホスト以降の階層
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    breadcrumbs: Vec<String>,
+    // ドメインフィルターで除外する前の順位（除外があったページのみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    serp_rank: Option<usize>,
+    // エンジン固有の値。共通フィールドに無いものはここに入れる
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+impl SearchResult {
+    // rank はページ保存時に振り直す
+    fn new(title: String, url: String) -> Self {
+        Self {
+            rank: 0,
+            title,
+            normalized_url: Some(normalize_url(&url)),
+            url,
+            raw_url: None,
+            snippet: None,
+            display_url: None,
+            breadcrumbs: Vec::new(),
+            serp_rank: None,
+            extensions: serde_json::Map::new(),
+        }
+    }
+
+    // 検索結果ページ上での順位（ドメインフィルターで詰める前）
+    fn serp_position(&self) -> usize {
+        self.serp_rank.unwrap_or(self.rank)
+    }
+
+    // 属性値があればエンジン固有フィールドとして残す
+    fn extend_from_attr(&mut self, key: &str, element: ElementRef, attr: &str) {
+        if let Some(value) = element.value().attr(attr).filter(|v| !v.is_empty()) {
+            self.extensions.insert(key.to_string(), value.into());
+        }
+    }
+
+    // 結果ブロックからスニペットと表示URLを拾う
+    fn fill_details(&mut self, container: ElementRef, snippet: &Selector, cite: &Selector) {
+        self.snippet = container
+            .select(snippet)
+            .map(element_text)
+            .find(|t| !t.is_empty());
+        if let Some(cite_text) = container
+            .select(cite)
+            .map(element_text)
+            .find(|t| !t.is_empty())
+        {
+            self.breadcrumbs = cite_text
+                .split('›')
+                .skip(1)
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            self.display_url = Some(cite_text);
+        }
+    }
+}
+
+// 同じページを指すURLを同じ文字列にする
+// ホストの小文字化・フラグメントと追跡パラメータ（utm_* / gclid 等）の除去・末尾スラッシュの除去
+fn normalize_url(url: &str) -> String {
+    const TRACKING_PARAMS: [&str; 14] = [
+        "gclid", "dclid", "gbraid", "wbraid", "fbclid", "msclkid", "yclid", "igshid", "mc_cid",
+        "mc_eid", "srsltid", "_ga", "_gl", "_hsenc",
+    ];
+    let Ok(mut parsed) = url::Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    parsed.set_fragment(None);
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(k, _)| !k.starts_with("utm_") && !TRACKING_PARAMS.contains(&k.as_ref()))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    // ルートの "/" は URL の仕様上消せないのでそのまま
+    let path = parsed.path().trim_end_matches('/').to_string();
+    if !path.is_empty() {
+        parsed.set_path(&path);
+    }
+    parsed.to_string()
+}
+
+// 要素内のテキストを空白を詰めて取り出す
+fn element_text(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// リンクを含む最も近い結果ブロック
+fn enclosing_element<'a>(element: ElementRef<'a>, container: &Selector) -> Option<ElementRef<'a>> {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|e| container.matches(e))
+}
+
+// 全エンジン共通の出力形式。エンジンごとの違いは各パーサーが SearchResult へ写像する段階で吸収し、
+// 共通化できない値は SearchResult.extensions に入れる
+const RESULT_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PageResult {
+    // 項目が変わったら RESULT_SCHEMA_VERSION を上げる（version 1 は engine を持たない）
+    #[serde(default = "default_schema_version")]
+    schema_version: u32,
+    #[serde(default)]
+    engine: String,
+    #[serde(default, skip_serializing_if = "SearchType::is_web")]
+    search_type: SearchType,
+    query: String,
+    page: u32,
+    timestamp: String,
+    result_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suggestion_position: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refinement: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block_state: Option<BlockState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    serp_features: Option<SerpFeatures>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ads: Vec<AdResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    local_results: Vec<LocalResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    discrepancy: Option<ExtractionDiscrepancy>,
+    // 関連検索の候補語（表示順）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    related_queries: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timing: Option<PageTiming>,
+    // 検索エンジンが表示する推定ヒット件数と検索時間
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_results: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    search_time_ms: Option<u64>,
+    // 結果を取り出せた抽出方法（複数の方法を順に試すエンジンのみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extraction_strategy: Option<String>,
+    // ドメインフィルターで除外した件数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filtered_count: Option<usize>,
+    results: Vec<SearchResult>,
+}
+
+impl PageResult {
+    // 検索結果ページに並んでいた件数（ドメインフィルターで除外した分を含む）
+    fn serp_result_count(&self) -> usize {
+        self.result_count + self.filtered_count.unwrap_or(0)
+    }
+}
+
+// CDP Performance.getMetrics から求めたページの表示時間
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct PageTiming {
+    // 遷移開始から DOMContentLoaded まで
+    dom_content_loaded_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    first_meaningful_paint_ms: Option<u64>,
+    #[serde(default)]
+    over_budget: bool,
+}
+
+// ページ上の結果見出し数と抽出件数の食い違い
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct ExtractionDiscrepancy {
+    headings: usize,
+    extracted: usize,
+}
+
+// ローカルパックの店舗・施設
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LocalResult {
+    position: usize,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rating: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    review_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum AdPlacement {
+    Top,
+    Bottom,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AdResult {
+    placement: AdPlacement,
+    // 同じ枠の中での順位（1始まり）
+    position: usize,
+    title: String,
+    // 広告のリンク先（計測用リダイレクトを含むことがある）
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    advertiser_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_url: Option<String>,
+}
+
+// 自然検索結果の上に割り込む検索結果の特殊枠
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct SerpFeatures {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    featured_snippet: Option<FeaturedSnippet>,
+    // 「他の人はこちらも質問」の質問文（表示順）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    people_also_ask: Vec<String>,
+    #[serde(default)]
+    knowledge_panel: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct FeaturedSnippet {
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_title: Option<String>,
+}
+
+// エンジンが1ページから取り出したもの一式（timing は取得側で埋める）
+#[derive(Default)]
+struct ParsedPage {
+    search_type: SearchType,
+    results: Vec<SearchResult>,
+    serp_features: Option<SerpFeatures>,
+    ads: Vec<AdResult>,
+    local_results: Vec<LocalResult>,
+    related_queries: Vec<String>,
+    discrepancy: Option<ExtractionDiscrepancy>,
+    total_results: Option<u64>,
+    search_time_ms: Option<u64>,
+    timing: Option<PageTiming>,
+    extraction_strategy: Option<&'static str>,
+    filtered_count: usize,
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+fn format_duration(start: DateTime<Local>, end: DateTime<Local>) -> String {
+    format_elapsed(end.signed_duration_since(start))
+}
+
+fn format_elapsed(duration: chrono::TimeDelta) -> String {
+    let total_seconds = duration.num_seconds();
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+    let millis = duration.num_milliseconds() % 1000;
+    if hours > 0 {
+        tr!("{}時間{}分{}秒", "{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        tr!("{}分{}秒", "{}m {}s", minutes, seconds)
+    } else {
+        tr!("{}.{:03}秒", "{}.{:03}s", seconds, millis)
+    }
+}
+
+fn get_base_path(relative: &str) -> PathBuf {
+    if cfg!(debug_assertions) {
+        let current_dir = env::current_dir().expect("カレントディレクトリ取得失敗");
+        current_dir.join(relative)
+    } else {
+        let exe_path = env::current_exe().expect("実行ファイルパス取得失敗");
+        exe_path.parent().unwrap().join(relative)
+    }
+}
+
+fn init_profile_dir(config: &Config) -> Result<PathBuf> {
+    let path = get_base_path(&config.profile_dir);
+    fs::create_dir_all(&path)?;
+    if config.seed_preferences {
+        seed_profile_preferences(
+            &path,
+            !config.uses_geolocation(),
+            &launch_languages(&config.persona.locale),
+        )?;
+    }
+    Ok(path)
+}
+
+// 新規profileに Preferences / Local State を事前配置し、ポップアップ類をprofileレベルで抑止する
+fn seed_profile_preferences(
+    profile_dir: &Path,
+    block_geolocation: bool,
+    accept_languages: &str,
+) -> Result<()> {
+    const CONTENT_SETTING_BLOCK: u32 = 2;
+
+    let default_dir = profile_dir.join("Default");
+    fs::create_dir_all(&default_dir)?;
+
+    let mut blocked = serde_json::json!({ "notifications": CONTENT_SETTING_BLOCK });
+    if block_geolocation {
+        blocked["geolocation"] = serde_json::json!(CONTENT_SETTING_BLOCK);
+    }
+    let preferences = serde_json::json!({
+        "profile": {
+            "default_content_setting_values": blocked,
+            "exit_type": "Normal",
+            "exited_cleanly": true
+        },
+        "translate": { "enabled": false },
+        "translate_blocked_languages": ["ja", "en"],
+        "browser": {
+            "check_default_browser": false,
+            "has_seen_welcome_page": true
+        },
+        "intl": { "accept_languages": accept_languages }
+    });
+    fs::write(
+        default_dir.join("Preferences"),
+        serde_json::to_string(&preferences)?,
+    )?;
+
+    let local_state = serde_json::json!({
+        "browser": {
+            "has_seen_welcome_page": true,
+            "default_browser_infobar_declined": true
+        },
+        "user_experience_metrics": { "reporting_enabled": false }
+    });
+    fs::write(
+        profile_dir.join("Local State"),
+        serde_json::to_string(&local_state)?,
+    )?;
+
+    Ok(())
+}
+
+// 実行中はロックを持ち続けるので、ロックファイルは残して中身だけ消す
+fn clear_profile_dir(config: &Config) -> Result<()> {
+    let path = get_base_path(&config.profile_dir);
+    if path.exists() {
+        clear_dir_except_lock(&path)?;
+    }
+    Ok(())
+}
+
+fn clear_dir_except_lock(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name() == Some(OsStr::new(LOCK_FILE_NAME)) {
+            continue;
+        }
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+// profile_dir を消して作り直す（プロファイルプールを使わない場合の起動前処理）
+fn reset_profile_dir(config: &Config) -> Result<PathBuf> {
+    info!(
+        "{}",
+        tr!(
+            "profile を強制リセット中...",
+            "Force-resetting the profile..."
+        )
+    );
+    if let Err(e) = clear_profile_dir(config) {
+        warn!(
+            "{}",
+            tr!(
+                "profile 削除エラー: {}（残った内容のまま起動します）",
+                "Failed to delete the profile: {} (starting with what is left)",
+                e
+            )
+        );
+    }
+    info!(
+        "{}",
+        tr!(
+            "profile 削除完了。新規作成中...",
+            "Profile deleted. Creating a new one..."
+        )
+    );
+    let user_data_dir = init_profile_dir(config)?;
+    info!(
+        "{}",
+        tr!("新規 profile: {:?}", "New profile: {:?}", user_data_dir)
+    );
+    Ok(user_data_dir)
+}
+
+// ============================================================
+// プロファイルプール（[profile_pool]）
+// ============================================================
+// 毎回まっさらな profile で検索すること自体がボットの特徴になるので、Cookie や履歴の
+// 残ったプロファイルを複数用意し、起動のたびに最も長く使っていないものを使う
+const POOL_META_FILE: &str = "gnezdo-profile.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct PooledProfile {
+    created_at: String,
+    #[serde(default)]
+    warmed_at: Option<String>,
+    #[serde(default)]
+    last_used: Option<String>,
+    #[serde(default)]
+    uses: u32,
+}
+
+impl PooledProfile {
+    fn load(dir: &Path) -> Option<Self> {
+        fs::read_to_string(dir.join(POOL_META_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        fs::write(
+            dir.join(POOL_META_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    fn is_expired(&self, max_age_days: u32) -> bool {
+        NaiveDateTime::parse_from_str(&self.created_at, "%Y-%m-%dT%H:%M:%S").is_ok_and(|created| {
+            Local::now().naive_local() - created > chrono::Duration::days(max_age_days as i64)
+        })
+    }
+}
+
+// 使用中のプールのプロファイル。ブラウザを閉じるまで持っておく
+struct ProfileLease {
+    dir: PathBuf,
+    _lock: DirLock,
+}
+
+struct ProfilePool {
+    dir: PathBuf,
+    size: usize,
+}
+
+impl ProfilePool {
+    fn open(pool: &ProfilePoolConfig) -> Result<Self> {
+        let dir = get_base_path(&pool.dir);
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            size: pool.size.max(1),
+        })
+    }
+
+    fn profile_dirs(&self) -> Vec<PathBuf> {
+        (1..=self.size)
+            .map(|n| self.dir.join(format!("profile-{}", n)))
+            .collect()
+    }
+
+    // 他のワーカーや profiles コマンドが使用中なら None
+    fn try_lock(&self, dir: &Path) -> Result<Option<ProfileLease>> {
+        fs::create_dir_all(dir)?;
+        Ok(DirLock::try_acquire(dir)?.map(|lock| ProfileLease {
+            dir: dir.to_path_buf(),
+            _lock: lock,
+        }))
+    }
+
+    // 無ければ作る（作ったら true）。ロックを取ってから呼ぶ
+    fn ensure(&self, lease: &ProfileLease, config: &Config) -> Result<bool> {
+        if PooledProfile::load(&lease.dir).is_some() {
+            return Ok(false);
+        }
+        if config.seed_preferences {
+            seed_profile_preferences(
+                &lease.dir,
+                !config.uses_geolocation(),
+                &launch_languages(&config.persona.locale),
+            )?;
+        }
+        PooledProfile {
+            created_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+            ..Default::default()
+        }
+        .save(&lease.dir)?;
+        Ok(true)
+    }
+
+    // 中身を消して未作成の状態に戻す（ロックファイルは残す）
+    fn wipe(&self, lease: &ProfileLease) -> Result<()> {
+        clear_dir_except_lock(&lease.dir)
+    }
+
+    // 使用中でないもののうち最も長く使っていないプロファイルを取る（未使用のものが優先）
+    fn checkout(&self, config: &Config) -> Result<ProfileLease> {
+        let mut dirs = self.profile_dirs();
+        dirs.sort_by_key(|dir| PooledProfile::load(dir).and_then(|p| p.last_used));
+        for dir in dirs {
+            let Some(lease) = self.try_lock(&dir)? else {
+                continue;
+            };
+            self.ensure(&lease, config)?;
+            if let Err(e) = mark_profile_exited_cleanly(&lease.dir) {
+                warn!(
+                    "{}",
+                    tr!(
+                        "Preferences 更新エラー: {}",
+                        "Failed to update Preferences: {}",
+                        e
+                    )
+                );
+            }
+            let mut profile = PooledProfile::load(&lease.dir).unwrap_or_default();
+            profile.last_used = Some(Local::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+            profile.uses += 1;
+            profile.save(&lease.dir)?;
+            info!(
+                "{}",
+                tr!(
+                    "プールの profile: {:?}（{}回目）",
+                    "Pooled profile: {:?} (use #{})",
+                    lease.dir,
+                    profile.uses
+                )
+            );
+            return Ok(lease);
+        }
+        anyhow::bail!(tr!(
+            "profile_pool のプロファイルがすべて使用中です。size を同時に動かすブラウザの数以上にしてください",
+            "All profile_pool profiles are in use. Set size to at least the number of concurrent browsers"
+        ))
+    }
+}
+
+// 前回の Chromium は強制終了しているので、「ページを復元しますか？」を出さないようにする
+fn mark_profile_exited_cleanly(profile_dir: &Path) -> Result<()> {
+    let path = profile_dir.join("Default").join("Preferences");
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let mut preferences: serde_json::Value = serde_json::from_str(&content)?;
+    preferences["profile"]["exit_type"] = serde_json::json!("Normal");
+    preferences["profile"]["exited_cleanly"] = serde_json::json!(true);
+    fs::write(&path, serde_json::to_string(&preferences)?)?;
+    Ok(())
+}
+
+fn run_profiles(config: &Config, action: ProfilesAction, shutdown: &Arc<AtomicBool>) -> Result<()> {
+    let Some(pool_config) = &config.profile_pool else {
+        anyhow::bail!(tr!(
+            "profiles には Config.toml の [profile_pool] が必要です",
+            "profiles needs [profile_pool] in Config.toml"
+        ));
+    };
+    let pool = ProfilePool::open(pool_config)?;
+    for dir in pool.profile_dirs() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Some(lease) = pool.try_lock(&dir)? else {
+            info!(
+                "{}",
+                tr!(
+                    "{:?} は使用中のためスキップ",
+                    "{:?} is in use, skipping",
+                    dir
+                )
+            );
+            continue;
+        };
+        let existing = PooledProfile::load(&dir);
+        let expired = existing
+            .as_ref()
+            .is_some_and(|p| p.is_expired(pool_config.max_age_days));
+        let unwarmed = existing.is_none_or(|p| p.warmed_at.is_none());
+        if action == ProfilesAction::Refresh && expired {
+            info!("{}", tr!("作り直し: {:?}", "Recreating {:?}", dir));
+            pool.wipe(&lease)?;
+        }
+        let created = pool.ensure(&lease, config)?;
+        if created {
+            info!("{}", tr!("作成: {:?}", "Created {:?}", dir));
+        }
+        let warm = match action {
+            ProfilesAction::Create => false,
+            ProfilesAction::Warm => true,
+            ProfilesAction::Refresh => expired || unwarmed,
+        };
+        if !warm {
+            continue;
+        }
+        info!("{}", tr!("巡回中: {:?}", "Warming {:?}", dir));
+        if let Err(e) = warm_profile(config, &pool_config.warm_urls, lease, shutdown) {
+            error!(
+                "{}",
+                tr!("巡回エラー（{:?}）: {}", "Warm-up error ({:?}): {}", dir, e)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn warm_profile(
+    config: &Config,
+    urls: &[String],
+    lease: ProfileLease,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    let dir = lease.dir.clone();
+    let mut manager = BrowserManager::new(config)?;
+    manager.launch_with(Some(lease))?;
+    let tab = get_active_tab(&mut manager)?;
+    setup_proxy_auth(&tab, manager.current_proxy())?;
+    setup_stealth_cdp(&tab, &config.persona, manager.user_agent())?;
+    inject_stealth_scripts(&tab, config, !config.uses_geolocation())?;
+    let visited = browse_sites(&tab, urls, shutdown);
+
+    let mut profile = PooledProfile::load(&dir).unwrap_or_default();
+    if visited > 0 {
+        profile.warmed_at = Some(Local::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+        profile.save(&dir)?;
+    }
+    info!(
+        "{}",
+        tr!(
+            "  {}/{} サイトを巡回しました",
+            "  Visited {}/{} sites",
+            visited,
+            urls.len()
+        )
+    );
+    Ok(())
+}
+
+// 検索とは関係のないサイトを順不同で開き、少しずつスクロールしながら読む。
+// 開けなかったサイトは飛ばし、読めたサイト数を返す
+fn browse_sites(tab: &Arc<Tab>, urls: &[String], shutdown: &AtomicBool) -> usize {
+    let mut rng = WyRand::new();
+    let mut urls: Vec<&String> = urls.iter().collect();
+    rng.shuffle(&mut urls);
+    let mut visited = 0;
+    for url in urls {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        info!("{}", tr!("  閲覧: {}", "  Browsing {}", url));
+        match browse_site(tab, url, &mut rng) {
+            Ok(()) => visited += 1,
+            Err(e) => warn!(
+                "{}",
+                tr!(
+                    "  閲覧エラー（{}）: {}",
+                    "  Browsing error ({}): {}",
+                    url,
+                    e
+                )
+            ),
+        }
+    }
+    visited
+}
+
+fn browse_site(tab: &Arc<Tab>, url: &str, rng: &mut WyRand) -> Result<()> {
+    log_action("navigate", serde_json::json!({ "url": url }));
+    tab.navigate_to(url)?;
+    tab.wait_until_navigated()?;
+    let dwell = rng.generate_range(8000_u64..=25000);
+    dwell_on_page(tab, dwell, rng)
+}
+
+// 開いたページを total_ms の間、少しずつスクロールしながら読む
+fn dwell_on_page(tab: &Arc<Tab>, total_ms: u64, rng: &mut WyRand) -> Result<()> {
+    let start = Instant::now();
+    human_pause_with_keepalive(tab, rng.generate_range(1500_u64..=4000).min(total_ms))?;
+    loop {
+        let elapsed = start.elapsed().as_millis() as u64;
+        if elapsed >= total_ms {
+            return Ok(());
+        }
+        let dy = rng.generate_range(200_i32..=600);
+        log_action("scroll", serde_json::json!({ "dy": dy }));
+        tab.evaluate(
+            &format!("window.scrollBy({{ top: {}, behavior: 'smooth' }})", dy),
+            false,
+        )?;
+        let pause = rng.generate_range(800_u64..=3500);
+        human_pause_with_keepalive(tab, pause.min(total_ms - elapsed))?;
+    }
+}
+
+fn init_result_dir(config: &Config, start_time: DateTime<Local>) -> Result<PathBuf> {
+    let time_str = start_time.format("%Y-%m-%d-%H-%M-%S").to_string();
+    let root = get_base_path(&config.result_dir);
+    fs::create_dir_all(&root)?;
+    // 同じ秒に別インスタンスが起動しても同じディレクトリを使わないよう create_dir で確保する
+    for n in 1.. {
+        let name = if n == 1 {
+            time_str.clone()
+        } else {
+            format!("{}-{}", time_str, n)
+        };
+        let path = root.join(name);
+        match fs::create_dir(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!()
+}
+
+// ============================================================
+// ディレクトリロック（複数インスタンス同時実行時の保護）
+// ============================================================
+// ロックファイルを開いている間だけ排他ロックを持つ。プロセスが落ちればOSが解放する
+const LOCK_FILE_NAME: &str = "gnezdo.lock";
+
+struct DirLock {
+    _file: fs::File,
+}
+
+impl DirLock {
+    // 他のインスタンスがロック中なら None
+    fn try_acquire(dir: &Path) -> Result<Option<Self>> {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE_NAME))?;
+        match file.try_lock() {
+            Ok(()) => {
+                file.set_len(0)?;
+                write!(file, "{}", std::process::id())?;
+                Ok(Some(Self { _file: file }))
+            }
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    // 実行中（ロックされている）ディレクトリかどうか
+    fn is_held(dir: &Path) -> bool {
+        let Ok(file) = fs::File::open(dir.join(LOCK_FILE_NAME)) else {
+            return false;
+        };
+        matches!(file.try_lock_shared(), Err(fs::TryLockError::WouldBlock))
+    }
+}
+
+// クエリ名からファイル名に使えない文字を除いたディレクトリ名（過去の実行を引くときも同じ変換を通す）
+fn safe_dir_name(query: &str) -> String {
+    query.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+}
+
+fn init_query_result_dir(result_base: &PathBuf, query: &str) -> Result<PathBuf> {
+    let path = result_base.join(safe_dir_name(query));
+    fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+fn save_search_results(
+    sinks: &mut OutputSinks,
+    query_dir: &Path,
+    query: &str,
+    page_num: u32,
+    parsed: &ParsedPage,
+    suggestion_position: Option<usize>,
+    block_state: Option<BlockState>,
+) -> Result<()> {
+    let mut page_result = build_page_result(sinks.engine, query, page_num, parsed);
+    page_result.suggestion_position = suggestion_position;
+    page_result.block_state = block_state;
+    sinks.write_page(query_dir, &page_num.to_string(), &page_result)
+}
+
+fn save_refinement_results(
+    sinks: &mut OutputSinks,
+    query_dir: &Path,
+    step_num: usize,
+    query: &str,
+    step: &FlowStep,
+    parsed: &ParsedPage,
+    block_state: Option<BlockState>,
+) -> Result<()> {
+    let mut page_result = build_page_result(sinks.engine, query, 1, parsed);
+    page_result.refinement = Some(step.describe());
+    page_result.block_state = block_state;
+    sinks.write_page(query_dir, &format!("step{}", step_num), &page_result)
+}
+
+fn build_page_result(engine: &str, query: &str, page_num: u32, parsed: &ParsedPage) -> PageResult {
+    let search_results: Vec<SearchResult> = parsed
+        .results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| SearchResult {
+            rank: i + 1,
+            ..result.clone()
+        })
+        .collect();
+    PageResult {
+        schema_version: RESULT_SCHEMA_VERSION,
+        engine: engine.to_string(),
+        search_type: parsed.search_type,
+        query: query.to_string(),
+        page: page_num,
+        timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        result_count: search_results.len(),
+        suggestion_position: None,
+        refinement: None,
+        block_state: None,
+        serp_features: parsed.serp_features.clone(),
+        ads: parsed.ads.clone(),
+        local_results: parsed.local_results.clone(),
+        discrepancy: parsed.discrepancy,
+        related_queries: parsed.related_queries.clone(),
+        timing: parsed.timing,
+        total_results: parsed.total_results,
+        search_time_ms: parsed.search_time_ms,
+        extraction_strategy: parsed.extraction_strategy.map(str::to_string),
+        filtered_count: (parsed.filtered_count > 0).then_some(parsed.filtered_count),
+        results: search_results,
+    }
+}
+
+// ============================================================
+// 出力シンク
+// ============================================================
+trait OutputSink: Send {
+    fn name(&self) -> &'static str;
+
+    // 1ページ分の結果を書き出す。file_stem はページ番号（"3"）や "step1"
+    fn write_page(&mut self, query_dir: &Path, file_stem: &str, page: &PageResult) -> Result<()>;
+
+    // 実行終了時の後処理
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// クエリディレクトリに N.json を書く（従来の出力）
+struct JsonSink {
+    pretty: bool,
+}
+
+impl OutputSink for JsonSink {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn write_page(&mut self, query_dir: &Path, file_stem: &str, page: &PageResult) -> Result<()> {
+        let json = if self.pretty {
+            serde_json::to_string_pretty(page)?
+        } else {
+            serde_json::to_string(page)?
+        };
+        let mut file = fs::File::create(query_dir.join(format!("{}.json", file_stem)))?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+}
+
+// クエリディレクトリに全ページをまとめた query.json を書く。
+// ページが増えるたびに書き直すので、途中で止まってもそこまでの内容は残る
+#[derive(Serialize, Deserialize, Debug)]
+struct QueryResult {
+    query: String,
+    page_count: usize,
+    result_count: usize,
+    pages: Vec<QueryPage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QueryPage {
+    // 個別ファイル出力時のファイル名（"3" や "step1"）
+    file: String,
+    #[serde(flatten)]
+    page: PageResult,
+}
+
+struct QueryJsonSink {
+    pretty: bool,
+    pages: HashMap<PathBuf, Vec<QueryPage>>,
+}
+
+impl OutputSink for QueryJsonSink {
+    fn name(&self) -> &'static str {
+        "query"
+    }
+
+    fn write_page(&mut self, query_dir: &Path, file_stem: &str, page: &PageResult) -> Result<()> {
+        let pages = self.pages.entry(query_dir.to_path_buf()).or_default();
+        // リトライで同じページを取り直した場合は置き換える
+        let entry = QueryPage {
+            file: file_stem.to_string(),
+            page: page.clone(),
+        };
+        match pages.iter_mut().find(|p| p.file == file_stem) {
+            Some(existing) => *existing = entry,
+            None => pages.push(entry),
+        }
+
+        let merged = QueryResult {
+            query: page.query.clone(),
+            page_count: pages.len(),
+            result_count: pages.iter().map(|p| p.page.result_count).sum(),
+            pages: pages.clone(),
+        };
+        let json = if self.pretty {
+            serde_json::to_string_pretty(&merged)?
+        } else {
+            serde_json::to_string(&merged)?
+        };
+        // 書きかけのファイルが残らないよう一時ファイル経由で置き換える
+        let tmp_path = query_dir.join("query.json.tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, query_dir.join("query.json"))?;
+        Ok(())
+    }
+}
+
+// クエリディレクトリに N.csv（rank,title,url,query,page,timestamp）を書く
+struct CsvSink {
+    bom: bool,
+}
+
+impl OutputSink for CsvSink {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write_page(&mut self, query_dir: &Path, file_stem: &str, page: &PageResult) -> Result<()> {
+        let mut file = fs::File::create(query_dir.join(format!("{}.csv", file_stem)))?;
+        if self.bom {
+            file.write_all(b"\xEF\xBB\xBF")?;
+        }
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record([
+            "rank",
+            "title",
+            "url",
+            "query",
+            "page",
+            "timestamp",
+            "snippet",
+            "display_url",
+            "normalized_url",
+        ])?;
+        for result in &page.results {
+            writer.write_record([
+                result.rank.to_string().as_str(),
+                &result.title,
+                &result.url,
+                &page.query,
+                &page.page.to_string(),
+                &page.timestamp,
+                result.snippet.as_deref().unwrap_or(""),
+                result.display_url.as_deref().unwrap_or(""),
+                result.normalized_url.as_deref().unwrap_or(""),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// result_dir 直下の results.db に runs / queries / pages / results として蓄積する
+struct SqliteSink {
+    conn: rusqlite::Connection,
+    run_id: i64,
+}
+
+impl SqliteSink {
+    fn open(db_path: &Path, result_base: &Path) -> Result<Self> {
+        let conn = rusqlite::Connection::open(db_path)?;
+        // 他のインスタンスが書き込み中なら待つ
+        conn.busy_timeout(Duration::from_secs(30))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS runs (
+                 id INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL,
+                 started_at TEXT NOT NULL,
+                 finished_at TEXT
+             );
+             CREATE TABLE IF NOT EXISTS queries (
+                 id INTEGER PRIMARY KEY,
+                 run_id INTEGER NOT NULL REFERENCES runs(id),
+                 dir_name TEXT NOT NULL,
+                 query TEXT NOT NULL,
+                 UNIQUE (run_id, dir_name)
+             );
+             CREATE TABLE IF NOT EXISTS pages (
+                 id INTEGER PRIMARY KEY,
+                 query_id INTEGER NOT NULL REFERENCES queries(id),
+                 file_stem TEXT NOT NULL,
+                 page INTEGER NOT NULL,
+                 timestamp TEXT NOT NULL,
+                 result_count INTEGER NOT NULL,
+                 refinement TEXT,
+                 suggestion_position INTEGER
+             );
+             CREATE TABLE IF NOT EXISTS results (
+                 id INTEGER PRIMARY KEY,
+                 page_id INTEGER NOT NULL REFERENCES pages(id),
+                 rank INTEGER NOT NULL,
+                 title TEXT NOT NULL,
+                 url TEXT NOT NULL,
+                 raw_url TEXT,
+                 snippet TEXT,
+                 display_url TEXT,
+                 normalized_url TEXT
+             );
+             CREATE INDEX IF NOT EXISTS idx_results_url ON results(url);",
+        )?;
+        // 列追加前に作られたDBへ後から足す
+        for column in ["snippet", "display_url", "normalized_url"] {
+            let exists = conn
+                .prepare("SELECT 1 FROM pragma_table_info('results') WHERE name = ?1")?
+                .exists([column])?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE results ADD COLUMN {} TEXT", column))?;
+            }
+        }
+
+        let run_name = result_base
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        conn.execute(
+            "INSERT INTO runs (name, started_at) VALUES (?1, ?2)",
+            (
+                &run_name,
+                Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+            ),
+        )?;
+        let run_id = conn.last_insert_rowid();
+        Ok(Self { conn, run_id })
+    }
+}
+
+impl OutputSink for SqliteSink {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn write_page(&mut self, query_dir: &Path, file_stem: &str, page: &PageResult) -> Result<()> {
+        let dir_name = query_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO queries (run_id, dir_name, query) VALUES (?1, ?2, ?3)",
+            (self.run_id, &dir_name, &page.query),
+        )?;
+        let query_id: i64 = tx.query_row(
+            "SELECT id FROM queries WHERE run_id = ?1 AND dir_name = ?2",
+            (self.run_id, &dir_name),
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "INSERT INTO pages (query_id, file_stem, page, timestamp, result_count, refinement, suggestion_position)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (
+                query_id,
+                file_stem,
+                page.page,
+                &page.timestamp,
+                page.result_count as i64,
+                &page.refinement,
+                page.suggestion_position.map(|p| p as i64),
+            ),
+        )?;
+        let page_id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO results (page_id, rank, title, url, raw_url, snippet, display_url, normalized_url)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for result in &page.results {
+                stmt.execute((
+                    page_id,
+                    result.rank as i64,
+                    &result.title,
+                    &result.url,
+                    &result.raw_url,
+                    &result.snippet,
+                    &result.display_url,
+                    &result.normalized_url,
+                ))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.conn.execute(
+            "UPDATE runs SET finished_at = ?1 WHERE id = ?2",
+            (
+                Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+                self.run_id,
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+// 実行ディレクトリの results.ndjson に1結果1行で追記する（tail -f で逐次取り込み可能）
+struct NdjsonSink {
+    file: fs::File,
+}
+
+#[derive(Serialize)]
+struct NdjsonRecord<'a> {
+    query: &'a str,
+    page: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refinement: Option<&'a str>,
+    timestamp: &'a str,
+    #[serde(flatten)]
+    result: &'a SearchResult,
+}
+
+impl NdjsonSink {
+    fn open(result_base: &Path) -> Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(result_base.join("results.ndjson"))?;
+        Ok(Self { file })
+    }
+}
+
+impl OutputSink for NdjsonSink {
+    fn name(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn write_page(&mut self, _query_dir: &Path, _file_stem: &str, page: &PageResult) -> Result<()> {
+        // ページ単位でまとめて書き、読み手に途中までの行が見えないようにする
+        let mut buf = String::new();
+        for result in &page.results {
+            let record = NdjsonRecord {
+                query: &page.query,
+                page: page.page,
+                refinement: page.refinement.as_deref(),
+                timestamp: &page.timestamp,
+                result,
+            };
+            buf.push_str(&serde_json::to_string(&record)?);
+            buf.push('\n');
+        }
+        self.file.write_all(buf.as_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+// 並列実行時はワーカーごとに clone し、書き込みは1ページずつ順番に行う
+#[derive(Clone)]
+struct OutputSinks {
+    sinks: Arc<Mutex<Vec<Box<dyn OutputSink>>>>,
+    // 書き出すページに記録するエンジン名（クエリごとの engine 上書きに合わせて実行中に切り替える）
+    engine: &'static str,
+}
+
+impl OutputSinks {
+    fn from_config(config: &Config, result_base: &Path) -> Result<Self> {
+        let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+        for kind in &config.sinks {
+            match kind {
+                SinkKind::Json => sinks.push(Box::new(JsonSink {
+                    pretty: config.sink_options.json.pretty,
+                })),
+                SinkKind::Csv => sinks.push(Box::new(CsvSink {
+                    bom: config.sink_options.csv.bom,
+                })),
+                SinkKind::Sqlite => {
+                    let db_path =
+                        get_base_path(&config.result_dir).join(&config.sink_options.sqlite.path);
+                    info!(
+                        "{}",
+                        tr!("SQLite出力: {:?}", "SQLite output: {:?}", db_path)
+                    );
+                    sinks.push(Box::new(SqliteSink::open(&db_path, result_base)?));
+                }
+                SinkKind::Ndjson => sinks.push(Box::new(NdjsonSink::open(result_base)?)),
+                SinkKind::Query => sinks.push(Box::new(QueryJsonSink {
+                    pretty: config.sink_options.json.pretty,
+                    pages: HashMap::new(),
+                })),
+            }
+        }
+        Ok(Self {
+            sinks: Arc::new(Mutex::new(sinks)),
+            engine: create_engine(config).name(),
+        })
+    }
+
+    // 1つのシンクが失敗しても他のシンクへの書き込みは続け、最初のエラーを返す
+    fn write_page(&mut self, query_dir: &Path, file_stem: &str, page: &PageResult) -> Result<()> {
+        let mut first_error = None;
+        for sink in self.sinks.lock().unwrap().iter_mut() {
+            if let Err(e) = sink.write_page(query_dir, file_stem, page) {
+                warn!(
+                    "{}",
+                    tr!(
+                        "  出力エラー（{}）: {}",
+                        "  Output error ({}): {}",
+                        sink.name(),
+                        e
+                    )
+                );
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn finish(&mut self) {
+        for sink in self.sinks.lock().unwrap().iter_mut() {
+            if let Err(e) = sink.finish() {
+                warn!(
+                    "{}",
+                    tr!(
+                        "出力終了処理エラー（{}）: {}",
+                        "Error finishing output ({}): {}",
+                        sink.name(),
+                        e
+                    )
+                );
+            }
+        }
+    }
+}
+
+// ============================================================
+// 実行状態（外部監視用 status.json）
+// ============================================================
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum RunPhase {
+    Starting,
+    Searching,
+    Resting,
+    Restarting,
+    WaitingInput,
+    WarmingUp,
+    BackingOff,
+    Paused,
+    Finished,
+    Interrupted,
+    Failed,
+}
+
+#[derive(Serialize, Debug)]
+struct RunStatus {
+    pid: u32,
+    run_dir: String,
+    started_at: String,
+    state: RunPhase,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_query: Option<String>,
+    query_index: usize,
+    query_total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+    results_collected: usize,
+    captcha_count: u32,
+    last_activity: String,
+}
+
+// result_dir 直下の status.json を状態が変わるたびに書き換える。
+// 実行ディレクトリ名は毎回変わるので、監視側が固定パスで読めるようにしている
+// （同じ result_dir を別インスタンスが使用中の場合は実行ディレクトリに置く）
+// progress = true のときは同じ状態を進捗バーにも反映する。
+// 並列実行時はワーカーごとに clone し、最後に更新したワーカーの状態が書かれる
+#[derive(Clone)]
+struct StatusFile {
+    path: PathBuf,
+    status: Arc<Mutex<RunStatus>>,
+    progress: Option<ProgressBars>,
+}
+
+impl StatusFile {
+    fn new(
+        control_dir: &Path,
+        result_base: &Path,
+        program_start: DateTime<Local>,
+        max_pages: u32,
+    ) -> Self {
+        let status_file = Self {
+            path: control_dir.join("status.json"),
+            status: Arc::new(Mutex::new(RunStatus {
+                pid: std::process::id(),
+                run_dir: result_base.to_string_lossy().to_string(),
+                started_at: program_start.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                state: RunPhase::Starting,
+                current_query: None,
+                query_index: 0,
+                query_total: 0,
+                page: None,
+                results_collected: 0,
+                captcha_count: 0,
+                last_activity: String::new(),
+            })),
+            progress: PROGRESS
+                .get()
+                .map(|multi| ProgressBars::new(multi, max_pages)),
+        };
+        status_file.update(|_| {});
+        status_file
+    }
+
+    fn set_state(&mut self, state: RunPhase) {
+        self.update(|status| status.state = state);
+    }
+
+    fn set_query(&mut self, index: usize, total: usize, query: &str) {
+        self.update(|status| {
+            status.state = RunPhase::Searching;
+            status.current_query = Some(query.to_string());
+            status.query_index = index;
+            status.query_total = total;
+            status.page = None;
+        });
+    }
+
+    fn record_captcha(&mut self) {
+        self.update(|status| {
+            status.state = RunPhase::BackingOff;
+            status.captcha_count += 1;
+        });
+    }
+
+    fn set_page(&mut self, page: u32) {
+        self.update(|status| {
+            status.state = RunPhase::Searching;
+            status.page = Some(page);
+        });
+    }
+
+    fn add_results(&mut self, count: usize) {
+        self.update(|status| status.results_collected += count);
+    }
+
+    fn captcha_count(&self) -> u32 {
+        self.status.lock().unwrap().captcha_count
+    }
+
+    // 書き込みもロックを持ったまま行い、一時ファイルを取り合わないようにする
+    fn update(&self, f: impl FnOnce(&mut RunStatus)) {
+        let mut status = self.status.lock().unwrap();
+        f(&mut status);
+        status.last_activity = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        if let Some(progress) = &self.progress {
+            progress.update(&status);
+        }
+        if let Err(e) = self.try_write(&status) {
+            warn!(
+                "{}",
+                tr!(
+                    "status.json 書き込みエラー: {}",
+                    "Failed to write status.json: {}",
+                    e
+                )
+            );
+        }
+    }
+
+    // 監視側が読みかけのファイルを掴まないよう一時ファイル経由で置き換える
+    fn try_write(&self, status: &RunStatus) -> Result<()> {
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(status)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+// ============================================================
+// 進捗表示（progress = true のとき）
+// ============================================================
+// ログ出力とバーが同じ端末を取り合わないよう、起動時に一度だけ作る
+static PROGRESS: std::sync::OnceLock<indicatif::MultiProgress> = std::sync::OnceLock::new();
+
+// バーを一時的に消してから書くコンソール出力（ログ用）
+struct ProgressWriter(indicatif::MultiProgress);
+
+impl Write for ProgressWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.suspend(|| std::io::stdout().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()
+    }
+}
+
+#[derive(Clone)]
+struct ProgressBars {
+    queries: indicatif::ProgressBar,
+    page: indicatif::ProgressBar,
+}
+
+impl ProgressBars {
+    fn new(multi: &indicatif::MultiProgress, max_pages: u32) -> Self {
+        use indicatif::{ProgressBar, ProgressStyle};
+
+        let queries = multi.add(ProgressBar::new(0));
+        queries.set_style(
+            ProgressStyle::with_template(
+                "{prefix:>6} [{bar:30.cyan/blue}] {pos}/{len} {elapsed_precise} (ETA {eta}) {wide_msg}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        queries.set_prefix(tr!("クエリ", "Query"));
+        let page = multi.add(ProgressBar::new(max_pages as u64));
+        page.set_style(
+            ProgressStyle::with_template("{prefix:>6} [{bar:30}] {pos}/{len} {wide_msg}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        page.set_prefix(tr!("ページ", "Page"));
+        Self { queries, page }
+    }
+
+    fn update(&self, status: &RunStatus) {
+        self.queries.set_length(status.query_total as u64);
+        // 表示中のクエリはまだ終わっていないので1つ手前までを完了とする
+        self.queries
+            .set_position(status.query_index.saturating_sub(1) as u64);
+        if let Some(query) = &status.current_query {
+            self.queries.set_message(query.clone());
+        }
+        self.page.set_position(status.page.unwrap_or(0) as u64);
+        let phase = format!("{:?}", status.state).to_lowercase();
+        self.page.set_message(tr!(
+            "結果 {} 件 / CAPTCHA {} 回 / {}",
+            "{} results / {} CAPTCHAs / {}",
+            status.results_collected,
+            status.captcha_count,
+            phase
+        ));
+        match status.state {
+            RunPhase::Finished => {
+                self.queries.set_position(status.query_total as u64);
+                self.queries.finish();
+                self.page.finish_and_clear();
+            }
+            RunPhase::Interrupted | RunPhase::Failed => {
+                self.queries.abandon();
+                self.page.finish_and_clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+// ============================================================
+// チェックポイント（--resume 用 checkpoint.json）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Checkpoint {
+    // 全ページ取得を終えたクエリ
+    completed_queries: Vec<String>,
+    // 実行中だったクエリと保存済みページ（再開時はこのクエリを最初からやり直す）
+    #[serde(default)]
+    current_query: Option<String>,
+    #[serde(default)]
+    completed_pages: Vec<u32>,
+    // SKIP で放棄したクエリ（再開時は再度実行する）
+    #[serde(default)]
+    skipped_queries: Vec<String>,
+    // 完了したクエリの所要時間（ミリ秒）
+    #[serde(default)]
+    durations_ms: BTreeMap<String, i64>,
+    // 検索エラー（リトライ分も含む）
+    #[serde(default)]
+    errors: Vec<QueryError>,
+    // 前ページとほぼ同じ結果が返ってきたためページ送りを打ち切ったクエリ
+    #[serde(default)]
+    looping_queries: Vec<String>,
+    #[serde(default)]
+    updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QueryError {
+    query: String,
+    at: String,
+    message: String,
+}
+
+// 並列実行時はワーカーごとに clone して同じ checkpoint.json を更新する
+// （current_query / completed_pages は最後に更新したワーカーのもの）
+#[derive(Clone)]
+struct CheckpointFile {
+    path: PathBuf,
+    checkpoint: Arc<Mutex<Checkpoint>>,
+}
+
+impl CheckpointFile {
+    fn load_or_new(result_base: &Path) -> Result<Self> {
+        let path = result_base.join("checkpoint.json");
+        let checkpoint = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Checkpoint::default()
+        };
+        Ok(Self {
+            path,
+            checkpoint: Arc::new(Mutex::new(checkpoint)),
+        })
+    }
+
+    fn is_completed(&self, query: &str) -> bool {
+        self.checkpoint
+            .lock()
+            .unwrap()
+            .completed_queries
+            .iter()
+            .any(|q| q == query)
+    }
+
+    fn start_query(&mut self, query: &str) {
+        self.update(|checkpoint| {
+            checkpoint.current_query = Some(query.to_string());
+            checkpoint.completed_pages.clear();
+        });
+    }
+
+    fn complete_page(&mut self, page: u32) {
+        self.update(|checkpoint| checkpoint.completed_pages.push(page));
+    }
+
+    fn complete_query(&mut self, query: &str, duration: chrono::TimeDelta) {
+        self.update(|checkpoint| {
+            if !checkpoint.completed_queries.iter().any(|q| q == query) {
+                checkpoint.completed_queries.push(query.to_string());
+            }
+            checkpoint
+                .durations_ms
+                .insert(query.to_string(), duration.num_milliseconds());
+            checkpoint.current_query = None;
+            checkpoint.completed_pages.clear();
+        });
+    }
+
+    fn record_error(&mut self, query: &str, error: &anyhow::Error) {
+        self.update(|checkpoint| {
+            checkpoint.errors.push(QueryError {
+                query: query.to_string(),
+                at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+                message: format!("{:#}", error),
+            });
+        });
+    }
+
+    fn looping_queries(&self) -> Vec<String> {
+        self.checkpoint.lock().unwrap().looping_queries.clone()
+    }
+
+    fn mark_looping(&mut self, query: &str) {
+        self.update(|checkpoint| {
+            if !checkpoint.looping_queries.iter().any(|q| q == query) {
+                checkpoint.looping_queries.push(query.to_string());
+            }
+        });
+    }
+
+    fn skip_query(&mut self, query: &str) {
+        self.update(|checkpoint| {
+            checkpoint.skipped_queries.push(query.to_string());
+            checkpoint.current_query = None;
+            checkpoint.completed_pages.clear();
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Checkpoint)) {
+        let mut checkpoint = self.checkpoint.lock().unwrap();
+        f(&mut checkpoint);
+        checkpoint.updated_at = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        if let Err(e) = self.try_write(&checkpoint) {
+            warn!(
+                "{}",
+                tr!(
+                    "checkpoint.json 書き込みエラー: {}",
+                    "Failed to write checkpoint.json: {}",
+                    e
+                )
+            );
+        }
+    }
+
+    fn try_write(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(checkpoint)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+// ============================================================
+// Ctrl+C による終了要求
+// ============================================================
+// 1回目はフラグを立てて区切りの良いところで終了、2回目は即時終了
+fn install_ctrlc_handler() -> Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = flag.clone();
+    ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            warn!("{}", tr!("強制終了します", "Force quitting"));
+            std::process::exit(130);
+        }
+        warn!(
+            "{}",
+            tr!(
+                "Ctrl+C を受信。現在のページを保存して終了します（もう一度押すと強制終了）",
+                "Ctrl+C received. Saving the current page and exiting (press again to force quit)"
+            )
+        );
+    })?;
+    Ok(flag)
+}
+
+// クエリをまたいで引き継ぐ実行中の状態
+struct RunState {
+    consecutive_no_next: u32,
+    status: StatusFile,
+    checkpoint: CheckpointFile,
+    shutdown: Arc<AtomicBool>,
+    // このファイルが存在する間は区切りの良いところで一時停止する
+    pause_file: PathBuf,
+    // このファイルが置かれたら現在のクエリを放棄して次へ進む
+    skip_file: PathBuf,
+    skip_current: bool,
+    // 表示時間の予算を連続で超過したページ数
+    slow_streak: u32,
+    // 受け持つジョブ（worker 番目から workers 個おき。concurrency = 1 なら全部）
+    worker: usize,
+    workers: usize,
+}
+
+impl RunState {
+    fn shutdown_requested(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    fn sleep_unless_shutdown(&self, total_ms: u64) {
+        sleep_unless_shutdown(&self.shutdown, total_ms);
+    }
+
+    // SKIP ファイルがあれば消費して現在のクエリの放棄を予約する
+    fn take_skip_request(&mut self) -> bool {
+        if !self.skip_file.exists() {
+            return false;
+        }
+        if let Err(e) = fs::remove_file(&self.skip_file) {
+            warn!(
+                "{}",
+                tr!(
+                    "  SKIP ファイル削除エラー: {}",
+                    "  Failed to delete SKIP file: {}",
+                    e
+                )
+            );
+        }
+        info!(
+            "{}",
+            tr!(
+                "  スキップ要求を受信。現在のクエリを放棄します",
+                "  Skip requested. Abandoning the current query"
+            )
+        );
+        self.skip_current = true;
+        true
+    }
+}
+
+// 終了要求が来たら途中で切り上げる待機
+fn sleep_unless_shutdown(shutdown: &AtomicBool, total_ms: u64) {
+    let mut elapsed = 0;
+    while elapsed < total_ms && !shutdown.load(Ordering::SeqCst) {
+        let sleep_time = (total_ms - elapsed).min(200);
+        thread::sleep(Duration::from_millis(sleep_time));
+        elapsed += sleep_time;
+    }
+}
+
+// PAUSE ファイルがある間はキープアライブしながら待つ
+fn wait_while_paused(tab: &Arc<Tab>, state: &mut RunState) -> Result<()> {
+    if !state.pause_file.exists() {
+        return Ok(());
+    }
+    info!(
+        "{}",
+        tr!(
+            "  一時停止中（{:?} を削除すると再開）...",
+            "  Paused (delete {:?} to resume)...",
+            state.pause_file
+        )
+    );
+    state.status.set_state(RunPhase::Paused);
+    while state.pause_file.exists() && !state.shutdown_requested() && !state.skip_file.exists() {
+        human_pause_with_keepalive(tab, 2000)?;
+    }
+    info!("{}", tr!("  再開します", "  Resuming"));
+    state.status.set_state(RunPhase::Searching);
+    Ok(())
+}
+
+fn extract_bing_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse("li.b_algo").unwrap();
+    let link_selector = Selector::parse("h2 a").unwrap();
+    let snippet_selector = Selector::parse(".b_caption p, p.b_lineclamp2, p.b_lineclamp3").unwrap();
+    let cite_selector = Selector::parse("cite").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for item in document.select(&item_selector) {
+        let Some(link) = item.select(&link_selector).next() else {
+            continue;
+        };
+        let href = link.value().attr("href").unwrap_or("");
+        let unwrapped = unwrap_bing_redirect(href);
+        let url = unwrapped.clone().unwrap_or_else(|| href.to_string());
+        let title = link.text().collect::<String>().trim().to_string();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            result.raw_url = unwrapped.map(|_| href.to_string());
+            // クリック計測用ID（ID=SERP,5120.1 等）
+            result.extend_from_attr("tracking_id", link, "h");
+            result.fill_details(item, &snippet_selector, &cite_selector);
+            results.push(result);
+        }
+    }
+    results
+}
+
+fn extract_duckduckgo_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let selector =
+        Selector::parse(r#"article[data-testid="result"] a[data-testid="result-title-a"]"#)
+            .unwrap();
+    let container_selector = Selector::parse(r#"article[data-testid="result"]"#).unwrap();
+    let snippet_selector = Selector::parse(r#"[data-result="snippet"]"#).unwrap();
+    let cite_selector = Selector::parse(r#"a[data-testid="result-extras-url-link"]"#).unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for link in document.select(&selector) {
+        let url = link.value().attr("href").unwrap_or("").to_string();
+        let title = link.text().collect::<String>().trim().to_string();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            if let Some(container) = enclosing_element(link, &container_selector) {
+                // ページ内の通し番号（More results で追加された分も続き番号）
+                result.extend_from_attr("nrn", container, "data-nrn");
+                result.fill_details(container, &snippet_selector, &cite_selector);
+            }
+            results.push(result);
+        }
+    }
+    results
+}
+
+fn extract_yahoo_japan_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("div.Algo a").unwrap();
+    let title_selector = Selector::parse("h3").unwrap();
+    let container_selector = Selector::parse("div.Algo").unwrap();
+    let snippet_selector = Selector::parse(".sw-Card__summary, .sw-Card__section p").unwrap();
+    let cite_selector = Selector::parse("cite").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for link in document.select(&link_selector) {
+        let Some(h3) = link.select(&title_selector).next() else {
+            continue;
+        };
+        let url = link.value().attr("href").unwrap_or("").to_string();
+        let title = h3.text().collect::<String>().trim().to_string();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            if let Some(container) = enclosing_element(link, &container_selector) {
+                result.fill_details(container, &snippet_selector, &cite_selector);
+            }
+            results.push(result);
+        }
+    }
+    results
+}
+
+// ニュース検索。カードの data 属性に記事情報がある
+fn extract_bing_news_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let card_selector = Selector::parse("div.news-card[data-url]").unwrap();
+    let snippet_selector = Selector::parse(".snippet").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for card in document.select(&card_selector) {
+        let url = card.value().attr("data-url").unwrap_or("").to_string();
+        let title = card
+            .value()
+            .attr("data-title")
+            .unwrap_or("")
+            .trim()
+            .to_string();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            result.snippet = card
+                .select(&snippet_selector)
+                .map(element_text)
+                .find(|t| !t.is_empty());
+            result.extend_from_attr("source", card, "data-author");
+            results.push(result);
+        }
+    }
+    results
+}
+
+// 画像検索。a.iusc の m 属性（JSON）に掲載ページ purl と画像 murl がある
+fn extract_bing_image_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("a.iusc[m]").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for link in document.select(&selector) {
+        let Ok(meta) =
+            serde_json::from_str::<serde_json::Value>(link.value().attr("m").unwrap_or(""))
+        else {
+            continue;
+        };
+        let url = meta["purl"].as_str().unwrap_or("").to_string();
+        let title = meta["t"].as_str().unwrap_or("").trim().to_string();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            if let Some(image) = meta["murl"].as_str() {
+                result
+                    .extensions
+                    .insert("image_url".to_string(), image.into());
+            }
+            results.push(result);
+        }
+    }
+    results
+}
+
+// https://www.bing.com/ck/a?...&u=a1<base64url> 形式のリダイレクトを元のURLに戻す
+fn unwrap_bing_redirect(href: &str) -> Option<String> {
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    if !href.contains("bing.com/ck/a") {
+        return None;
+    }
+    let query = href.split_once('?')?.1;
+    let encoded = query
+        .split('&')
+        .find_map(|kv| kv.strip_prefix("u="))?
+        .strip_prefix("a1")?;
+    let decoded = URL_SAFE_NO_PAD.decode(encoded.trim_end_matches('=')).ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+// ============================================================
+// 実行サマリー（ドメイン別集計）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug)]
+struct RunSummary {
+    generated_at: String,
+    query_count: usize,
+    page_count: usize,
+    result_count: usize,
+    top_domains: Vec<DomainStat>,
+    #[serde(default)]
+    previous_run: Option<String>,
+    #[serde(default)]
+    anomalies: Vec<Anomaly>,
+    // 「通常と異なるトラフィック」警告付きで取得できたページ数
+    #[serde(default)]
+    soft_warning_pages: usize,
+    // 見出し数と抽出件数が食い違ったページ数
+    #[serde(default)]
+    discrepancy_pages: usize,
+    // 表示時間が perf_budget を超えたページ数
+    #[serde(default)]
+    over_budget_pages: usize,
+    #[serde(default)]
+    dedup: DedupSummary,
+}
+
+// 重複した結果の集計（同じURLかどうかは normalized_url で判定）
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct DedupSummary {
+    // 実行全体での異なるURLの数
+    unique_urls: usize,
+    // 同じ検索の前のページに既に出ていた結果の数
+    repeated_within_query: usize,
+    // 2つ以上の検索に出てきたURLの数
+    urls_in_multiple_queries: usize,
+    // ページをまたいだ重複があった検索（重複の多い順）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    queries: Vec<QueryDedup>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct QueryDedup {
+    query: String,
+    results: usize,
+    repeated: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Anomaly {
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    detail: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DomainStat {
+    domain: String,
+    appearances: usize,
+    query_count: usize,
+    average_rank: f64,
+    best_rank: usize,
+}
+
+// クエリディレクトリ名ごとのページ一覧（結果0件のクエリは空のVec）
+fn load_run_queries(run_dir: &Path) -> Result<Vec<(String, Vec<PageResult>)>> {
+    let mut queries = Vec::new();
+    for entry in fs::read_dir(run_dir)? {
+        let query_dir = entry?.path();
+        if !query_dir.is_dir() {
+            continue;
+        }
+        let name = query_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut pages = Vec::new();
+        let mut merged_pages = Vec::new();
+        for file in fs::read_dir(&query_dir)? {
+            let path = file?.path();
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+            if path.file_name() == Some(OsStr::new("query.json")) {
+                if let Ok(content) = fs::read_to_string(&path)
+                    && let Ok(merged) = serde_json::from_str::<QueryResult>(&content)
+                {
+                    merged_pages = merged.pages.into_iter().map(|p| p.page).collect();
+                }
+                continue;
+            }
+            if let Ok(content) = fs::read_to_string(&path)
+                && let Ok(page) = serde_json::from_str::<PageResult>(&content)
+            {
+                pages.push(page);
+            }
+        }
+        // ページ別ファイルが無い場合（sinks = ["query"] のみ）は query.json を使う
+        if pages.is_empty() {
+            pages = merged_pages;
+        }
+        queries.push((name, pages));
+    }
+    queries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(queries)
+}
+
+// 結果ディレクトリ直下の実行ディレクトリ（タイムスタンプ名なので名前順＝時系列順）
+fn list_run_dirs(result_root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(result_root) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+// 同じ結果ディレクトリ内で、現在の実行より前の最新の実行ディレクトリ
+fn find_previous_run(result_base: &Path) -> Option<PathBuf> {
+    let current = result_base.file_name()?;
+    list_run_dirs(result_base.parent()?)
+        .into_iter()
+        .filter(|p| p.file_name().is_some_and(|n| n < current))
+        .filter(|p| !DirLock::is_held(p))
+        .max()
+}
+
+// 通常ページ（絞り込みステップを除く）の上位n件のURLを通し順位順に返す
+fn top_urls(pages: &[PageResult], n: usize) -> Vec<String> {
+    let mut numbered: Vec<&PageResult> = pages.iter().filter(|p| p.refinement.is_none()).collect();
+    numbered.sort_by_key(|p| p.page);
+    numbered
+        .iter()
+        .flat_map(|p| p.results.iter())
+        .take(n)
+        .map(|r| r.url.clone())
+        .collect()
+}
+
+// ============================================================
+// 順位安定度（過去の実行履歴から算出）
+// ============================================================
+#[derive(Serialize, Deserialize, Debug)]
+struct TrendReport {
+    generated_at: String,
+    runs_considered: usize,
+    keywords: Vec<KeywordStability>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KeywordStability {
+    query: String,
+    runs: usize,
+    // 連続する実行間の上位10件集合のJaccard係数の平均（1.0で完全一致）
+    mean_top10_overlap: f64,
+    // 上位10件に現れたURLごとの順位分散の平均（圏外は11位扱い）
+    mean_rank_variance: f64,
+    recommended_frequency: String,
+}
+
+fn compute_keyword_stability(query: &str, history: &[Vec<String>]) -> KeywordStability {
+    const TOP_N: usize = 10;
+    const DAILY_THRESHOLD: f64 = 0.7;
+
+    let overlaps: Vec<f64> = history
+        .windows(2)
+        .map(|w| {
+            let a: HashSet<&String> = w[0].iter().collect();
+            let b: HashSet<&String> = w[1].iter().collect();
+            let union = a.union(&b).count();
+            if union == 0 {
+                1.0
+            } else {
+                a.intersection(&b).count() as f64 / union as f64
+            }
+        })
+        .collect();
+    let mean_top10_overlap = if overlaps.is_empty() {
+        1.0
+    } else {
+        overlaps.iter().sum::<f64>() / overlaps.len() as f64
+    };
+
+    let all_urls: HashSet<&String> = history.iter().flatten().collect();
+    let variances: Vec<f64> = all_urls
+        .iter()
+        .map(|url| {
+            let ranks: Vec<f64> = history
+                .iter()
+                .map(|top| {
+                    top.iter()
+                        .position(|u| u == *url)
+                        .map_or((TOP_N + 1) as f64, |i| (i + 1) as f64)
+                })
+                .collect();
+            let mean = ranks.iter().sum::<f64>() / ranks.len() as f64;
+            ranks.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / ranks.len() as f64
+        })
+        .collect();
+    let mean_rank_variance = if variances.is_empty() {
+        0.0
+    } else {
+        variances.iter().sum::<f64>() / variances.len() as f64
+    };
+
+    let recommended_frequency = if history.len() < 2 {
+        "unknown"
+    } else if mean_top10_overlap < DAILY_THRESHOLD {
+        "daily"
+    } else {
+        "weekly"
+    };
+
+    KeywordStability {
+        query: query.to_string(),
+        runs: history.len(),
+        mean_top10_overlap,
+        mean_rank_variance,
+        recommended_frequency: recommended_frequency.to_string(),
+    }
+}
+
+fn write_trend_report(result_base: &Path) -> Result<TrendReport> {
+    const HISTORY_WINDOW: usize = 30;
+
+    let current = result_base.file_name().map(|n| n.to_os_string());
+    let runs: Vec<PathBuf> = list_run_dirs(result_base.parent().unwrap_or(result_base))
+        .into_iter()
+        .filter(|p| p.file_name().map(|n| n.to_os_string()) <= current)
+        .filter(|p| p == result_base || !DirLock::is_held(p))
+        .collect();
+    let runs = &runs[runs.len().saturating_sub(HISTORY_WINDOW)..];
+
+    // クエリ -> 実行ごとの上位10件（古い順）
+    let mut history: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+    for run in runs {
+        for (name, pages) in load_run_queries(run)? {
+            let top = top_urls(&pages, 10);
+            if !top.is_empty() {
+                history.entry(name).or_default().push(top);
+            }
+        }
+    }
+
+    let mut keywords: Vec<KeywordStability> = history
+        .iter()
+        .map(|(query, tops)| compute_keyword_stability(query, tops))
+        .collect();
+    keywords.sort_by(|a, b| {
+        a.mean_top10_overlap
+            .total_cmp(&b.mean_top10_overlap)
+            .then(a.query.cmp(&b.query))
+    });
+
+    let report = TrendReport {
+        generated_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        runs_considered: runs.len(),
+        keywords,
+    };
+    fs::write(
+        result_base.join("trends.json"),
+        serde_json::to_string_pretty(&report)?,
+    )?;
+    Ok(report)
+}
+
+// ============================================================
+// 履歴に基づくクエリの選択（smart_schedule）
+// ============================================================
+// 実行ディレクトリ名（%Y-%m-%d-%H-%M-%S[-N]）から開始時刻を読む
+fn run_started_at(run_dir: &Path) -> Option<NaiveDateTime> {
+    let name = run_dir.file_name()?.to_str()?;
+    NaiveDateTime::parse_from_str(name.get(..19)?, "%Y-%m-%d-%H-%M-%S").ok()
+}
+
+fn select_scheduled_jobs(
+    jobs: Vec<QueryJob>,
+    result_base: &Path,
+    smart: &SmartScheduleConfig,
+    config: &Config,
+) -> Result<Vec<QueryJob>> {
+    const HISTORY_WINDOW: usize = 30;
+
+    let now = Local::now().naive_local();
+    let runs: Vec<PathBuf> = list_run_dirs(result_base.parent().unwrap_or(result_base))
+        .into_iter()
+        .filter(|p| p != result_base && !DirLock::is_held(p))
+        .collect();
+    let runs = &runs[runs.len().saturating_sub(HISTORY_WINDOW)..];
+
+    // クエリ -> 実行ごとの上位10件（古い順）、最後の実行時刻とページ数
+    let mut history: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+    let mut last_run: HashMap<String, (NaiveDateTime, usize)> = HashMap::new();
+    let mut used_pages = 0;
+    for run in runs {
+        let Some(started) = run_started_at(run) else {
+            continue;
+        };
+        // 壊れた過去の実行が一つあってもスケジュール全体は止めない
+        let queries = match load_run_queries(run) {
+            Ok(queries) => queries,
+            Err(e) => {
+                warn!(
+                    "{}",
+                    tr!(
+                        "警告: 過去の実行を読み込めないためスキップします: {:?}: {}",
+                        "Warning: skipping unreadable past run {:?}: {}",
+                        run,
+                        e
+                    )
+                );
+                continue;
+            }
+        };
+        for (name, pages) in queries {
+            if now - started < chrono::Duration::hours(24) {
+                used_pages += pages.len();
+            }
+            let top = top_urls(&pages, 10);
+            if !top.is_empty() {
+                history.entry(name.clone()).or_default().push(top);
+            }
+            last_run.insert(name, (started, pages.len()));
+        }
+    }
+
+    // (ジョブ番号, 経過時間 / 実行間隔, 見込みページ数, 実行間隔)
+    let mut candidates: Vec<(usize, f64, usize, f64)> = jobs
+        .iter()
+        .enumerate()
+        .map(|(i, job)| {
+            // 過去の実行はディレクトリ名で読んでいるので、同じ変換をしてから引く
+            let name = safe_dir_name(&job.dir_name());
+            // 履歴が足りないクエリは最短間隔で扱う
+            let stability = history
+                .get(&name)
+                .filter(|h| h.len() >= 2)
+                .map_or(0.0, |h| {
+                    compute_keyword_stability(&name, h).mean_top10_overlap
+                });
+            let interval = smart.min_interval_hours as f64
+                + smart
+                    .max_interval_hours
+                    .saturating_sub(smart.min_interval_hours) as f64
+                    * stability;
+            let (ratio, cost) = match last_run.get(&name) {
+                Some((at, pages)) => (
+                    (now - *at).num_minutes() as f64 / 60.0 / interval.max(1.0),
+                    (*pages).max(1),
+                ),
+                None => (f64::INFINITY, config.max_pages as usize),
+            };
+            (i, ratio, cost, interval)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut budget = smart.pages_per_day.saturating_sub(used_pages);
+    info!(
+        "{}",
+        tr!(
+            "smart_schedule: 直近24時間で {}/{} ページ使用済み",
+            "smart_schedule: {}/{} pages used in the last 24 hours",
+            used_pages,
+            smart.pages_per_day
+        )
+    );
+    let mut selected = HashSet::new();
+    for (i, ratio, cost, interval) in candidates {
+        let name = jobs[i].key();
+        if ratio < 1.0 {
+            info!(
+                "{}",
+                tr!(
+                    "  見送り（間隔{:.0}時間に未達）: {}",
+                    "  Deferred (interval of {:.0}h not reached): {}",
+                    interval,
+                    name
+                )
+            );
+        } else if cost > budget {
+            info!(
+                "{}",
+                tr!(
+                    "  見送り（予算不足、見込み{}ページ）: {}",
+                    "  Deferred (budget exhausted, ~{} pages): {}",
+                    cost,
+                    name
+                )
+            );
+        } else {
+            info!(
+                "{}",
+                tr!(
+                    "  実行（間隔{:.0}時間）: {}",
+                    "  Run (interval {:.0}h): {}",
+                    interval,
+                    name
+                )
+            );
+            budget -= cost;
+            selected.insert(i);
+        }
+    }
+
+    Ok(jobs
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, job)| job)
+        .collect())
+}
+
+fn print_trend_report(report: &TrendReport) {
+    info!(
+        "{}",
+        tr!(
+            "--- 順位安定度（直近{}回の実行、不安定な順） ---",
+            "--- Rank stability (last {} runs, least stable first) ---",
+            report.runs_considered
+        )
+    );
+    for k in &report.keywords {
+        info!(
+            "{}",
+            tr!(
+                "  一致率{:>5.1}%  順位分散{:>6.2}  {:>2}回  推奨:{:<7}  {}",
+                "  overlap{:>5.1}%  rank var{:>6.2}  {:>2} runs  advice:{:<7}  {}",
+                k.mean_top10_overlap * 100.0,
+                k.mean_rank_variance,
+                k.runs,
+                k.recommended_frequency,
+                k.query
+            )
+        );
+    }
+    info!("------------------------------------------------------------");
+}
+
+// ============================================================
+// 前回実行との比較による異常検知
+// ============================================================
+fn detect_anomalies(
+    current: &[(String, Vec<PageResult>)],
+    previous: &[(String, Vec<PageResult>)],
+) -> Vec<Anomaly> {
+    const RESULT_DROP_RATIO: f64 = 0.5;
+    const ZERO_RESULT_QUERY_RATIO: f64 = 0.3;
+    const MIN_DOMAIN_OVERLAP: f64 = 0.5;
+    const TOP_DOMAIN_COUNT: usize = 20;
+
+    let mut anomalies = Vec::new();
+    let total = |pages: &[PageResult]| pages.iter().map(|p| p.result_count).sum::<usize>();
+
+    // 1. クエリ単位の取得件数の急減
+    let previous_totals: HashMap<&str, usize> = previous
+        .iter()
+        .map(|(name, pages)| (name.as_str(), total(pages)))
+        .collect();
+    for (name, pages) in current {
+        let Some(&before) = previous_totals.get(name.as_str()) else {
+            continue;
+        };
+        let now = total(pages);
+        if before > 0 && (now as f64) < before as f64 * RESULT_DROP_RATIO {
+            anomalies.push(Anomaly {
+                kind: "result_drop".to_string(),
+                query: Some(name.clone()),
+                detail: tr!("取得件数 {} → {}", "result count {} → {}", before, now),
+            });
+        }
+    }
+
+    // 2. 結果0件のクエリが多い
+    let zero = current
+        .iter()
+        .filter(|(_, pages)| total(pages) == 0)
+        .count();
+    if !current.is_empty() && zero as f64 / current.len() as f64 >= ZERO_RESULT_QUERY_RATIO {
+        anomalies.push(Anomaly {
+            kind: "zero_results".to_string(),
+            query: None,
+            detail: tr!(
+                "{}/{} クエリで結果0件（セレクタ破損またはブロックの可能性）",
+                "{}/{} queries returned no results (broken selectors or blocking?)",
+                zero,
+                current.len()
+            ),
+        });
+    }
+
+    // 3. 上位ドメイン構成の大幅な変化
+    let top_domains = |queries: &[(String, Vec<PageResult>)]| -> HashSet<String> {
+        let pages: Vec<PageResult> = queries
+            .iter()
+            .flat_map(|(_, pages)| pages.iter().cloned())
+            .collect();
+        compute_domain_stats(&pages)
+            .into_iter()
+            .take(TOP_DOMAIN_COUNT)
+            .map(|s| s.domain)
+            .collect()
+    };
+    let now_domains = top_domains(current);
+    let before_domains = top_domains(previous);
+    if !now_domains.is_empty() && !before_domains.is_empty() {
+        let overlap = now_domains.intersection(&before_domains).count() as f64
+            / now_domains.len().max(before_domains.len()) as f64;
+        if overlap < MIN_DOMAIN_OVERLAP {
+            anomalies.push(Anomaly {
+                kind: "domain_shift".to_string(),
+                query: None,
+                detail: tr!(
+                    "上位{}ドメインの一致率 {:.0}%（パーソナライズまたは地域設定の変化の可能性）",
+                    "Top {} domains overlap {:.0}% (personalization or locale change?)",
+                    TOP_DOMAIN_COUNT,
+                    overlap * 100.0
+                ),
+            });
+        }
+    }
+
+    anomalies
+}
+
+// ============================================================
+// 指定した実行との差分（--diff）
+// ============================================================
+#[derive(Serialize, Debug)]
+struct RunDiff {
+    generated_at: String,
+    previous_run: String,
+    queries: Vec<QueryDiff>,
+}
+
+#[derive(Serialize, Debug)]
+struct QueryDiff {
+    query: String,
+    // 前回の実行に無い検索（比較対象なし）
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    new_query: bool,
+    new_urls: Vec<RankedUrl>,
+    dropped_urls: Vec<RankedUrl>,
+    moved: Vec<RankMove>,
+}
+
+#[derive(Serialize, Debug)]
+struct RankedUrl {
+    url: String,
+    rank: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct RankMove {
+    url: String,
+    previous_rank: usize,
+    rank: usize,
+    // 正なら順位が上がった
+    delta: i64,
+}
+
+// 検索単位キー -> URL -> 最高順位
+fn best_ranks_by_search(
+    run_queries: &[(String, Vec<PageResult>)],
+) -> BTreeMap<String, HashMap<String, usize>> {
+    let mut searches: BTreeMap<String, HashMap<String, usize>> = BTreeMap::new();
+    for (_, pages) in run_queries {
+        for page in pages {
+            searches.entry(search_key(page)).or_default();
+        }
+        for (key, rank, url) in absolute_ranked_urls(pages) {
+            let best = searches
+                .entry(key)
+                .or_default()
+                .entry(url.to_string())
+                .or_insert(rank);
+            *best = (*best).min(rank);
+        }
+    }
+    searches
+}
+
+fn compute_query_diff(
+    query: &str,
+    now: &HashMap<String, usize>,
+    before: Option<&HashMap<String, usize>>,
+) -> QueryDiff {
+    let sorted = |mut urls: Vec<RankedUrl>| {
+        urls.sort_by(|a, b| a.rank.cmp(&b.rank).then(a.url.cmp(&b.url)));
+        urls
+    };
+    let empty = HashMap::new();
+    let previous = before.unwrap_or(&empty);
+
+    let new_urls = now
+        .iter()
+        .filter(|(url, _)| !previous.contains_key(*url))
+        .map(|(url, &rank)| RankedUrl {
+            url: url.clone(),
+            rank,
+        })
+        .collect();
+    let dropped_urls = previous
+        .iter()
+        .filter(|(url, _)| !now.contains_key(*url))
+        .map(|(url, &rank)| RankedUrl {
+            url: url.clone(),
+            rank,
+        })
+        .collect();
+    let mut moved: Vec<RankMove> = now
+        .iter()
+        .filter_map(|(url, &rank)| {
+            let &previous_rank = previous.get(url)?;
+            (previous_rank != rank).then(|| RankMove {
+                url: url.clone(),
+                previous_rank,
+                rank,
+                delta: previous_rank as i64 - rank as i64,
+            })
+        })
+        .collect();
+    moved.sort_by(|a, b| a.rank.cmp(&b.rank).then(a.url.cmp(&b.url)));
+
+    QueryDiff {
+        query: query.to_string(),
+        new_query: before.is_none(),
+        new_urls: sorted(new_urls),
+        dropped_urls: sorted(dropped_urls),
+        moved,
+    }
+}
+
+fn write_run_diff(result_base: &Path, previous_run: &Path) -> Result<RunDiff> {
+    let now = best_ranks_by_search(&load_run_queries(result_base)?);
+    let before = best_ranks_by_search(&load_run_queries(previous_run)?);
+    let diff = RunDiff {
+        generated_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        previous_run: previous_run.display().to_string(),
+        queries: now
+            .iter()
+            .map(|(query, urls)| compute_query_diff(query, urls, before.get(query)))
+            .collect(),
+    };
+    fs::write(
+        result_base.join("diff.json"),
+        serde_json::to_string_pretty(&diff)?,
+    )?;
+    Ok(diff)
+}
+
+fn print_run_diff(diff: &RunDiff) {
+    info!(
+        "{}",
+        tr!(
+            "--- {} との差分（新規 / 消失 / 順位変動） ---",
+            "--- Diff against {} (new / dropped / moved) ---",
+            diff.previous_run
+        )
+    );
+    for q in &diff.queries {
+        if q.new_query {
+            info!(
+                "{}",
+                tr!(
+                    "  「{}」 前回の実行に無い検索",
+                    "  \"{}\" not in the previous run",
+                    q.query
+                )
+            );
+            continue;
+        }
+        let up = q.moved.iter().filter(|m| m.delta > 0).count();
+        let down = q.moved.len() - up;
+        info!(
+            "{}",
+            tr!(
+                "  「{}」 新規{} 消失{} 上昇{} 下降{}",
+                "  \"{}\" new {} dropped {} up {} down {}",
+                q.query,
+                q.new_urls.len(),
+                q.dropped_urls.len(),
+                up,
+                down
+            )
+        );
+    }
+    info!("------------------------------------------------------------");
+}
+
+fn extract_domain(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, r)| r)?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    if host.is_empty() { None } else { Some(host) }
+}
+
+// 絞り込みステップは別の検索として扱う
+fn search_key(page: &PageResult) -> String {
+    match &page.refinement {
+        Some(r) => format!("{} [{}]", page.query, r),
+        None => page.query.clone(),
+    }
+}
+
+// ページ内順位をクエリ全体の通し順位に変換して (検索単位キー, 通し順位, URL) を返す
+fn absolute_ranked_urls(pages: &[PageResult]) -> Vec<(String, usize, &str)> {
+    let mut groups: HashMap<String, Vec<&PageResult>> = HashMap::new();
+    for page in pages {
+        groups.entry(search_key(page)).or_default().push(page);
+    }
+
+    let mut ranked = Vec::new();
+    for (key, mut group) in groups {
+        group.sort_by_key(|p| p.page);
+        let mut offset = 0;
+        for page in group {
+            for result in &page.results {
+                ranked.push((
+                    key.clone(),
+                    offset + result.serp_position(),
+                    result.url.as_str(),
+                ));
+            }
+            offset += page.serp_result_count();
+        }
+    }
+    ranked
+}
+
+fn compute_domain_stats(pages: &[PageResult]) -> Vec<DomainStat> {
+    // ドメイン -> (出現回数, 順位合計, 最高順位, 出現クエリ)
+    let mut acc: HashMap<String, (usize, usize, usize, HashSet<String>)> = HashMap::new();
+    for (key, rank, url) in absolute_ranked_urls(pages) {
+        let Some(domain) = extract_domain(url) else {
+            continue;
+        };
+        let entry = acc
+            .entry(domain)
+            .or_insert((0, 0, usize::MAX, HashSet::new()));
+        entry.0 += 1;
+        entry.1 += rank;
+        entry.2 = entry.2.min(rank);
+        entry.3.insert(key);
+    }
+
+    let mut stats: Vec<DomainStat> = acc
+        .into_iter()
+        .map(
+            |(domain, (appearances, rank_sum, best_rank, queries))| DomainStat {
+                domain,
+                appearances,
+                query_count: queries.len(),
+                average_rank: rank_sum as f64 / appearances as f64,
+                best_rank,
+            },
+        )
+        .collect();
+    stats.sort_by(|a, b| {
+        b.appearances
+            .cmp(&a.appearances)
+            .then(a.average_rank.total_cmp(&b.average_rank))
+            .then(a.domain.cmp(&b.domain))
+    });
+    stats
+}
+
+// 前ページと90%以上同じURLが並んだらループとみなす
+const SERP_LOOP_THRESHOLD: f64 = 0.9;
+
+fn result_url_set(results: &[SearchResult]) -> HashSet<String> {
+    results
+        .iter()
+        .map(|r| {
+            r.normalized_url
+                .clone()
+                .unwrap_or_else(|| normalize_url(&r.url))
+        })
+        .collect()
+}
+
+fn is_looping_serp(previous: &HashSet<String>, current: &HashSet<String>) -> bool {
+    if previous.is_empty() || current.is_empty() {
+        return false;
+    }
+    let shared = previous.intersection(current).count();
+    shared as f64 / previous.len().max(current.len()) as f64 >= SERP_LOOP_THRESHOLD
+}
+
+fn compute_dedup_summary(pages: &[PageResult]) -> DedupSummary {
+    let mut groups: BTreeMap<String, Vec<&PageResult>> = BTreeMap::new();
+    for page in pages {
+        groups.entry(search_key(page)).or_default().push(page);
+    }
+
+    let mut summary = DedupSummary::default();
+    // URL -> 出てきた検索の数
+    let mut query_counts: HashMap<String, usize> = HashMap::new();
+    for (key, mut group) in groups {
+        group.sort_by_key(|p| p.page);
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut results = 0;
+        let mut repeated = 0;
+        for result in group.iter().flat_map(|p| &p.results) {
+            // normalized_url を持たない古い結果はここで正規化する
+            let url = result
+                .normalized_url
+                .clone()
+                .unwrap_or_else(|| normalize_url(&result.url));
+            results += 1;
+            if seen.contains(&url) {
+                repeated += 1;
+            } else {
+                *query_counts.entry(url.clone()).or_default() += 1;
+                seen.insert(url);
+            }
+        }
+        summary.repeated_within_query += repeated;
+        if repeated > 0 {
+            summary.queries.push(QueryDedup {
+                query: key,
+                results,
+                repeated,
+            });
+        }
+    }
+    summary.unique_urls = query_counts.len();
+    summary.urls_in_multiple_queries = query_counts.values().filter(|&&n| n > 1).count();
+    summary
+        .queries
+        .sort_by(|a, b| b.repeated.cmp(&a.repeated).then(a.query.cmp(&b.query)));
+    summary
+}
+
+fn print_dedup_summary(summary: &RunSummary) {
+    let dedup = &summary.dedup;
+    info!(
+        "{}",
+        tr!(
+            "重複: 異なるURL {} / 前のページと重複 {} 件 / 複数クエリに出たURL {}",
+            "Duplicates: {} unique URLs / {} repeats of earlier pages / {} URLs in multiple queries",
+            dedup.unique_urls,
+            dedup.repeated_within_query,
+            dedup.urls_in_multiple_queries
+        )
+    );
+    for query in dedup.queries.iter().take(5) {
+        info!(
+            "{}",
+            tr!(
+                "  {}: {} 件中 {} 件が重複",
+                "  {}: {} of {} results were repeats",
+                query.query,
+                query.results,
+                query.repeated
+            )
+        );
+    }
+}
+
+fn write_run_summary(result_base: &Path) -> Result<RunSummary> {
+    let run_queries = load_run_queries(result_base)?;
+    let pages: Vec<PageResult> = run_queries
+        .iter()
+        .flat_map(|(_, pages)| pages.iter().cloned())
+        .collect();
+    let queries: HashSet<&str> = pages.iter().map(|p| p.query.as_str()).collect();
+
+    let previous_run = find_previous_run(result_base);
+    let anomalies = match &previous_run {
+        Some(dir) => detect_anomalies(&run_queries, &load_run_queries(dir)?),
+        None => Vec::new(),
+    };
+
+    let summary = RunSummary {
+        generated_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        query_count: queries.len(),
+        page_count: pages.len(),
+        result_count: pages.iter().map(|p| p.result_count).sum(),
+        top_domains: compute_domain_stats(&pages),
+        previous_run: previous_run
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string()),
+        anomalies,
+        soft_warning_pages: pages
+            .iter()
+            .filter(|p| p.block_state == Some(BlockState::SoftWarning))
+            .count(),
+        discrepancy_pages: pages.iter().filter(|p| p.discrepancy.is_some()).count(),
+        over_budget_pages: pages
+            .iter()
+            .filter(|p| p.timing.is_some_and(|t| t.over_budget))
+            .count(),
+        dedup: compute_dedup_summary(&pages),
+    };
+
+    let json = serde_json::to_string_pretty(&summary)?;
+    fs::write(result_base.join("summary.json"), json)?;
+    Ok(summary)
+}
+
+fn print_domain_table(summary: &RunSummary, limit: usize) {
+    info!(
+        "{}",
+        tr!(
+            "--- 上位ドメイン（出現回数 / 平均順位 / 最高順位 / クエリ数） ---",
+            "--- Top domains (count / avg rank / best rank / queries) ---"
+        )
+    );
+    for stat in summary.top_domains.iter().take(limit) {
+        info!(
+            "{}",
+            tr!(
+                "  {:>4}回  平均{:>6.1}位  最高{:>3}位  {:>3}クエリ  {}",
+                "  {:>4}x  avg #{:>6.1}  best #{:>3}  {:>3} queries  {}",
+                stat.appearances,
+                stat.average_rank,
+                stat.best_rank,
+                stat.query_count,
+                stat.domain
+            )
+        );
+    }
+    info!("------------------------------------------------------------");
+}
+
+fn print_anomalies(summary: &RunSummary) {
+    let Some(previous) = &summary.previous_run else {
+        return;
+    };
+    if summary.anomalies.is_empty() {
+        info!(
+            "{}",
+            tr!(
+                "前回実行（{}）との比較: 異常なし",
+                "Compared with the previous run ({}): no anomalies",
+                previous
+            )
+        );
+        return;
+    }
+    info!("========================================");
+    warn!(
+        "{}",
+        tr!(
+            "警告: 前回実行（{}）との比較で {} 件の異常を検出",
+            "Warning: {1} anomalies compared with the previous run ({0})",
+            previous,
+            summary.anomalies.len()
+        )
+    );
+    for anomaly in &summary.anomalies {
+        match &anomaly.query {
+            Some(q) => info!(
+                "{}",
+                tr!(
+                    "  [{}] 「{}」 {}",
+                    "  [{}] \"{}\" {}",
+                    anomaly.kind,
+                    q,
+                    anomaly.detail
+                )
+            ),
+            None => info!("  [{}] {}", anomaly.kind, anomaly.detail),
+        }
+    }
+    info!("========================================");
+}
+
+// ============================================================
+// 順位トラッキング（tracked_domains）
+// ============================================================
+#[derive(Serialize, Debug)]
+struct DomainRanking {
+    query: String,
+    domain: String,
+    // 取得した全ページを通しての最高順位（圏外なら None）
+    best_rank: Option<usize>,
+    url: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct RankingReport {
+    generated_at: String,
+    tracked_domains: Vec<String>,
+    rankings: Vec<DomainRanking>,
+}
+
+// サブドメインも一致とみなす（www. と大文字小文字は呼び出し側で揃える）
+fn domain_matches(domain: &str, tracked: &str) -> bool {
+    domain == tracked || domain.ends_with(&format!(".{}", tracked))
+}
+
+fn compute_rankings(
+    run_queries: &[(String, Vec<PageResult>)],
+    tracked: &[String],
+) -> Vec<DomainRanking> {
+    let mut rankings = Vec::new();
+    for (_, pages) in run_queries {
+        // 結果0件の検索も圏外として載せる
+        let keys: BTreeSet<String> = pages.iter().map(search_key).collect();
+        let mut best: BTreeMap<(String, &str), (usize, &str)> = BTreeMap::new();
+        for (key, rank, url) in absolute_ranked_urls(pages) {
+            let Some(domain) = extract_domain(url) else {
+                continue;
+            };
+            for t in tracked.iter().filter(|t| domain_matches(&domain, t)) {
+                let entry = best.entry((key.clone(), t)).or_insert((rank, url));
+                if rank < entry.0 {
+                    *entry = (rank, url);
+                }
+            }
+        }
+        for key in keys {
+            for t in tracked {
+                let hit = best.get(&(key.clone(), t.as_str()));
+                rankings.push(DomainRanking {
+                    query: key.clone(),
+                    domain: t.clone(),
+                    best_rank: hit.map(|(rank, _)| *rank),
+                    url: hit.map(|(_, url)| url.to_string()),
+                });
+            }
+        }
+    }
+    rankings
+}
+
+fn write_rankings(result_base: &Path, config: &Config) -> Result<Option<RankingReport>> {
+    if config.tracked_domains.is_empty() {
+        return Ok(None);
+    }
+    let tracked: Vec<String> = config
+        .tracked_domains
+        .iter()
+        .map(|d| {
+            let d = d.trim().to_lowercase();
+            d.strip_prefix("www.").unwrap_or(&d).to_string()
+        })
+        .collect();
+    let report = RankingReport {
+        generated_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        rankings: compute_rankings(&load_run_queries(result_base)?, &tracked),
+        tracked_domains: tracked,
+    };
+    fs::write(
+        result_base.join("rankings.json"),
+        serde_json::to_string_pretty(&report)?,
+    )?;
+
+    let mut file = fs::File::create(result_base.join("rankings.csv"))?;
+    if config.sink_options.csv.bom {
+        file.write_all(b"\xEF\xBB\xBF")?;
+    }
+    let mut writer = csv::Writer::from_writer(file);
+    writer.write_record(["query", "domain", "best_rank", "url"])?;
+    for r in &report.rankings {
+        writer.write_record([
+            r.query.as_str(),
+            &r.domain,
+            &r.best_rank.map(|n| n.to_string()).unwrap_or_default(),
+            r.url.as_deref().unwrap_or(""),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(Some(report))
+}
+
+fn print_rankings(report: &RankingReport) {
+    info!(
+        "{}",
+        tr!(
+            "--- 追跡ドメインの最高順位 ---",
+            "--- Best rank of tracked domains ---"
+        )
+    );
+    for r in &report.rankings {
+        match r.best_rank {
+            Some(rank) => info!(
+                "{}",
+                tr!(
+                    "  {:>4}位  {}  「{}」",
+                    "  #{:>4}  {}  \"{}\"",
+                    rank,
+                    r.domain,
+                    r.query
+                )
+            ),
+            None => info!(
+                "{}",
+                tr!(
+                    "  圏外    {}  「{}」",
+                    "  not found  {}  \"{}\"",
+                    r.domain,
+                    r.query
+                )
+            ),
+        }
+    }
+    info!("------------------------------------------------------------");
+}
+
+// ============================================================
+// 実行レポート（report.html / report.md）
+// ============================================================
+struct QueryReport {
+    name: String,
+    pages: usize,
+    results: usize,
+    // ブロック・警告付きだったページ
+    flagged_pages: usize,
+    top: Vec<(usize, String, String)>,
+}
+
+fn collect_query_reports(run_queries: &[(String, Vec<PageResult>)]) -> Vec<QueryReport> {
+    let mut reports: Vec<QueryReport> = run_queries
+        .iter()
+        .map(|(name, pages)| {
+            let mut numbered: Vec<&PageResult> =
+                pages.iter().filter(|p| p.refinement.is_none()).collect();
+            numbered.sort_by_key(|p| p.page);
+            let mut offset = 0;
+            let mut top = Vec::new();
+            for page in numbered {
+                for r in &page.results {
+                    if top.len() < 10 {
+                        top.push((offset + r.serp_position(), r.title.clone(), r.url.clone()));
+                    }
+                }
+                offset += page.serp_result_count();
+            }
+            QueryReport {
+                name: name.clone(),
+                pages: pages.len(),
+                results: pages.iter().map(|p| p.result_count).sum(),
+                flagged_pages: pages.iter().filter(|p| p.block_state.is_some()).count(),
+                top,
+            }
+        })
+        .collect();
+    reports.sort_by(|a, b| a.name.cmp(&b.name));
+    reports
+}
+
+fn write_run_report(result_base: &Path, format: ReportFormat) -> Result<PathBuf> {
+    let queries = collect_query_reports(&load_run_queries(result_base)?);
+    let checkpoint: Checkpoint = match fs::read_to_string(result_base.join("checkpoint.json")) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(_) => Checkpoint::default(),
+    };
+    let run_name = result_base
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (file_name, body) = match format {
+        ReportFormat::Markdown => (
+            "report.md",
+            render_report_markdown(&run_name, &queries, &checkpoint),
+        ),
+        _ => (
+            "report.html",
+            render_report_html(&run_name, &queries, &checkpoint),
+        ),
+    };
+    let path = result_base.join(file_name);
+    fs::write(&path, body)?;
+    Ok(path)
+}
+
+// レポートの見出し・列名（表示言語に合わせる）
+struct ReportLabels {
+    lang: &'static str,
+    title: &'static str,
+    by_query: &'static str,
+    query: &'static str,
+    pages: &'static str,
+    results: &'static str,
+    flagged_pages: &'static str,
+    durations: &'static str,
+    search: &'static str,
+    duration: &'static str,
+    errors: &'static str,
+    time: &'static str,
+    message: &'static str,
+    top: &'static str,
+    rank: &'static str,
+    result_title: &'static str,
+    no_results: &'static str,
+}
+
+fn report_labels() -> ReportLabels {
+    if english() {
+        ReportLabels {
+            lang: "en",
+            title: "Run report",
+            by_query: "By query",
+            query: "Query",
+            pages: "Pages",
+            results: "Results",
+            flagged_pages: "Flagged pages",
+            durations: "Durations",
+            search: "Search",
+            duration: "Duration",
+            errors: "Errors",
+            time: "Time",
+            message: "Message",
+            top: "Top 10",
+            rank: "Rank",
+            result_title: "Title",
+            no_results: "No results",
+        }
+    } else {
+        ReportLabels {
+            lang: "ja",
+            title: "実行レポート",
+            by_query: "クエリ別",
+            query: "クエリ",
+            pages: "ページ",
+            results: "結果",
+            flagged_pages: "警告ページ",
+            durations: "所要時間",
+            search: "検索",
+            duration: "所要時間",
+            errors: "エラー",
+            time: "時刻",
+            message: "内容",
+            top: "上位10件",
+            rank: "順位",
+            result_title: "タイトル",
+            no_results: "結果なし",
+        }
+    }
+}
+
+// 「クエリ 3（完了 2 / スキップ 1）」のような集計行
+fn report_totals(queries: &[QueryReport], checkpoint: &Checkpoint) -> String {
+    let mut totals = tr!(
+        "クエリ {}（完了 {} / スキップ {}）、ページ {}、結果 {}、エラー {}",
+        "{} queries ({} completed / {} skipped), {} pages, {} results, {} errors",
+        queries.len(),
+        checkpoint.completed_queries.len(),
+        checkpoint.skipped_queries.len(),
+        queries.iter().map(|q| q.pages).sum::<usize>(),
+        queries.iter().map(|q| q.results).sum::<usize>(),
+        checkpoint.errors.len()
+    );
+    if !checkpoint.looping_queries.is_empty() {
+        totals.push_str(&tr!(
+            "、ループで打ち切り {}",
+            ", {} stopped on looping results",
+            checkpoint.looping_queries.len()
+        ));
+    }
+    totals
+}
+
+fn render_report_markdown(
+    run_name: &str,
+    queries: &[QueryReport],
+    checkpoint: &Checkpoint,
+) -> String {
+    let l = report_labels();
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+    let mut md = format!("# {} {}\n\n", l.title, run_name);
+    md.push_str(&format!("{}\n\n", report_totals(queries, checkpoint)));
+
+    md.push_str(&format!(
+        "## {}\n\n| {} | {} | {} | {} |\n|---|---:|---:|---:|\n",
+        l.by_query, l.query, l.pages, l.results, l.flagged_pages
+    ));
+    for q in queries {
+        md.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            cell(&q.name),
+            q.pages,
+            q.results,
+            q.flagged_pages
+        ));
+    }
+
+    if !checkpoint.durations_ms.is_empty() {
+        md.push_str(&format!(
+            "\n## {}\n\n| {} | {} |\n|---|---:|\n",
+            l.durations, l.search, l.duration
+        ));
+        for (query, ms) in &checkpoint.durations_ms {
+            md.push_str(&format!(
+                "| {} | {} |\n",
+                cell(query),
+                format_elapsed(chrono::TimeDelta::milliseconds(*ms))
+            ));
+        }
+    }
+
+    if !checkpoint.errors.is_empty() {
+        md.push_str(&format!(
+            "\n## {}\n\n| {} | {} | {} |\n|---|---|---|\n",
+            l.errors, l.time, l.search, l.message
+        ));
+        for e in &checkpoint.errors {
+            md.push_str(&format!(
+                "| {} | {} | {} |\n",
+                e.at,
+                cell(&e.query),
+                cell(&e.message)
+            ));
+        }
+    }
+
+    md.push_str(&format!("\n## {}\n", l.top));
+    for q in queries {
+        md.push_str(&format!("\n### {}\n\n", q.name));
+        if q.top.is_empty() {
+            md.push_str(&format!("{}\n", l.no_results));
+            continue;
+        }
+        md.push_str(&format!(
+            "| {} | {} | URL |\n|---:|---|---|\n",
+            l.rank, l.result_title
+        ));
+        for (rank, title, url) in &q.top {
+            md.push_str(&format!("| {} | {} | {} |\n", rank, cell(title), cell(url)));
+        }
+    }
+    md
+}
+
+fn render_report_html(run_name: &str, queries: &[QueryReport], checkpoint: &Checkpoint) -> String {
+    let l = report_labels();
+    let mut query_rows = String::new();
+    for q in queries {
+        query_rows.push_str(&format!(
+            "<tr{}><td><a href=\"#{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            if q.results == 0 {
+                " class=\"empty\""
+            } else {
+                ""
+            },
+            escape_html(&q.name),
+            escape_html(&q.name),
+            q.pages,
+            q.results,
+            q.flagged_pages
+        ));
+    }
+
+    let mut duration_rows = String::new();
+    for (query, ms) in &checkpoint.durations_ms {
+        duration_rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            escape_html(query),
+            format_elapsed(chrono::TimeDelta::milliseconds(*ms))
+        ));
+    }
+
+    let mut error_rows = String::new();
+    for e in &checkpoint.errors {
+        error_rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&e.at),
+            escape_html(&e.query),
+            escape_html(&e.message)
+        ));
+    }
+
+    let mut tops = String::new();
+    for q in queries {
+        tops.push_str(&format!("<h3 id=\"{0}\">{0}</h3>\n", escape_html(&q.name)));
+        if q.top.is_empty() {
+            tops.push_str(&format!("<p>{}</p>\n", l.no_results));
+            continue;
+        }
+        tops.push_str(&format!(
+            "<table>\n<tr><th>{}</th><th>{}</th><th>URL</th></tr>\n",
+            l.rank, l.result_title
+        ));
+        for (rank, title, url) in &q.top {
+            tops.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td><a href=\"{2}\">{2}</a></td></tr>\n",
+                rank,
+                escape_html(title),
+                escape_html(url)
+            ));
+        }
+        tops.push_str("</table>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<title>report: {title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 1em; }}
+table {{ border-collapse: collapse; margin-bottom: 1em; }}
+th, td {{ border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; font-size: 13px; }}
+tr.empty {{ background: #fff0f0; }}
+td {{ word-break: break-all; }}
+</style>
+</head>
+<body>
+<h1>{report_title} {title}</h1>
+<p>{totals}</p>
+<h2>{by_query}</h2>
+<table>
+<tr><th>{query}</th><th>{pages}</th><th>{results}</th><th>{flagged_pages}</th></tr>
+{query_rows}</table>
+<h2>{durations}</h2>
+<table>
+<tr><th>{search}</th><th>{duration}</th></tr>
+{duration_rows}</table>
+<h2>{errors}</h2>
+<table>
+<tr><th>{time}</th><th>{search}</th><th>{message}</th></tr>
+{error_rows}</table>
+<h2>{top}</h2>
+{tops}</body>
+</html>
+"#,
+        lang = l.lang,
+        title = escape_html(run_name),
+        report_title = l.title,
+        totals = report_totals(queries, checkpoint),
+        by_query = l.by_query,
+        query = l.query,
+        pages = l.pages,
+        results = l.results,
+        flagged_pages = l.flagged_pages,
+        durations = l.durations,
+        search = l.search,
+        duration = l.duration,
+        errors = l.errors,
+        time = l.time,
+        message = l.message,
+        top = l.top,
+    )
+}
+
+// モバイル版: 結果リンクの中の見出しは div[role="heading"]
+fn extract_google_mobile_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    // 連続スクロールで追加された結果は #search の外（arc-srp_*）に入る
+    let link_selector = Selector::parse("#search a[href], [id^='arc-srp_'] a[href]").unwrap();
+    let heading_selector = Selector::parse(r#"div[role="heading"][aria-level="3"], h3"#).unwrap();
+    let container_selector = Selector::parse("div.MjjYud, div[data-hveid]").unwrap();
+    let snippet_selector =
+        Selector::parse(r#"div.VwiC3b, div[style*="-webkit-line-clamp"]"#).unwrap();
+    let cite_selector = Selector::parse("cite, span.qzEoUe").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for element in document.select(&link_selector) {
+        let Some(heading) = element.select(&heading_selector).next() else {
+            continue;
+        };
+        let href = element.value().attr("href").unwrap_or("");
+        let unwrapped = unwrap_google_redirect(href);
+        let url = unwrapped.clone().unwrap_or_else(|| href.to_string());
+        let title = element_text(heading);
+        if !url.starts_with("http") || title.is_empty() || !seen_urls.insert(normalize_url(&url)) {
+            continue;
+        }
+        let mut result = SearchResult::new(title, url);
+        result.raw_url = unwrapped.map(|_| href.to_string());
+        result.extend_from_attr("ved", element, "data-ved");
+        if let Some(container) = enclosing_element(element, &container_selector) {
+            result.fill_details(container, &snippet_selector, &cite_selector);
+        }
+        results.push(result);
+    }
+    results
+}
+
+// 抽出方法を上から順に試し、結果が取れた最初の方法を使う
+// jsname: 設定のセレクタ / container: div.g の中の見出し付きリンク / heuristic: ページ中の見出し付き外部リンク
+fn extract_search_results(
+    html: &str,
+    selectors: &SelectorsConfig,
+) -> (Vec<SearchResult>, Option<&'static str>) {
+    let document = Html::parse_document(html);
+    let title_selector = Selector::parse(&selectors.result_title).unwrap();
+    let strategies = [
+        ("jsname", selectors.result_link.as_str(), false),
+        ("container", "div.g a[href]", true),
+        ("heuristic", "a[href]", true),
+    ];
+
+    for (i, (name, link_selector, external_only)) in strategies.into_iter().enumerate() {
+        let link_selector = Selector::parse(link_selector).unwrap();
+        let results =
+            collect_google_results(&document, &link_selector, &title_selector, external_only);
+        if results.is_empty() {
+            continue;
+        }
+        if i > 0 {
+            warn!(
+                "{}",
+                tr!(
+                    "  結果セレクタで0件のため {} で抽出しました（{}件）",
+                    "  Result selector matched nothing; extracted {} results with {}",
+                    name,
+                    results.len()
+                )
+            );
+        }
+        return (results, Some(name));
+    }
+    (Vec::new(), None)
+}
+
+// 見出しを含むリンクを結果として集める。external_only なら Google 自身へのリンクを除く
+fn collect_google_results(
+    document: &Html,
+    link_selector: &Selector,
+    title_selector: &Selector,
+    external_only: bool,
+) -> Vec<SearchResult> {
+    let container_selector = Selector::parse("div.MjjYud, div.g").unwrap();
+    let snippet_selector =
+        Selector::parse(r#"div.VwiC3b, div[data-sncf="1"], div[style*="-webkit-line-clamp"]"#)
+            .unwrap();
+    let cite_selector = Selector::parse("cite").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for element in document.select(link_selector) {
+        let href = element.value().attr("href").unwrap_or("");
+        let unwrapped = unwrap_google_redirect(href);
+        let url = unwrapped.clone().unwrap_or_else(|| href.to_string());
+        if external_only && !is_external_result_url(&url) {
+            continue;
+        }
+        let title = element
+            .select(title_selector)
+            .next()
+            .map(|h3| h3.text().collect::<String>())
+            .unwrap_or_default();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            result.raw_url = unwrapped.map(|_| href.to_string());
+            result.extend_from_attr("ved", element, "data-ved");
+            if let Some(container) = enclosing_element(element, &container_selector) {
+                result.fill_details(container, &snippet_selector, &cite_selector);
+            }
+            results.push(result);
+        }
+    }
+    results
+}
+
+// host が domain そのものかそのサブドメインなら true
+fn host_matches_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches('.').to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+}
+
+// include_domains / exclude_domains を当てる（remove なら消し、flag なら印を付ける）
+// 除外した件数を返す。除外があれば残った結果に元の順位（serp_rank）を残す
+fn apply_domain_filters(results: &mut Vec<SearchResult>, config: &Config) -> usize {
+    if config.include_domains.is_empty() && config.exclude_domains.is_empty() {
+        return 0;
+    }
+    let matches_any =
+        |host: &str, domains: &[String]| domains.iter().any(|d| host_matches_domain(host, d));
+    let verdict = |result: &SearchResult| {
+        let host = url::Url::parse(&result.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        if matches_any(&host, &config.exclude_domains) {
+            Some("excluded")
+        } else if !config.include_domains.is_empty() {
+            Some(if matches_any(&host, &config.include_domains) {
+                "included"
+            } else {
+                "not_included"
+            })
+        } else {
+            None
+        }
+    };
+    match config.domain_filter_mode {
+        DomainFilterMode::Remove => {
+            let before = results.len();
+            let mut kept = Vec::with_capacity(before);
+            for (i, mut result) in results.drain(..).enumerate() {
+                if !matches!(verdict(&result), Some("excluded" | "not_included")) {
+                    result.serp_rank = Some(i + 1);
+                    kept.push(result);
+                }
+            }
+            let removed = before - kept.len();
+            if removed == 0 {
+                for result in &mut kept {
+                    result.serp_rank = None;
+                }
+            }
+            *results = kept;
+            removed
+        }
+        DomainFilterMode::Flag => {
+            for result in results.iter_mut() {
+                if let Some(label) = verdict(result) {
+                    result
+                        .extensions
+                        .insert("domain_filter".to_string(), label.into());
+                }
+            }
+            0
+        }
+    }
+}
+
+// 保存しない空ページの警告。フィルターで全件除外したページは保存したうえで別の表示にする
+fn log_filtered_or_empty(parsed: &ParsedPage) {
+    if parsed.results.is_empty() && parsed.filtered_count > 0 {
+        info!(
+            "{}",
+            tr!(
+                "  ドメインフィルターで全 {} 件を除外しました",
+                "  All {} results were removed by the domain filter",
+                parsed.filtered_count
+            )
+        );
+    } else if parsed.results.is_empty() {
+        warn!(
+            "{}",
+            tr!(
+                "  警告: 検索結果が見つかりませんでした",
+                "  Warning: no search results found"
+            )
+        );
+    }
+}
+
+// 結果として数えてよいURL（http(s) で Google のサービス以外）
+fn is_external_result_url(url: &str) -> bool {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return false;
+    };
+    matches!(parsed.scheme(), "http" | "https")
+        && parsed.host_str().is_some_and(|h| {
+            !(h.starts_with("google.") || h.contains(".google.") || h.ends_with(".gstatic.com"))
+        })
+}
+
+// 見出し数と抽出件数が大きく食い違えば、セレクタ破損等による取りこぼしを疑う
+fn check_extraction_count(
+    html: &str,
+    heading_selector: &str,
+    extracted: usize,
+) -> Option<ExtractionDiscrepancy> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(heading_selector).unwrap();
+    let headings = document.select(&selector).count();
+    // 重複URLの除外やURLを持たない見出しがあるので多少の差は許す
+    let tolerance = (headings / 5).max(2);
+    (headings.abs_diff(extracted) > tolerance).then_some(ExtractionDiscrepancy {
+        headings,
+        extracted,
+    })
+}
+
+// 件数は行内で最大の整数（2ページ目以降は「… 2 ページ目」も含むため）、
+// 検索時間は「秒」「seconds」が続く小数
+fn parse_result_stats(text: &str) -> (Option<u64>, Option<u64>) {
+    let mut total: Option<u64> = None;
+    let mut time_ms = None;
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == ',' || chars[i] == '.') {
+            i += 1;
+        }
+        let token: String = chars[start..i].iter().collect();
+        let token = token.trim_end_matches([',', '.']);
+        let rest: String = chars[i..].iter().collect();
+        let rest = rest.trim_start();
+        if token.contains('.') {
+            if (rest.starts_with('秒') || rest.starts_with("sec"))
+                && let Ok(secs) = token.replace(',', "").parse::<f64>()
+            {
+                time_ms = Some((secs * 1000.0).round() as u64);
+            }
+        } else if let Ok(n) = token.replace(',', "").parse::<u64>() {
+            total = Some(total.map_or(n, |t| t.max(n)));
+        }
+    }
+    (total, time_ms)
+}
+
+// 取得したHTMLを解析し、HTML自体はここで手放す。
+// low_memory では結果以外（それぞれページ全体を再パースする）の解析を省く
+fn parse_fetched_page(
+    engine: &dyn SearchEngine,
+    html: String,
+    search_type: SearchType,
+    config: &Config,
+) -> ParsedPage {
+    if config.low_memory && search_type.is_web() {
+        let (results, extraction_strategy) = engine.parse_results_with_strategy(&html);
+        ParsedPage {
+            results,
+            extraction_strategy,
+            ..Default::default()
+        }
+    } else {
+        engine.parse_page(&html, search_type)
+    }
+}
+
+// ページを離れる前にレンダラーのJSヒープを回収させる
+fn release_page_memory(tab: &Tab) {
+    use headless_chrome::protocol::cdp::HeapProfiler::CollectGarbage;
+
+    if let Err(e) = tab.call_method(CollectGarbage(None)) {
+        warn!("{}", tr!("  GC要求エラー: {}", "  GC request error: {}", e));
+    }
+}
+
+fn extract_related_queries(html: &str, selector: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(selector).unwrap();
+    let mut queries: Vec<String> = Vec::new();
+    for link in document.select(&selector) {
+        let text = element_text(link);
+        if !text.is_empty() && !queries.contains(&text) {
+            queries.push(text);
+        }
+    }
+    queries
+}
+
+fn print_discrepancy(discrepancy: &ExtractionDiscrepancy) {
+    warn!(
+        "{}",
+        tr!(
+            "  警告: 抽出件数の食い違い（見出し {} 件 / 抽出 {} 件）。パーサーの取りこぼしの可能性",
+            "  Warning: result count mismatch ({} headings / {} extracted). The parser may be missing results",
+            discrepancy.headings,
+            discrepancy.extracted
+        )
+    );
+    log_action("extraction_discrepancy", serde_json::json!(discrepancy));
+}
+
+// 広告1件分の各要素のセレクタ
+struct AdSelectors {
+    item: &'static str,
+    link: &'static str,
+    title: &'static str,
+    display_url: &'static str,
+    // 広告主ドメインを直接持っている属性
+    domain_attr: Option<&'static str>,
+}
+
+fn extract_ads(
+    html: &str,
+    blocks: &[(&str, AdPlacement)],
+    selectors: &AdSelectors,
+) -> Vec<AdResult> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse(selectors.item).unwrap();
+    let link_selector = Selector::parse(selectors.link).unwrap();
+    let title_selector = Selector::parse(selectors.title).unwrap();
+    let display_selector = Selector::parse(selectors.display_url).unwrap();
+    let mut ads = Vec::new();
+
+    for (block, placement) in blocks {
+        let block_selector = Selector::parse(block).unwrap();
+        let mut position = 0;
+        for item in document
+            .select(&block_selector)
+            .flat_map(|b| b.select(&item_selector))
+        {
+            let Some(link) = item.select(&link_selector).next() else {
+                continue;
+            };
+            let url = link.value().attr("href").unwrap_or("").to_string();
+            let title = item
+                .select(&title_selector)
+                .map(element_text)
+                .find(|t| !t.is_empty())
+                .unwrap_or_default();
+            if url.is_empty() || title.is_empty() {
+                continue;
+            }
+            let display_url = item
+                .select(&display_selector)
+                .map(element_text)
+                .find(|t| !t.is_empty());
+            let advertiser_domain = selectors
+                .domain_attr
+                .and_then(|attr| item.value().attr(attr))
+                .map(|d| d.trim_start_matches("www.").to_lowercase())
+                .or_else(|| ad_landing_url(&url).as_deref().and_then(extract_domain))
+                .or_else(|| {
+                    // 表示URLはスキーム無し（example.com › ...）のことが多い
+                    let shown = display_url.as_deref()?.split('›').next()?.trim();
+                    let shown = if shown.contains("://") {
+                        shown.to_string()
+                    } else {
+                        format!("https://{}", shown)
+                    };
+                    extract_domain(&shown)
+                });
+            position += 1;
+            ads.push(AdResult {
+                placement: *placement,
+                position,
+                title,
+                url,
+                advertiser_domain,
+                display_url,
+            });
+        }
+    }
+    ads
+}
+
+// 広告の計測用リダイレクト（/aclk?...&adurl=<URL>）から遷移先を取り出す
+fn ad_landing_url(href: &str) -> Option<String> {
+    let parsed = url::Url::parse(href).ok()?;
+    parsed
+        .query_pairs()
+        .find(|(k, _)| k == "adurl")
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| v.starts_with("http://") || v.starts_with("https://"))
+}
+
+fn extract_google_serp_features(html: &str) -> Option<SerpFeatures> {
+    let document = Html::parse_document(html);
+    let snippet_block = Selector::parse("div.xpdopen, block-component").unwrap();
+    let snippet_text = Selector::parse(
+        r#"span.hgKElc, div[data-attrid="wa:/description"], div[data-tts="answers"]"#,
+    )
+    .unwrap();
+    let snippet_link = Selector::parse("a[href^='http']").unwrap();
+    let h3 = Selector::parse("h3").unwrap();
+    let paa_question = Selector::parse("div.related-question-pair").unwrap();
+    let paa_button = Selector::parse("[role='button']").unwrap();
+    let knowledge_panel =
+        Selector::parse("div.kp-wholepage, #rhs .kp-blk, #rhs [data-attrid='title']").unwrap();
+
+    let featured_snippet = document.select(&snippet_block).find_map(|block| {
+        let text = block
+            .select(&snippet_text)
+            .map(element_text)
+            .find(|t| !t.is_empty())?;
+        let link = block.select(&snippet_link).next();
+        Some(FeaturedSnippet {
+            text,
+            source_url: link.and_then(|a| a.value().attr("href").map(str::to_string)),
+            source_title: link
+                .and_then(|a| a.select(&h3).next())
+                .map(element_text)
+                .filter(|t| !t.is_empty()),
+        })
+    });
+
+    let mut people_also_ask: Vec<String> = Vec::new();
+    for pair in document.select(&paa_question) {
+        let question = pair
+            .value()
+            .attr("data-q")
+            .map(str::to_string)
+            .or_else(|| pair.select(&paa_button).next().map(element_text))
+            .unwrap_or_default();
+        if !question.is_empty() && !people_also_ask.contains(&question) {
+            people_also_ask.push(question);
+        }
+    }
+
+    let features = SerpFeatures {
+        featured_snippet,
+        people_also_ask,
+        knowledge_panel: document.select(&knowledge_panel).next().is_some(),
+    };
+    let empty = features.featured_snippet.is_none()
+        && features.people_also_ask.is_empty()
+        && !features.knowledge_panel;
+    (!empty).then_some(features)
+}
+
+// 3件の店舗が並ぶローカルパック。詳細行は「評価(件数) · 価格 · 業種」「業種 · 住所」の順に並ぶ
+fn extract_google_local_results(html: &str) -> Vec<LocalResult> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse("div.VkpGBb").unwrap();
+    let name_selector = Selector::parse("div[role='heading'], .dbg0pd, .OSrXXb").unwrap();
+    let rating_selector = Selector::parse("span.yi40Hd, span.MW4etd").unwrap();
+    let reviews_selector = Selector::parse("span.RDApEe, span.UY7F9").unwrap();
+    let detail_selector = Selector::parse(".rllt__details > div").unwrap();
+    let mut results = Vec::new();
+
+    for item in document.select(&item_selector) {
+        let Some(name) = item
+            .select(&name_selector)
+            .map(element_text)
+            .find(|t| !t.is_empty())
+        else {
+            continue;
+        };
+        let rating = item
+            .select(&rating_selector)
+            .map(element_text)
+            .find_map(|t| t.replace(',', ".").parse::<f64>().ok());
+        let review_count = item
+            .select(&reviews_selector)
+            .map(element_text)
+            .find_map(|t| {
+                t.chars()
+                    .filter(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse::<u64>()
+                    .ok()
+            });
+        // 店名・評価の行を除いた最初の詳細行の末尾を住所とみなす
+        // 評価の行は数字の有無ではなく評価・件数の要素を含むかで見分ける（番地の数字で誤判定しない）
+        let address = item
+            .select(&detail_selector)
+            .filter(|line| {
+                line.select(&rating_selector).next().is_none()
+                    && line.select(&reviews_selector).next().is_none()
+            })
+            .map(element_text)
+            .find(|line| !line.is_empty() && *line != name)
+            .and_then(|line| line.rsplit('·').next().map(|s| s.trim().to_string()))
+            .filter(|s| !s.is_empty());
+
+        results.push(LocalResult {
+            position: results.len() + 1,
+            name,
+            rating,
+            review_count,
+            address,
+        });
+    }
+    results
+}
+
+// ニュースタブ（tbm=nws）。配信元と掲載時刻は extensions に入れる
+fn extract_google_news_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse("div.SoaBEf, div.dbsr").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let title_selector = Selector::parse("div[role='heading']").unwrap();
+    let source_selector = Selector::parse(".MgUUmf, .NUnG9d span, .CEMjEf span").unwrap();
+    let time_selector = Selector::parse(".OSrXXb span, .LfVVr, .ZE0LJd span").unwrap();
+    let snippet_selector = Selector::parse(".GI74Re, .Y3v8qd").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for item in document.select(&item_selector) {
+        let Some(link) = item.select(&link_selector).next() else {
+            continue;
+        };
+        let href = link.value().attr("href").unwrap_or("");
+        let url = unwrap_google_redirect(href).unwrap_or_else(|| href.to_string());
+        let title = item
+            .select(&title_selector)
+            .map(element_text)
+            .find(|t| !t.is_empty())
+            .unwrap_or_default();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            let mut result = SearchResult::new(title, url);
+            result.snippet = item
+                .select(&snippet_selector)
+                .map(element_text)
+                .find(|t| !t.is_empty());
+            for (key, selector) in [("source", &source_selector), ("published", &time_selector)] {
+                if let Some(text) = item
+                    .select(selector)
+                    .map(element_text)
+                    .find(|t| !t.is_empty())
+                {
+                    result.extensions.insert(key.to_string(), text.into());
+                }
+            }
+            results.push(result);
+        }
+    }
+    results
+}
+
+// 画像タブ（tbm=isch）。URLは画像ではなく掲載ページ
+fn extract_google_image_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse("div[data-lpage]").unwrap();
+    let img_selector = Selector::parse("img[alt]").unwrap();
+    let mut results = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+
+    for item in document.select(&item_selector) {
+        let url = item.value().attr("data-lpage").unwrap_or("").to_string();
+        let title = item
+            .select(&img_selector)
+            .filter_map(|img| img.value().attr("alt"))
+            .map(|alt| alt.trim().to_string())
+            .find(|alt| !alt.is_empty())
+            .unwrap_or_default();
+
+        // URL重複チェック（上位優先で残す）
+        if !url.is_empty() && !title.is_empty() && !seen_urls.contains(&normalize_url(&url)) {
+            seen_urls.insert(normalize_url(&url));
+            results.push(SearchResult::new(title, url));
+        }
+    }
+    results
+}
+
+// /url?q=<URL>&sa=... 形式（一部のバリアントで出る）のリダイレクトを元のURLに戻す
+fn unwrap_google_redirect(href: &str) -> Option<String> {
+    let base = url::Url::parse("https://www.google.com").unwrap();
+    let parsed = base.join(href).ok()?;
+    let is_google = parsed
+        .host_str()
+        .is_some_and(|h| h.starts_with("www.google.") || h.starts_with("google."));
+    if !is_google || parsed.path() != "/url" {
+        return None;
+    }
+    parsed
+        .query_pairs()
+        .find(|(k, _)| k == "q" || k == "url")
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| v.starts_with("http://") || v.starts_with("https://"))
+}
+
+struct BrowserManager<'a> {
+    browser: Option<Browser>,
+    config: &'a Config,
+    proxy_pool: ProxyPool,
+    current_proxy: Option<usize>,
+    user_agents: Vec<UserAgentProfile>,
+    current_user_agent: usize,
+    rng: WyRand,
+    // 起動するたびに増える（タブ共有時に、他のワーカーが再起動したかを見分ける）
+    generation: u64,
+    // プロキシを失敗扱いにしたので、次の restart は restart_strategy によらず起動し直す
+    needs_relaunch: bool,
+    // 使用中のプールのプロファイル（browser より後に drop してロックを外す）
+    pool_lease: Option<ProfileLease>,
+}
+
+impl<'a> BrowserManager<'a> {
+    fn new(config: &'a Config) -> Result<Self> {
+        let user_agents: Vec<UserAgentProfile> = if config.user_agents.is_empty() {
+            let default = match config.device {
+                DeviceKind::Desktop => DEFAULT_USER_AGENT,
+                DeviceKind::Mobile => DEFAULT_MOBILE_USER_AGENT,
+            };
+            vec![UserAgentProfile::parse(default)?]
+        } else {
+            config
+                .user_agents
+                .iter()
+                .map(|ua| UserAgentProfile::parse(ua))
+                .collect::<Result<_>>()?
+        };
+        let mobile = config.device == DeviceKind::Mobile;
+        if let Some(ua) = user_agents.iter().find(|ua| ua.mobile != mobile) {
+            anyhow::bail!(tr!(
+                "device = {:?} と一致しない User-Agent: {}",
+                "User-Agent does not match device = {:?}: {}",
+                config.device,
+                ua.user_agent
+            ));
+        }
+        Ok(Self {
+            browser: None,
+            config,
+            proxy_pool: ProxyPool::from_config(config),
+            current_proxy: None,
+            user_agents,
+            current_user_agent: 0,
+            rng: WyRand::new(),
+            generation: 0,
+            needs_relaunch: false,
+            pool_lease: None,
+        })
+    }
+
+    fn get_or_create(&mut self) -> Result<&Browser> {
+        if self.browser.is_none() {
+            self.launch()?;
+        }
+        Ok(self.browser.as_ref().unwrap())
+    }
+
+    fn restart(&mut self) -> Result<&Browser> {
+        let needs_relaunch = std::mem::take(&mut self.needs_relaunch);
+        // プールのプロファイルは Cookie を残したいので消さずに別のプロファイルへ切り替える
+        if self.config.restart_strategy == RestartStrategy::Clear
+            && self.config.profile_pool.is_none()
+            && !needs_relaunch
+            && let Some(browser) = &self.browser
+        {
+            info!(
+                "{}",
+                tr!(
+                    "ブラウザを再利用中（Cookie・ストレージを消去）...",
+                    "Reusing the browser (clearing cookies and storage)..."
+                )
+            );
+            match reset_browser_session(browser, &create_engine(self.config).home_url()) {
+                Ok(()) => return Ok(self.browser.as_ref().unwrap()),
+                Err(e) => warn!(
+                    "{}",
+                    tr!(
+                        "消去に失敗したため再起動します: {}",
+                        "Clearing failed, relaunching instead: {}",
+                        e
+                    )
+                ),
+            }
+        }
+        info!(
+            "{}",
+            tr!(
+                "ブラウザを再起動中（profileリセット）...",
+                "Restarting the browser (profile reset)..."
+            )
+        );
+        self.browser = None;
+        thread::sleep(Duration::from_millis(2000));
+        self.launch()?;
+        Ok(self.browser.as_ref().unwrap())
+    }
+
+    fn launch(&mut self) -> Result<()> {
+        // 直前のプロファイルを返してから次を選ぶ（最後に使ったものは選ばれにくい）
+        self.browser = None;
+        self.pool_lease = None;
+        let lease = match &self.config.profile_pool {
+            Some(pool) => Some(ProfilePool::open(pool)?.checkout(self.config)?),
+            None => None,
+        };
+        self.launch_with(lease)
+    }
+
+    // lease があればそのプロファイルで、無ければ profile_dir を作り直して起動する
+    fn launch_with(&mut self, lease: Option<ProfileLease>) -> Result<()> {
+        self.current_proxy = self.proxy_pool.next();
+        self.current_user_agent = self.rng.generate_range(0..self.user_agents.len());
+        let user_data_dir = match &lease {
+            Some(lease) => lease.dir.clone(),
+            None => reset_profile_dir(self.config)?,
+        };
+        self.browser = Some(launch_browser(
+            self.config,
+            user_data_dir,
+            self.current_proxy(),
+            self.user_agent(),
+        )?);
+        self.pool_lease = lease;
+        self.generation += 1;
+        Ok(())
+    }
+
+    // このセッションで名乗る User-Agent
+    fn user_agent(&self) -> &UserAgentProfile {
+        &self.user_agents[self.current_user_agent]
+    }
+
+    fn current_proxy(&self) -> Option<&ProxyConfig> {
+        self.current_proxy.map(|i| &self.proxy_pool.entries[i])
+    }
+
+    fn mark_current_proxy_failed(&mut self) {
+        if let Some(index) = self.current_proxy {
+            self.proxy_pool.mark_failed(index);
+        }
+        self.needs_relaunch = true;
+    }
+}
+
+// restart_strategy = "clear" の再利用前処理。新しいタブだけを残して履歴と sessionStorage を捨て、
+// Cookie・キャッシュと検索エンジンのオリジンのストレージを消す
+fn reset_browser_session(browser: &Browser, home_url: &str) -> Result<()> {
+    use headless_chrome::protocol::cdp::Network::{ClearBrowserCache, ClearBrowserCookies};
+    use headless_chrome::protocol::cdp::Storage::ClearDataForOrigin;
+
+    let tab = browser.new_tab()?;
+    let old_tabs: Vec<Arc<Tab>> = browser
+        .get_tabs()
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| !Arc::ptr_eq(t, &tab))
+        .cloned()
+        .collect();
+    for old_tab in old_tabs {
+        let _ = old_tab.close(false);
+    }
+    tab.call_method(ClearBrowserCookies(None))?;
+    tab.call_method(ClearBrowserCache(None))?;
+    tab.call_method(ClearDataForOrigin {
+        origin: url::Url::parse(home_url)?.origin().ascii_serialization(),
+        storage_Types: "all".to_string(),
+    })?;
+    Ok(())
+}
+
+// 1つのワーカーから見たブラウザ。tabs = 1 ならブラウザを占有し、
+// 2以上なら同じブラウザを他のワーカーとタブ単位で共有する
+enum BrowserSlot<'m, 'a> {
+    Owned(&'m mut BrowserManager<'a>),
+    Shared {
+        manager: &'m Mutex<BrowserManager<'a>>,
+        tab: Option<Arc<Tab>>,
+        // タブを開いたときのブラウザの generation
+        generation: u64,
+        // プロキシ失敗を記録したので次の restart でブラウザごと再起動する
+        proxy_failed: bool,
+    },
+}
+
+impl BrowserSlot<'_, '_> {
+    fn shared<'m, 'a>(manager: &'m Mutex<BrowserManager<'a>>) -> BrowserSlot<'m, 'a> {
+        BrowserSlot::Shared {
+            manager,
+            tab: None,
+            generation: 0,
+            proxy_failed: false,
+        }
+    }
+
+    // 占有時は余分なタブを閉じて最初のタブを使い、共有時は自分用のタブを開く
+    fn tab(&mut self) -> Result<Arc<Tab>> {
+        match self {
+            BrowserSlot::Owned(manager) => get_active_tab(manager),
+            BrowserSlot::Shared {
+                manager,
+                tab,
+                generation,
+                ..
+            } => {
+                let mut manager = manager.lock().unwrap();
+                if let Some(tab) = tab
+                    && *generation == manager.generation
+                {
+                    return Ok(tab.clone());
+                }
+                let new_tab = manager.get_or_create()?.new_tab()?;
+                *generation = manager.generation;
+                drop(manager);
+                // 裏にあるタブでも document.hasFocus() などが前面と同じ値を返すようにする
+                use headless_chrome::protocol::cdp::Emulation::SetFocusEmulationEnabled;
+                if let Err(e) = new_tab.call_method(SetFocusEmulationEnabled { enabled: true }) {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "フォーカスエミュレーション設定エラー: {}",
+                            "Failed to enable focus emulation: {}",
+                            e
+                        )
+                    );
+                }
+                *tab = Some(new_tab.clone());
+                Ok(new_tab)
+            }
+        }
+    }
+
+    // 占有時はブラウザを再起動して profile をリセットする。共有時は他のタブを巻き込まないよう
+    // 自分のタブを閉じるだけにし、プロキシ失敗を記録していたときだけブラウザごと再起動する
+    fn restart(&mut self) -> Result<()> {
+        match self {
+            BrowserSlot::Owned(manager) => manager.restart().map(|_| ()),
+            BrowserSlot::Shared {
+                manager,
+                tab,
+                generation,
+                proxy_failed,
+            } => {
+                if let Some(tab) = tab.take() {
+                    let _ = tab.close(false);
+                }
+                let mut manager = manager.lock().unwrap();
+                if std::mem::take(proxy_failed) && *generation == manager.generation {
+                    manager.restart()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn mark_current_proxy_failed(&mut self) {
+        match self {
+            BrowserSlot::Owned(manager) => manager.mark_current_proxy_failed(),
+            BrowserSlot::Shared {
+                manager,
+                generation,
+                proxy_failed,
+                ..
+            } => {
+                let mut manager = manager.lock().unwrap();
+                // 他のワーカーが再起動した後なら、このタブのエラーはその巻き添え
+                if *generation == manager.generation {
+                    manager.mark_current_proxy_failed();
+                    *proxy_failed = true;
+                }
+            }
+        }
+    }
+
+    fn current_proxy(&self) -> Option<ProxyConfig> {
+        match self {
+            BrowserSlot::Owned(manager) => manager.current_proxy().cloned(),
+            BrowserSlot::Shared { manager, .. } => manager.lock().unwrap().current_proxy().cloned(),
+        }
+    }
+
+    fn user_agent(&self) -> UserAgentProfile {
+        match self {
+            BrowserSlot::Owned(manager) => manager.user_agent().clone(),
+            BrowserSlot::Shared { manager, .. } => manager.lock().unwrap().user_agent().clone(),
+        }
+    }
+}
+
+// ============================================================
+// ブラウザ起動
+// ============================================================
+// 起動引数とCDP上書きで名乗る値（ペルソナ整合性チェックの対象）
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.7499.110 Safari/537.36";
+const DEFAULT_MOBILE_USER_AGENT: &str = "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.7499.110 Mobile Safari/537.36";
+
+// --lang と Preferences の intl.accept_languages（ja-JP なら "ja-JP,ja"）
+fn launch_languages(locale: &str) -> String {
+    match locale.split_once('-') {
+        Some((language, _)) => format!("{},{}", locale, language),
+        None => locale.to_string(),
+    }
+}
+
+// Accept-Language（ja-JP なら "ja-JP,ja;q=0.9,en-US;q=0.8,en;q=0.7"）
+fn accept_language(locale: &str) -> String {
+    let language = locale.split('-').next().unwrap_or(locale);
+    let mut header = locale.to_string();
+    if language != locale {
+        header.push_str(&format!(",{};q=0.9", language));
+    }
+    if !language.eq_ignore_ascii_case("en") {
+        header.push_str(",en-US;q=0.8,en;q=0.7");
+    }
+    header
+}
+
+// ja-JP -> (hl=ja, gl=jp)
+fn locale_hl_gl(locale: &str) -> (String, Option<String>) {
+    let mut parts = locale.split('-');
+    let hl = parts.next().unwrap_or(locale).to_lowercase();
+    let gl = parts.next().map(str::to_lowercase);
+    (hl, gl)
+}
+
+// リビジョン指定時のダウンロード設定。キャッシュに無ければ起動時にダウンロードされる
+fn chromium_fetcher_options(config: &Config) -> Result<Option<FetcherOptions>> {
+    let Some(revision) = &config.chromium_revision else {
+        return Ok(None);
+    };
+    let cache_dir = get_base_path(&config.chromium_cache_dir);
+    fs::create_dir_all(&cache_dir)?;
+    Ok(Some(
+        FetcherOptions::default()
+            .with_revision(Revision::Specific(revision.clone()))
+            .with_install_dir(Some(cache_dir))
+            .with_allow_standard_dirs(false),
+    ))
+}
+
+// 実行に使ったブラウザのビルド情報を実行ディレクトリの browser.json に残す
+#[derive(Serialize, Debug)]
+struct BrowserInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chromium_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chromium_revision: Option<String>,
+    product: String,
+    revision: String,
+    user_agent: String,
+    protocol_version: String,
+}
+
+fn write_browser_info(
+    browser: &Browser,
+    config: &Config,
+    result_base: &Path,
+) -> Result<BrowserInfo> {
+    let version = browser.get_version()?;
+    info!(
+        "{}",
+        tr!(
+            "ブラウザ: {}（{}）",
+            "Browser: {} ({})",
+            version.product,
+            version.revision
+        )
+    );
+    let info = BrowserInfo {
+        chromium_path: match config.chromium_revision {
+            Some(_) => None,
+            None => Some(config.chromium_path.clone()),
+        },
+        chromium_revision: config.chromium_revision.clone(),
+        product: version.product,
+        revision: version.revision,
+        user_agent: version.user_agent,
+        protocol_version: version.protocol_version,
+    };
+    fs::write(
+        result_base.join("browser.json"),
+        serde_json::to_string_pretty(&info)?,
+    )?;
+    Ok(info)
+}
+
+// ============================================================
+// 実行マニフェスト（manifest.json）
+// ============================================================
+const MANIFEST_VERSION: u32 = 1;
+
+// 実行を再現できるよう、実際に使った設定とバージョンを実行ディレクトリに残す
+#[derive(Serialize, Debug)]
+struct RunManifest {
+    manifest_version: u32,
+    gnezdo_version: String,
+    // 各ページJSONの schema_version
+    result_schema_version: u32,
+    started_at: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resumed_at: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<RunPhase>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chromium: Option<BrowserInfo>,
+    // デフォルト適用後の設定（秘密情報は伏せる）
+    config: Config,
+}
+
+impl RunManifest {
+    // 再開時は最初の開始時刻を引き継ぐ
+    fn load_or_new(result_base: &Path, config: &Config, run_start: DateTime<Local>) -> Self {
+        let now = run_start.format("%Y-%m-%dT%H:%M:%S").to_string();
+        let previous: Option<serde_json::Value> =
+            fs::read_to_string(result_base.join("manifest.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+        let (started_at, resumed_at) = match &previous {
+            Some(prev) => {
+                let mut resumed: Vec<String> = prev["resumed_at"]
+                    .as_array()
+                    .map(|a| {
+                        a.iter()
+                            .filter_map(|v| v.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                resumed.push(now.clone());
+                (
+                    prev["started_at"].as_str().unwrap_or(&now).to_string(),
+                    resumed,
+                )
+            }
+            None => (now, Vec::new()),
+        };
+        Self {
+            manifest_version: MANIFEST_VERSION,
+            gnezdo_version: env!("CARGO_PKG_VERSION").to_string(),
+            result_schema_version: RESULT_SCHEMA_VERSION,
+            started_at,
+            resumed_at,
+            finished_at: None,
+            status: None,
+            chromium: None,
+            config: redact_config(config),
+        }
+    }
+
+    fn finish(&mut self, status: RunPhase) {
+        self.finished_at = Some(Local::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+        self.status = Some(status);
+    }
+
+    fn write(&self, result_base: &Path) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(fs::write(result_base.join("manifest.json"), json)?));
+        if let Err(e) = result {
+            warn!(
+                "{}",
+                tr!(
+                    "manifest.json 書き込みエラー: {}",
+                    "Failed to write manifest.json: {}",
+                    e
+                )
+            );
+        }
+    }
+}
+
+// ============================================================
+// User-Agent（起動引数・CDP上書き・UA-CH はすべてここから作る）
+// ============================================================
+#[derive(Debug, Clone)]
+struct UserAgentProfile {
+    // 送信・navigator.userAgent 用（実際のChromeと同じく Chrome/143.0.0.0 に縮小）
+    user_agent: String,
+    // UA-CH の fullVersion（設定のUAに書かれた版）
+    full_version: String,
+    major_version: String,
+    // navigator.platform
+    nav_platform: &'static str,
+    // UA-CH の platform / platformVersion
+    ch_platform: &'static str,
+    ch_platform_version: &'static str,
+    mobile: bool,
+}
+
+impl UserAgentProfile {
+    fn parse(user_agent: &str) -> Result<Self> {
+        let Some(full_version) = user_agent
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix("Chrome/"))
+        else {
+            anyhow::bail!(tr!(
+                "Chrome/<バージョン> を含まない User-Agent: {}",
+                "User-Agent without Chrome/<version>: {}",
+                user_agent
+            ));
+        };
+        let major_version = full_version.split('.').next().unwrap_or("");
+        if major_version.parse::<u32>().is_err() {
+            anyhow::bail!(tr!(
+                "User-Agent のバージョンを読めません: {}",
+                "Cannot read the User-Agent version: {}",
+                user_agent
+            ));
+        }
+        let (nav_platform, ch_platform, ch_platform_version) = if user_agent.contains("Android") {
+            ("Linux armv81", "Android", "14.0.0")
+        } else if user_agent.contains("Windows NT") {
+            ("Win32", "Windows", "19.0.0.0")
+        } else if user_agent.contains("Macintosh") {
+            ("MacIntel", "macOS", "14.0.0")
+        } else if user_agent.contains("X11; Linux") {
+            ("Linux x86_64", "Linux", "")
+        } else {
+            anyhow::bail!(tr!(
+                "User-Agent のOSが Windows / macOS / Linux / Android のいずれでもありません: {}",
+                "User-Agent OS is not Windows / macOS / Linux / Android: {}",
+                user_agent
+            ));
+        };
+        Ok(Self {
+            user_agent: user_agent.replace(
+                &format!("Chrome/{}", full_version),
+                &format!("Chrome/{}.0.0.0", major_version),
+            ),
+            full_version: full_version.to_string(),
+            major_version: major_version.to_string(),
+            nav_platform,
+            ch_platform,
+            ch_platform_version,
+            mobile: ch_platform == "Android",
+        })
+    }
+
+    fn metadata(&self) -> headless_chrome::protocol::cdp::Emulation::UserAgentMetadata {
+        use headless_chrome::protocol::cdp::Emulation::{UserAgentBrandVersion, UserAgentMetadata};
+
+        let brand = |name: &str, version: &str| UserAgentBrandVersion {
+            brand: name.to_string(),
+            version: version.to_string(),
+        };
+        // Android の Chrome は architecture / bitness を空で返す
+        let (architecture, bitness) = if self.mobile { ("", "") } else { ("x86", "64") };
+        UserAgentMetadata {
+            platform: self.ch_platform.to_string(),
+            platform_version: self.ch_platform_version.to_string(),
+            architecture: architecture.to_string(),
+            model: "".to_string(),
+            mobile: self.mobile,
+            bitness: Some(bitness.to_string()),
+            wow_64: Some(false),
+            full_version_list: Some(vec![
+                brand("Chromium", &self.full_version),
+                brand("Google Chrome", &self.full_version),
+                brand("Not/A)Brand", "99.0.0.0"),
+            ]),
+            full_version: Some(self.full_version.clone()),
+            brands: Some(vec![
+                brand("Chromium", &self.major_version),
+                brand("Google Chrome", &self.major_version),
+                brand("Not/A)Brand", "99"),
+            ]),
+            form_factors: None,
+        }
+    }
+}
+
+fn launch_browser(
+    config: &Config,
+    user_data_dir: PathBuf,
+    proxy: Option<&ProxyConfig>,
+    user_agent: &UserAgentProfile,
+) -> Result<Browser> {
+    let fetcher_options = chromium_fetcher_options(config)?;
+    let chromium_path = match &config.chromium_revision {
+        Some(revision) => {
+            info!(
+                "{}",
+                tr!(
+                    "Chromium: r{}（{}）",
+                    "Chromium: r{} ({})",
+                    revision,
+                    config.chromium_cache_dir
+                )
+            );
+            None
+        }
+        None => {
+            let path = get_base_path(&config.chromium_path);
+            info!("Chromium: {:?}", path);
+            Some(path)
+        }
+    };
+
+    info!("User-Agent: {}", user_agent.user_agent);
+    let user_agent_arg = format!("--user-agent={}", user_agent.user_agent);
+    let lang_arg = format!("--lang={}", launch_languages(&config.persona.locale));
+
+    let mut args: Vec<&OsStr> = vec![
+        OsStr::new("--no-sandbox"),
+        OsStr::new("--disable-setuid-sandbox"),
+        OsStr::new("--disable-infobars"),
+        OsStr::new("--no-first-run"),
+        OsStr::new("--no-default-browser-check"),
+        OsStr::new("--window-size=1920,1080"),
+        OsStr::new("--start-maximized"),
+        OsStr::new("--disable-blink-features=AutomationControlled"),
+        OsStr::new("--webrtc-ip-handling-policy=default_public_interface_only"),
+        OsStr::new("--force-webrtc-ip-handling-policy"),
+        OsStr::new(&user_agent_arg),
+        OsStr::new(&lang_arg),
+        OsStr::new("--use-angle=d3d11"),
+        OsStr::new("--enable-gpu-rasterization"),
+        OsStr::new("--enable-zero-copy"),
+        OsStr::new("--ignore-gpu-blocklist"),
+        OsStr::new("--disable-dev-shm-usage"),
+        OsStr::new("--disable-notifications"),
+        OsStr::new("--disable-popup-blocking"),
+    ];
+    if config.low_memory {
+        args.extend([
+            OsStr::new("--renderer-process-limit=1"),
+            OsStr::new("--js-flags=--max-old-space-size=256"),
+            OsStr::new("--disk-cache-size=1048576"),
+            OsStr::new("--disable-features=BackForwardCache"),
+        ]);
+    }
+    // 裏に回ったタブのタイマーや描画が間引かれないようにする
+    if config.tabs > 1 {
+        args.extend([
+            OsStr::new("--disable-background-timer-throttling"),
+            OsStr::new("--disable-renderer-backgrounding"),
+            OsStr::new("--disable-backgrounding-occluded-windows"),
+        ]);
+    }
+
+    if !config.uses_geolocation() {
+        args.push(OsStr::new("--disable-geolocation"));
+    }
+
+    // 旧ヘッドレス（headless: true）は UA やAPIの差異で検出されやすいため、
+    // 通常版と同じ実装の新ヘッドレスを引数で指定する
+    if config.headless {
+        args.push(OsStr::new("--headless=new"));
+    }
+
+    let ignore_default_args: Vec<&OsStr> = vec![OsStr::new("--enable-automation")];
+
+    let proxy_server = proxy.map(|p| p.server_arg());
+    if let Some(proxy) = proxy {
+        info!("{}", tr!("プロキシ: {}", "Proxy: {}", proxy.server_arg()));
+        if proxy.scheme == ProxyScheme::Socks5 && proxy.has_auth() {
+            warn!(
+                "{}",
+                tr!(
+                    "警告: ChromiumはSOCKS5の認証に未対応のため、認証情報は無視されます",
+                    "Warning: Chromium does not support SOCKS5 authentication; credentials are ignored"
+                )
+            );
+        }
+    }
+
+    let browser = Browser::new(LaunchOptions {
+        headless: false,
+        window_size: Some((1920, 1080)),
+        sandbox: false,
+        enable_gpu: true,
+        user_data_dir: Some(user_data_dir),
+        path: chromium_path,
+        fetcher_options: fetcher_options.unwrap_or_default(),
+        args,
+        ignore_default_args,
+        disable_default_args: false,
+        idle_browser_timeout: Duration::from_secs(600),
+        proxy_server: proxy_server.as_deref(),
+        ..Default::default()
+    })?;
+
+    Ok(browser)
+}
+
+// ============================================================
+// プロキシ認証（CDP Fetch.authRequired で応答）
+// ============================================================
+fn setup_proxy_auth(tab: &Tab, proxy: Option<&ProxyConfig>) -> Result<()> {
+    let Some(proxy) = proxy else {
+        return Ok(());
+    };
+    if !proxy.has_auth() || proxy.scheme == ProxyScheme::Socks5 {
+        return Ok(());
+    }
+
+    tab.authenticate(proxy.username.clone(), proxy.password.clone())?;
+    tab.enable_fetch(None, Some(true))?;
+
+    Ok(())
+}
+
+// ============================================================
+// CDP Stealth設定
+// ============================================================
+fn setup_stealth_cdp(
+    tab: &Tab,
+    persona: &PersonaConfig,
+    user_agent: &UserAgentProfile,
+) -> Result<()> {
+    use headless_chrome::protocol::cdp::Emulation::SetTimezoneOverride;
+    use headless_chrome::protocol::cdp::Network::SetUserAgentOverride;
+
+    let ua_override = tab.call_method(SetUserAgentOverride {
+        user_agent: user_agent.user_agent.clone(),
+        accept_language: Some(accept_language(&persona.locale)),
+        platform: Some(user_agent.nav_platform.to_string()),
+        user_agent_metadata: Some(user_agent.metadata()),
+    });
+    if let Err(e) = ua_override {
+        anyhow::bail!(tr!(
+            "UA上書き（Network.setUserAgentOverride）に失敗: {}",
+            "UA override (Network.setUserAgentOverride) failed: {}",
+            e
+        ));
+    }
+    if user_agent.mobile {
+        setup_mobile_emulation(tab)?;
+    }
+
+    if let Err(e) = tab.call_method(SetTimezoneOverride {
+        timezone_id: persona.timezone.clone(),
+    }) {
+        anyhow::bail!(tr!(
+            "タイムゾーン上書き（Emulation.setTimezoneOverride）に失敗: {}",
+            "Timezone override (Emulation.setTimezoneOverride) failed: {}",
+            e
+        ));
+    }
+
+    Ok(())
+}
+
+// Pixel 7 相当の画面とタッチ入力
+fn setup_mobile_emulation(tab: &Tab) -> Result<()> {
+    use headless_chrome::protocol::cdp::Emulation::{
+        SetDeviceMetricsOverride, SetTouchEmulationEnabled,
+    };
+
+    if let Err(e) = tab.call_method(SetDeviceMetricsOverride {
+        width: 412,
+        height: 915,
+        device_scale_factor: 2.625,
+        mobile: true,
+        scale: None,
+        screen_width: Some(412),
+        screen_height: Some(915),
+        position_x: None,
+        position_y: None,
+        dont_set_visible_size: None,
+        screen_orientation: None,
+        viewport: None,
+        display_feature: None,
+        device_posture: None,
+    }) {
+        anyhow::bail!(tr!(
+            "画面サイズ上書き（Emulation.setDeviceMetricsOverride）に失敗: {}",
+            "Screen size override (Emulation.setDeviceMetricsOverride) failed: {}",
+            e
+        ));
+    }
+    if let Err(e) = tab.call_method(SetTouchEmulationEnabled {
+        enabled: true,
+        max_touch_points: Some(5),
+    }) {
+        anyhow::bail!(tr!(
+            "タッチ入力の有効化（Emulation.setTouchEmulationEnabled）に失敗: {}",
+            "Enabling touch input (Emulation.setTouchEmulationEnabled) failed: {}",
+            e
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================
+// CDP権限拒否（JSによる位置情報ブロックの下層）
+// ============================================================
+fn deny_permissions_via_cdp(
+    tab: &Tab,
+    home_url: &str,
+    geolocation: Option<&GeolocationConfig>,
+) -> Result<()> {
+    use headless_chrome::protocol::cdp::Browser::{
+        PermissionDescriptor, PermissionSetting, SetPermission,
+    };
+    use headless_chrome::protocol::cdp::Emulation::SetGeolocationOverride;
+
+    let origin = url::Url::parse(home_url)?.origin().ascii_serialization();
+    let permissions = [
+        (
+            "geolocation",
+            match geolocation {
+                Some(_) => PermissionSetting::Granted,
+                None => PermissionSetting::Denied,
+            },
+        ),
+        ("notifications", PermissionSetting::Denied),
+    ];
+    for (name, setting) in permissions {
+        tab.call_method(SetPermission {
+            permission: PermissionDescriptor {
+                name: name.to_string(),
+                sysex: None,
+                user_visible_only: None,
+                allow_without_sanitization: None,
+                allow_without_gesture: None,
+                pan_tilt_zoom: None,
+            },
+            setting,
+            origin: Some(origin.clone()),
+            embedding_origin: None,
+            browser_context_id: None,
+        })?;
+    }
+    if let Some(geo) = geolocation {
+        tab.call_method(SetGeolocationOverride {
+            latitude: Some(geo.latitude),
+            longitude: Some(geo.longitude),
+            accuracy: Some(geo.accuracy),
+            altitude: None,
+            altitude_accuracy: None,
+            heading: None,
+            speed: None,
+        })?;
+    }
+    Ok(())
+}
+
+// ============================================================
+// ペルソナ整合性チェック
+// ============================================================
+fn primary_language(list: &str) -> String {
+    list.split([',', ';'])
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase()
+}
+
+// persona.platform に対応する (navigator.platform, UA内のOSトークン)
+fn expected_platform_tokens(platform: &str) -> (&'static str, &'static str) {
+    match platform.to_lowercase().as_str() {
+        "macos" => ("MacIntel", "Macintosh"),
+        "linux" => ("Linux x86_64", "X11; Linux"),
+        "android" => ("Linux armv81", "Android"),
+        _ => ("Win32", "Windows NT"),
+    }
+}
+
+fn check_persona_consistency(
+    tab: &Arc<Tab>,
+    persona: &PersonaConfig,
+    launch_user_agent: &str,
+) -> Result<Vec<String>> {
+    let mut mismatches = Vec::new();
+    let locale = persona.locale.to_lowercase();
+    let (nav_platform, ua_token) = expected_platform_tokens(&persona.platform);
+
+    // ===== 起動設定側（静的） =====
+    let accept = accept_language(&persona.locale);
+    if primary_language(&accept) != locale {
+        mismatches.push(tr!(
+            "Accept-Language「{}」",
+            "Accept-Language \"{}\"",
+            accept
+        ));
+    }
+    let lang = launch_languages(&persona.locale);
+    if primary_language(&lang) != locale {
+        mismatches.push(tr!("--lang「{}」", "--lang \"{}\"", lang));
+    }
+    if !launch_user_agent.contains(ua_token) {
+        mismatches.push(tr!(
+            "起動引数のUser-AgentのOS",
+            "OS in the launch User-Agent"
+        ));
+    }
+
+    // ===== ページ内（実行時） =====
+    let script = r#"(async () => {
+        const opts = Intl.DateTimeFormat().resolvedOptions();
+        const r = {
+            userAgent: navigator.userAgent,
+            platform: navigator.platform,
+            chPlatform: navigator.userAgentData ? navigator.userAgentData.platform : null,
+            languages: navigator.languages ? Array.from(navigator.languages) : [],
+            timeZone: opts.timeZone,
+            keyQ: null,
+            keyY: null
+        };
+        try {
+            if (navigator.keyboard && navigator.keyboard.getLayoutMap) {
+                const map = await navigator.keyboard.getLayoutMap();
+                r.keyQ = map.get('KeyQ') || null;
+                r.keyY = map.get('KeyY') || null;
+            }
+        } catch (e) {}
+        return JSON.stringify(r);
+    })()"#;
+    let value = tab.evaluate(script, true)?.value;
+    let info: serde_json::Value = value
+        .as_ref()
+        .and_then(|v| v.as_str())
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or(serde_json::json!({}));
+
+    let user_agent = info["userAgent"].as_str().unwrap_or("");
+    if !user_agent.contains(ua_token) {
+        mismatches.push(tr!(
+            "navigator.userAgent「{}」",
+            "navigator.userAgent \"{}\"",
+            user_agent
+        ));
+    }
+    let platform = info["platform"].as_str().unwrap_or("");
+    if platform != nav_platform {
+        mismatches.push(tr!(
+            "navigator.platform「{}」",
+            "navigator.platform \"{}\"",
+            platform
+        ));
+    }
+    match info["chPlatform"].as_str() {
+        Some(ch) if !ch.eq_ignore_ascii_case(&persona.platform) => {
+            mismatches.push(tr!("UA-CH platform「{}」", "UA-CH platform \"{}\"", ch));
+        }
+        None => mismatches.push(tr!(
+            "UA-CH（navigator.userAgentData）が存在しない",
+            "UA-CH (navigator.userAgentData) is missing"
+        )),
+        _ => {}
+    }
+    let first_language = info["languages"][0].as_str().unwrap_or("").to_lowercase();
+    if first_language != locale {
+        mismatches.push(tr!(
+            "navigator.languages[0]「{}」",
+            "navigator.languages[0] \"{}\"",
+            first_language
+        ));
+    }
+    let time_zone = info["timeZone"].as_str().unwrap_or("");
+    if time_zone != persona.timezone {
+        mismatches.push(tr!("タイムゾーン「{}」", "time zone \"{}\"", time_zone));
+    }
+
+    // キーボード配列（取得できる環境のみ）: 仏語圏はAZERTY、独語圏はQWERTZ、それ以外はQWERTY
+    if let (Some(key_q), Some(key_y)) = (info["keyQ"].as_str(), info["keyY"].as_str()) {
+        let expected = match locale.split('-').next().unwrap_or("") {
+            "fr" => ("a", "y"),
+            "de" => ("q", "z"),
+            _ => ("q", "y"),
+        };
+        if (key_q, key_y) != expected {
+            mismatches.push(tr!(
+                "キーボード配列（KeyQ={} KeyY={}）",
+                "keyboard layout (KeyQ={} KeyY={})",
+                key_q,
+                key_y
+            ));
+        }
+    }
+
+    Ok(mismatches)
+}
+
+// 最初のクエリの前に一度だけ実施。strict なら不一致で実行を中止する
+fn run_startup_consistency_check(
+    slot: &mut BrowserSlot,
+    engine: &dyn SearchEngine,
+    config: &Config,
+) -> Result<()> {
+    let persona = &config.persona;
+    if persona.consistency_check == ConsistencyCheckMode::Off {
+        return Ok(());
+    }
+
+    info!(
+        "{}",
+        tr!(
+            "ペルソナ整合性チェック中...",
+            "Checking persona consistency..."
+        )
+    );
+    let checked = slot.tab().and_then(|tab| {
+        let user_agent = slot.user_agent();
+        setup_proxy_auth(&tab, slot.current_proxy().as_ref())?;
+        setup_stealth_cdp(&tab, persona, &user_agent)?;
+        inject_stealth_scripts(&tab, config, true)?;
+        tab.navigate_to(&engine.home_url())?;
+        tab.wait_until_navigated()?;
+        check_persona_consistency(&tab, persona, &user_agent.user_agent)
+    });
+
+    let mismatches = match checked {
+        Ok(m) => m,
+        Err(e) => {
+            warn!(
+                "{}",
+                tr!(
+                    "警告: 整合性チェックを実行できませんでした: {}",
+                    "Warning: could not run the consistency check: {}",
+                    e
+                )
+            );
+            return Ok(());
+        }
+    };
+    if mismatches.is_empty() {
+        info!(
+            "{}",
+            tr!(
+                "ペルソナ整合性チェック: 問題なし",
+                "Persona consistency check: OK"
+            )
+        );
+        return Ok(());
+    }
+
+    info!("========================================");
+    warn!(
+        "{}",
+        tr!(
+            "警告: ペルソナ（{} / {} / {}）と一致しない項目があります",
+            "Warning: some values do not match the persona ({} / {} / {})",
+            persona.locale,
+            persona.timezone,
+            persona.platform
+        )
+    );
+    for m in &mismatches {
+        info!("  - {}", m);
+    }
+    warn!(
+        "{}",
+        tr!(
+            "検出されやすい状態です。",
+            "The browser is likely to be detected."
+        )
+    );
+    info!("========================================");
+
+    if persona.consistency_check == ConsistencyCheckMode::Strict {
+        anyhow::bail!(tr!(
+            "ペルソナ整合性チェック失敗（{}件）",
+            "Persona consistency check failed ({} mismatches)",
+            mismatches.len()
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================
+// ステルス検証（起動後にページ内で自動化の痕跡が残っていないか確認）
+// ============================================================
+// strict モードで検証に失敗した場合のエラー（リトライせず実行を中止する）
+#[derive(Debug)]
+struct StealthCheckError {
+    failures: Vec<String>,
+}
+
+impl std::fmt::Display for StealthCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                "ステルス検証失敗: {}",
+                "Stealth check failed: {}",
+                self.failures.join(" / ")
+            )
+        )
+    }
+}
+
+impl std::error::Error for StealthCheckError {}
+
+// 検出されやすい項目を列挙して返す（空なら問題なし）
+fn check_stealth_layers(tab: &Tab) -> Result<Vec<String>> {
+    let script = r#"(() => {
+        const failures = [];
+        if (navigator.webdriver) failures.push('navigator.webdriver が true');
+        const markers = Object.keys(window).filter(k => /^\$?cdc_|__webdriver|__selenium|__driver|__fxdriver/.test(k));
+        if (markers.length) failures.push('自動化用のプロパティ: ' + markers.join(','));
+        if (!navigator.plugins || navigator.plugins.length === 0) failures.push('navigator.plugins が空');
+        if (!navigator.userAgentData) {
+            failures.push('UAクライアントヒント（navigator.userAgentData）が無い');
+        } else if (!navigator.userAgentData.brands || navigator.userAgentData.brands.length === 0) {
+            failures.push('navigator.userAgentData.brands が空');
+        }
+        if (!window.chrome) failures.push('window.chrome が無い');
+        if (/HeadlessChrome/.test(navigator.userAgent)) failures.push('UAに HeadlessChrome が含まれる');
+        return JSON.stringify(failures);
+    })()"#;
+    let value = tab
+        .evaluate(script, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "[]".to_string());
+    Ok(serde_json::from_str(&value)?)
+}
+
+fn verify_stealth_layers(tab: &Tab, config: &Config) -> Result<()> {
+    if config.stealth_check == ConsistencyCheckMode::Off {
+        return Ok(());
+    }
+    let failures = match check_stealth_layers(tab) {
+        Ok(f) => f,
+        Err(e) => {
+            warn!(
+                "{}",
+                tr!(
+                    "  警告: ステルス検証を実行できませんでした: {}",
+                    "  Warning: could not run the stealth check: {}",
+                    e
+                )
+            );
+            return Ok(());
+        }
+    };
+    if failures.is_empty() {
+        return Ok(());
+    }
+    warn!(
+        "{}",
+        tr!(
+            "  警告: ステルス対策が適用されていない項目があります",
+            "  Warning: some stealth measures were not applied"
+        )
+    );
+    for f in &failures {
+        info!("    - {}", f);
+    }
+    if config.stealth_check == ConsistencyCheckMode::Strict {
+        return Err(StealthCheckError { failures }.into());
+    }
+    Ok(())
+}
+
+// ============================================================
+// JavaScript Stealth Injection（Ver 1.2 強化版）
+// ============================================================
+// B. Geolocation API完全無効化（位置情報を上書きするクエリでは入れない）
+const GEOLOCATION_BLOCK_SCRIPT: &str = r#"
+        (function() {
+            // Geolocation API無効化
+            if (navigator.geolocation) {
+                navigator.geolocation.getCurrentPosition = function(success, error) {
+                    if (error) error({ code: 1, message: 'User denied Geolocation' });
+                };
+                navigator.geolocation.watchPosition = function(success, error) {
+                    if (error) error({ code: 1, message: 'User denied Geolocation' });
+                    return 0;
+                };
+                navigator.geolocation.clearWatch = function() {};
+            }
+            
+            // permissions.query偽装（geolocationを常にdenied）
+            const origPermQuery = navigator.permissions.query.bind(navigator.permissions);
+            navigator.permissions.query = function(descriptor) {
+                if (descriptor.name === 'geolocation') {
+                    return Promise.resolve({ 
+                        state: 'denied',
+                        onchange: null,
+                        addEventListener: function() {},
+                        removeEventListener: function() {}
+                    });
+                }
+                return origPermQuery(descriptor);
+            };
+        })();
+        "#;
+
+// 同じセッション内では同じノイズになるよう、注入時の乱数を種にする
+const NOISE_PRNG_SCRIPT: &str = r#"
+        const gnezdoNoise = (n) => {
+            let t = (__SEED__ + Math.imul(n, 0x6D2B79F5)) | 0;
+            t = Math.imul(t ^ (t >>> 15), t | 1);
+            t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+            return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+        };
+        const gnezdoMarkNative = (fn) => {
+            if (typeof customFunctions !== 'undefined') customFunctions.add(fn);
+            return fn;
+        };"#;
+
+// canvas: 読み出し時だけ一部ピクセルの最下位ビットを反転する（描画内容は変えない）
+const CANVAS_NOISE_SCRIPT: &str = r#"
+        (function() {
+            const noisify = (data) => {
+                for (let i = 0; i < data.length; i += 4) {
+                    if (gnezdoNoise(i) < 0.02) {
+                        const c = i + Math.floor(gnezdoNoise(i + 1) * 3);
+                        data[c] = data[c] ^ 1;
+                    }
+                }
+            };
+            const getImageData = CanvasRenderingContext2D.prototype.getImageData;
+            CanvasRenderingContext2D.prototype.getImageData = gnezdoMarkNative(function(...args) {
+                const image = getImageData.apply(this, args);
+                noisify(image.data);
+                return image;
+            });
+            const noisyCopy = (canvas) => {
+                if (!canvas.width || !canvas.height) return canvas;
+                const ctx = canvas.getContext('2d');
+                if (!ctx) return canvas;
+                const image = getImageData.call(ctx, 0, 0, canvas.width, canvas.height);
+                noisify(image.data);
+                const copy = document.createElement('canvas');
+                copy.width = canvas.width;
+                copy.height = canvas.height;
+                copy.getContext('2d').putImageData(image, 0, 0);
+                return copy;
+            };
+            const toDataURL = HTMLCanvasElement.prototype.toDataURL;
+            HTMLCanvasElement.prototype.toDataURL = gnezdoMarkNative(function(...args) {
+                return toDataURL.apply(noisyCopy(this), args);
+            });
+            const toBlob = HTMLCanvasElement.prototype.toBlob;
+            HTMLCanvasElement.prototype.toBlob = gnezdoMarkNative(function(...args) {
+                return toBlob.apply(noisyCopy(this), args);
+            });
+        })();"#;
+
+// AudioContext: 解析結果とバッファのサンプルにごく小さなノイズを足す
+const AUDIO_NOISE_SCRIPT: &str = r#"
+        (function() {
+            const noised = new WeakSet();
+            const getChannelData = AudioBuffer.prototype.getChannelData;
+            AudioBuffer.prototype.getChannelData = gnezdoMarkNative(function(...args) {
+                const data = getChannelData.apply(this, args);
+                if (!noised.has(data)) {
+                    noised.add(data);
+                    for (let i = 0; i < data.length; i += 100) {
+                        data[i] += (gnezdoNoise(i) - 0.5) * 1e-7;
+                    }
+                }
+                return data;
+            });
+            const getFloatFrequencyData = AnalyserNode.prototype.getFloatFrequencyData;
+            AnalyserNode.prototype.getFloatFrequencyData = gnezdoMarkNative(function(array) {
+                getFloatFrequencyData.call(this, array);
+                for (let i = 0; i < array.length; i++) {
+                    array[i] += (gnezdoNoise(i) - 0.5) * 0.1;
+                }
+            });
+        })();"#;
+
+// Cookie 同意ダイアログ（consent.google.com の「続行する前に」）を自動で閉じる
+const CONSENT_DISMISS_SCRIPT: &str = r#"
+        (function() {
+            const ACTION = '__ACTION__';
+            if (!/(^|\.)google\./.test(location.hostname)) return;
+            const LABELS = {
+                reject: ['Reject all', 'Alle ablehnen', 'Tout refuser', 'Rechazar todo', 'Rifiuta tutto',
+                         'Alles afwijzen', 'Odrzuć wszystko', 'Rejeitar tudo', 'Avvisa alla', 'すべて拒否'],
+                accept: ['Accept all', 'Alle akzeptieren', 'Tout accepter', 'Aceptar todo', 'Accetta tutto',
+                         'Alles accepteren', 'Zaakceptuj wszystko', 'Aceitar tudo', 'Godkänn alla',
+                         'すべて同意', '同意する']
+            };
+
+            const findButton = () => {
+                // consent.google.com へ送るフォーム（set_eom=true が「すべて拒否」）
+                for (const form of document.querySelectorAll('form[action*="consent.google"]')) {
+                    const eom = form.querySelector('input[name="set_eom"]');
+                    if (eom && (eom.value === 'true') === (ACTION === 'reject')) {
+                        const btn = form.querySelector('button, input[type="submit"]');
+                        if (btn) return btn;
+                    }
+                }
+                // 文言で探す（ロケールごと）
+                const all = document.querySelectorAll('button, div[role="button"], input[type="submit"]');
+                for (const b of all) {
+                    const text = (b.innerText || b.value || '').trim();
+                    if (LABELS[ACTION].includes(text)) return b;
+                }
+                return null;
+            };
+
+            let done = false;
+            const dismiss = () => {
+                if (done) return;
+                const btn = findButton();
+                if (!btn) return;
+                done = true;
+                btn.click();
+                console.log('[Gnezdo] 同意ダイアログに応答:', ACTION);
+            };
+
+            // ダイアログは遅れて出ることがあるので最初の30秒間だけ監視する
+            const timer = setInterval(dismiss, 500);
+            setTimeout(() => clearInterval(timer), 30000);
+            document.addEventListener('DOMContentLoaded', dismiss);
+        })();"#;
+
+fn inject_stealth_scripts(tab: &Tab, config: &Config, block_geolocation: bool) -> Result<()> {
+    use headless_chrome::protocol::cdp::Page::AddScriptToEvaluateOnNewDocument;
+
+    // (モジュール名, 有効か, スクリプト)。モジュール名は [stealth] のキーと同じ
+    let stealth = &config.stealth;
+    let modules = [
+        // ===== 基本Stealth =====
+        
+        // webdriver検出回避
+        (
+            "webdriver",
+            stealth.webdriver,
+            r#"Object.defineProperty(navigator, 'webdriver', { get: () => undefined, configurable: true });"#,
+        ),
+        // chrome オブジェクト偽装
+        (
+            "chrome_object",
+            stealth.chrome_object,
+            r#"window.chrome = {
+            runtime: {
+                connect: function() {},
+                sendMessage: function() {},
+                onMessage: { addListener: function() {} },
+                onConnect: { addListener: function() {} },
+                PlatformOs: { MAC: 'mac', WIN: 'win', ANDROID: 'android', CROS: 'cros', LINUX: 'linux', OPENBSD: 'openbsd' },
+                PlatformArch: { ARM: 'arm', X86_32: 'x86-32', X86_64: 'x86-64', MIPS: 'mips', MIPS64: 'mips64' },
+                PlatformNaclArch: { ARM: 'arm', X86_32: 'x86-32', X86_64: 'x86-64', MIPS: 'mips', MIPS64: 'mips64' },
+                RequestUpdateCheckStatus: { THROTTLED: 'throttled', NO_UPDATE: 'no_update', UPDATE_AVAILABLE: 'update_available' },
+                OnInstalledReason: { INSTALL: 'install', UPDATE: 'update', CHROME_UPDATE: 'chrome_update', SHARED_MODULE_UPDATE: 'shared_module_update' },
+                OnRestartRequiredReason: { APP_UPDATE: 'app_update', OS_UPDATE: 'os_update', PERIODIC: 'periodic' }
+            },
+            csi: function() { return {}; },
+            loadTimes: function() { return {}; }
+        };"#,
+        ),
+        // permissions.query 偽装（通知用）
+        (
+            "permissions",
+            stealth.permissions,
+            r#"const originalQuery = window.navigator.permissions.query;
+        window.navigator.permissions.query = (parameters) => (
+            parameters.name === 'notifications' ?
+                Promise.resolve({ state: Notification.permission }) :
+                originalQuery(parameters)
+        );"#,
+        ),
+        // plugins/mimeTypes 偽装
+        (
+            "plugins",
+            stealth.plugins,
+            r#"Object.defineProperty(navigator, 'plugins', {
+            get: () => {
+                const plugins = [
+                    { name: 'Chrome PDF Plugin', filename: 'internal-pdf-viewer', description: 'Portable Document Format' },
+                    { name: 'Chrome PDF Viewer', filename: 'mhjfbmdgcfjbbpaeojofohoefgiehjai', description: '' },
+                    { name: 'Native Client', filename: 'internal-nacl-plugin', description: '' }
+                ];
+                plugins.length = 3;
+                return plugins;
+            }
+        });
+        Object.defineProperty(navigator, 'mimeTypes', {
+            get: () => {
+                const mimeTypes = [
+                    { type: 'application/pdf', suffixes: 'pdf', description: 'Portable Document Format' },
+                    { type: 'application/x-google-chrome-pdf', suffixes: 'pdf', description: 'Portable Document Format' },
+                    { type: 'application/x-nacl', suffixes: '', description: 'Native Client Executable' },
+                    { type: 'application/x-pnacl', suffixes: '', description: 'Portable Native Client Executable' }
+                ];
+                mimeTypes.length = 4;
+                return mimeTypes;
+            }
+        });"#,
+        ),
+        // languages 偽装
+        (
+            "languages",
+            stealth.languages,
+            r#"Object.defineProperty(navigator, 'languages', { get: () => __LANGUAGES__ });"#,
+        ),
+        // hardwareConcurrency 偽装
+        (
+            "hardware_concurrency",
+            stealth.hardware_concurrency,
+            r#"Object.defineProperty(navigator, 'hardwareConcurrency', { get: () => 12 });"#,
+        ),
+        // deviceMemory 偽装
+        (
+            "device_memory",
+            stealth.device_memory,
+            r#"Object.defineProperty(navigator, 'deviceMemory', { get: () => 8 });"#,
+        ),
+        // WebGL 偽装
+        (
+            "webgl",
+            stealth.webgl,
+            r#"const getParameterOriginal = WebGLRenderingContext.prototype.getParameter;
+        WebGLRenderingContext.prototype.getParameter = function(parameter) {
+            if (parameter === 37445) return 'Google Inc. (NVIDIA)';
+            if (parameter === 37446) return 'ANGLE (NVIDIA, NVIDIA GeForce RTX 2080 Ti Direct3D11 vs_5_0 ps_5_0, D3D11)';
+            return getParameterOriginal.call(this, parameter);
+        };
+        const getParameterOriginal2 = WebGL2RenderingContext.prototype.getParameter;
+        WebGL2RenderingContext.prototype.getParameter = function(parameter) {
+            if (parameter === 37445) return 'Google Inc. (NVIDIA)';
+            if (parameter === 37446) return 'ANGLE (NVIDIA, NVIDIA GeForce RTX 2080 Ti Direct3D11 vs_5_0 ps_5_0, D3D11)';
+            return getParameterOriginal2.call(this, parameter);
+        };"#,
+        ),
+        // Brave/Firefox 検出回避
+        (
+            "brave",
+            stealth.brave,
+            r#"Object.defineProperty(navigator, 'brave', { get: () => undefined });
+        delete window.InstallTrigger;"#,
+        ),
+        // Function.prototype.toString 偽装
+        (
+            "function_to_string",
+            stealth.function_to_string,
+            r#"const nativeToString = Function.prototype.toString;
+        const customFunctions = new WeakSet();
+        const proxyHandler = {
+            apply: function(target, thisArg, args) {
+                if (customFunctions.has(thisArg)) return 'function () { [native code] }';
+                return nativeToString.apply(thisArg, args);
+            }
+        };
+        Function.prototype.toString = new Proxy(nativeToString, proxyHandler);
+        customFunctions.add(Function.prototype.toString);"#,
+        ),
+        // ===== 位置情報ポップアップ完全ブロック（Ver 1.2 新機能） =====
+
+        // A. CSS強制非表示
+        (
+            "popup_css",
+            stealth.popup_css,
+            r#"
+        (function() {
+            const style = document.createElement('style');
+            style.textContent = `
+                /* 位置情報ダイアログ本体 */
+                div.gTMtLb[id="lb"],
+                div[role="dialog"][aria-labelledby="lcMwfd"],
+                div.qk7LXc.JHqNkc,
+                /* update-location コンポーネント全体 */
+                update-location,
+                /* 位置情報スナックバー */
+                location-snackbar-with-learn-more,
+                /* ライトボックス背景 */
+                div.kJFf0c.KUf18 {
+                    display: none !important;
+                    visibility: hidden !important;
+                    opacity: 0 !important;
+                    pointer-events: none !important;
+                }
+            `;
+            (document.head || document.documentElement).appendChild(style);
+        })();
+        "#,
+        ),
+        // C. 精密クリック + 常時監視（強化版）
+        (
+            "popup_dismiss",
+            stealth.popup_dismiss,
+            r#"
+        (function() {
+            const SELECTORS = {
+                dialog: 'div[role="dialog"][aria-labelledby="lcMwfd"]',
+                dialogAlt: 'div.qk7LXc.JHqNkc[role="dialog"]',
+                lightbox: 'div.gTMtLb#lb',
+                laterButton: [
+                    'g-raised-button[jsaction="click:O6N1Pb"]',
+                    'div.mpQYc g-raised-button'
+                ],
+                closeButton: 'a[aria-label="閉じる"]'
+            };
+            
+            let lastDismissTime = 0;
+            const DEBOUNCE_MS = 100;
+            
+            const dismiss = () => {
+                const now = Date.now();
+                if (now - lastDismissTime < DEBOUNCE_MS) return false;
+                lastDismissTime = now;
+                
+                const dialog = document.querySelector(SELECTORS.dialog) ||
+                               document.querySelector(SELECTORS.dialogAlt);
+                if (!dialog) return false;
+                
+                const style = window.getComputedStyle(dialog);
+                if (style.display === 'none' || 
+                    style.visibility === 'hidden' || 
+                    parseFloat(style.opacity) === 0) {
+                    return false;
+                }
+                
+                // 「後で」ボタン検索・クリック
+                for (const sel of SELECTORS.laterButton) {
+                    const btn = dialog.querySelector(sel) || document.querySelector(sel);
+                    if (btn) {
+                        btn.click();
+                        btn.dispatchEvent(new MouseEvent('click', { bubbles: true, cancelable: true }));
+                        console.log('[Gnezdo] 「後で」クリック成功');
+                        setTimeout(remove, 50);
+                        return true;
+                    }
+                }
+                
+                // 閉じるボタン（フォールバック）
+                const close = document.querySelector(SELECTORS.closeButton);
+                if (close) { 
+                    close.click(); 
+                    console.log('[Gnezdo] 「閉じる」クリック');
+                    setTimeout(remove, 50);
+                    return true; 
+                }
+                
+                // テキストベース検索（最終手段）
+                const all = dialog.querySelectorAll('div[role="button"], button, g-raised-button');
+                for (const b of all) {
+                    const text = b.innerText?.trim();
+                    if (text === '後で' || text === 'Later' || text === 'Not now') {
+                        b.click();
+                        console.log('[Gnezdo] テキストマッチでクリック:', text);
+                        setTimeout(remove, 50);
+                        return true;
+                    }
+                }
+                return false;
+            };
+            
+            const remove = () => {
+                const lb = document.querySelector(SELECTORS.lightbox);
+                if (lb) { 
+                    lb.style.display = 'none'; 
+                    lb.remove(); 
+                    console.log('[Gnezdo] ライトボックス削除');
+                }
+            };
+            
+            // ===== 監視機構（多重化） =====
+            
+            // 1. 初回実行（ページロード後）
+            setTimeout(dismiss, 300);
+            setTimeout(dismiss, 600);
+            setTimeout(dismiss, 1000);
+            
+            // 2. MutationObserver（DOM変更検知）
+            const observer = new MutationObserver((mutations) => {
+                for (const m of mutations) {
+                    if (m.type === 'childList' && m.addedNodes.length > 0) {
+                        dismiss();
+                        return;
+                    }
+                    if (m.type === 'attributes') {
+                        const t = m.target;
+                        if (t.id === 'lb' || 
+                            t.matches?.('[role="dialog"]') ||
+                            t.classList?.contains('gTMtLb')) {
+                            dismiss();
+                            return;
+                        }
+                    }
+                }
+            });
+            
+            const startObserver = () => {
+                observer.observe(document.body, {
+                    childList: true,
+                    subtree: true,
+                    attributes: true,
+                    attributeFilter: ['style', 'class', 'aria-hidden', 'hidden']
+                });
+            };
             
             // 3. 定期チェック（500ms間隔）
             setInterval(dismiss, 500);
@@ -636,327 +7882,4914 @@ fn inject_stealth_scripts(tab: &Tab) -> Result<()> {
                     setTimeout(dismiss, 50);
                 }, { passive: true, capture: true });
             });
-            
-            // 5. フォーカス/表示状態変更時
-            window.addEventListener('focus', dismiss);
-            document.addEventListener('visibilitychange', () => {
-                if (document.visibilityState === 'visible') dismiss();
+            
+            // 5. フォーカス/表示状態変更時
+            window.addEventListener('focus', dismiss);
+            document.addEventListener('visibilitychange', () => {
+                if (document.visibilityState === 'visible') dismiss();
+            });
+            
+            // 6. ページ遷移系イベント
+            window.addEventListener('popstate', dismiss);
+            window.addEventListener('hashchange', dismiss);
+            
+            // 7. requestAnimationFrame監視（最初の30秒間のみ）
+            let rafActive = true;
+            const rafCheck = () => {
+                if (!rafActive) return;
+                dismiss();
+                setTimeout(() => requestAnimationFrame(rafCheck), 200);
+            };
+            requestAnimationFrame(rafCheck);
+            setTimeout(() => { rafActive = false; }, 30000);
+            
+            // 開始
+            if (document.body) {
+                startObserver();
+            } else {
+                document.addEventListener('DOMContentLoaded', () => {
+                    startObserver();
+                    dismiss();
+                });
+            }
+            
+            console.log('[Gnezdo] 位置情報ポップアップ監視開始 (Ver 1.3)');
+        })();
+        "#,
+        ),
+    ];
+    // navigator.languages は Accept-Language と同じ並びにする
+    let accept = accept_language(&config.persona.locale);
+    let languages: Vec<&str> = accept
+        .split(',')
+        .map(|l| l.split(';').next().unwrap_or(l).trim())
+        .collect();
+    let languages = serde_json::to_string(&languages)?;
+    let mut scripts: Vec<(String, String)> = modules
+        .into_iter()
+        .filter(|(_, enabled, _)| *enabled)
+        .map(|(name, _, source)| {
+            (
+                name.to_string(),
+                source.replace("__LANGUAGES__", &languages),
+            )
+        })
+        .collect();
+    if block_geolocation && stealth.geolocation_block {
+        scripts.push((
+            "geolocation_block".to_string(),
+            GEOLOCATION_BLOCK_SCRIPT.to_string(),
+        ));
+    }
+    if config.consent != ConsentAction::Off {
+        let action = format!("{:?}", config.consent).to_lowercase();
+        scripts.push((
+            "consent".to_string(),
+            CONSENT_DISMISS_SCRIPT.replace("__ACTION__", &action),
+        ));
+    }
+    if stealth.canvas_noise || stealth.audio_noise {
+        let seed = WyRand::new().generate::<u32>() as i32;
+        scripts.push((
+            "noise_prng".to_string(),
+            NOISE_PRNG_SCRIPT.replace("__SEED__", &seed.to_string()),
+        ));
+        if stealth.canvas_noise {
+            scripts.push(("canvas_noise".to_string(), CANVAS_NOISE_SCRIPT.to_string()));
+        }
+        if stealth.audio_noise {
+            scripts.push(("audio_noise".to_string(), AUDIO_NOISE_SCRIPT.to_string()));
+        }
+    }
+    scripts.extend(load_user_stealth_scripts(config)?);
+
+    for (name, script) in &scripts {
+        let injected = tab.call_method(AddScriptToEvaluateOnNewDocument {
+            source: script.clone(),
+            world_name: None,
+            include_command_line_api: None,
+            run_immediately: None,
+        });
+        if let Err(e) = injected {
+            anyhow::bail!(tr!(
+                "ステルススクリプト {} の注入に失敗: {}",
+                "Failed to inject stealth script {}: {}",
+                name,
+                e
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// stealth_scripts_dir 直下の .js をファイル名順に読む（名前はファイル名）
+fn load_user_stealth_scripts(config: &Config) -> Result<Vec<(String, String)>> {
+    let Some(dir) = &config.stealth_scripts_dir else {
+        return Ok(Vec::new());
+    };
+    let dir = get_base_path(dir);
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .map_err(|e| {
+            anyhow::anyhow!(tr!(
+                "stealth_scripts_dir の読み込みに失敗（{}）: {}",
+                "Failed to read stealth_scripts_dir ({}): {}",
+                dir.display(),
+                e
+            ))
+        })?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "js"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let source = fs::read_to_string(path).map_err(|e| {
+                anyhow::anyhow!(tr!(
+                    "ステルススクリプトの読み込みに失敗（{}）: {}",
+                    "Failed to read stealth script ({}): {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            Ok((name.into_owned(), source))
+        })
+        .collect()
+}
+
+// ============================================================
+// parse サブコマンド（標準入力/ファイルのHTMLを解析）
+// ============================================================
+fn run_parse(config: &Config, input: Option<&Path>, search_type: SearchType) -> Result<()> {
+    use std::io::Read;
+
+    let html = match input {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut buffer = String::new();
+            std::io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        }
+    };
+    let engine = create_engine(config);
+    let mut parsed = engine.parse_page(&html, search_type);
+    parsed.filtered_count = apply_domain_filters(&mut parsed.results, config);
+    if let Some(discrepancy) = &parsed.discrepancy {
+        eprintln!(
+            "{}",
+            tr!(
+                "警告: 抽出件数の食い違い（見出し {} 件 / 抽出 {} 件）",
+                "Warning: result count mismatch ({} headings / {} extracted)",
+                discrepancy.headings,
+                discrepancy.extracted
+            )
+        );
+    }
+    // 検索語はHTMLから確実には分からないので空のまま
+    let page = build_page_result(engine.name(), "", 1, &parsed);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if config.sink_options.json.pretty {
+        serde_json::to_writer_pretty(&mut out, &page)?;
+    } else {
+        serde_json::to_writer(&mut out, &page)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+// ============================================================
+// 保存済みHTMLでの抽出確認（parse-file）
+// ============================================================
+// フィクスチャ1件の確認結果
+struct FixtureReport {
+    page: PageResult,
+    // 期待値ファイルがあって突き合わせた
+    checked: bool,
+    differences: Vec<String>,
+}
+
+fn fixture_expected_path(html_path: &Path) -> PathBuf {
+    html_path.with_extension("expected.json")
+}
+
+// 保存済みページの engine（"google" など）から EngineKind に戻す
+fn engine_kind_from_name(name: &str) -> Result<EngineKind> {
+    serde_json::from_value(serde_json::json!(name))
+        .map_err(|_| anyhow::anyhow!(tr!("不明な engine: {}", "Unknown engine: {}", name)))
+}
+
+// 期待値があればそのエンジン・検索種別で解析して突き合わせる（無ければ設定のエンジンで解析だけ）
+fn check_fixture(config: &Config, html_path: &Path) -> Result<FixtureReport> {
+    let html = fs::read_to_string(html_path)?;
+    let expected: Option<PageResult> = match fs::read_to_string(fixture_expected_path(html_path)) {
+        Ok(content) => Some(serde_json::from_str(&content)?),
+        Err(_) => None,
+    };
+
+    let mut config = config.clone();
+    let mut search_type = SearchType::Web;
+    if let Some(expected) = &expected {
+        if !expected.engine.is_empty() {
+            config.engine = engine_kind_from_name(&expected.engine)?;
+        }
+        search_type = expected.search_type;
+    }
+    let engine = create_engine(&config);
+    let page = build_page_result(engine.name(), "", 1, &engine.parse_page(&html, search_type));
+    let differences = expected
+        .as_ref()
+        .map(|expected| compare_page_results(expected, &page))
+        .unwrap_or_default();
+    Ok(FixtureReport {
+        page,
+        checked: expected.is_some(),
+        differences,
+    })
+}
+
+// 時刻以外の違いを人が読める形で並べる
+fn compare_page_results(expected: &PageResult, actual: &PageResult) -> Vec<String> {
+    let mut differences = Vec::new();
+    let missing = tr!("（なし）", "(none)");
+    if expected.result_count != actual.result_count {
+        differences.push(tr!(
+            "件数: 期待 {} / 実際 {}",
+            "result count: expected {} / actual {}",
+            expected.result_count,
+            actual.result_count
+        ));
+    }
+    let describe = |result: Option<&SearchResult>| {
+        result.map_or(missing.clone(), |r| {
+            serde_json::to_string(r).unwrap_or_default()
+        })
+    };
+    for i in 0..expected.results.len().max(actual.results.len()) {
+        let (want, got) = (expected.results.get(i), actual.results.get(i));
+        let same = match (want, got) {
+            (Some(want), Some(got)) => {
+                serde_json::to_value(want).ok() == serde_json::to_value(got).ok()
+            }
+            _ => false,
+        };
+        if !same {
+            differences.push(tr!(
+                "{}位: 期待 {} / 実際 {}",
+                "#{}: expected {} / actual {}",
+                i + 1,
+                describe(want),
+                describe(got)
+            ));
+        }
+    }
+
+    // 結果以外の項目はJSONのキー単位で比べる
+    let to_map = |page: &PageResult| match serde_json::to_value(page) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    let (want, got) = (to_map(expected), to_map(actual));
+    let keys: BTreeSet<&String> = want.keys().chain(got.keys()).collect();
+    for key in keys {
+        if matches!(key.as_str(), "timestamp" | "results" | "result_count") {
+            continue;
+        }
+        if want.get(key) != got.get(key) {
+            differences.push(tr!(
+                "{}: 期待 {} / 実際 {}",
+                "{}: expected {} / actual {}",
+                key,
+                want.get(key).map_or(missing.clone(), |v| v.to_string()),
+                got.get(key).map_or(missing.clone(), |v| v.to_string())
+            ));
+        }
+    }
+    differences
+}
+
+// 指定されたファイルと、ディレクトリ直下の .html をまとめて確認する
+fn run_parse_files(config: &Config, inputs: &[PathBuf], bless: bool) -> Result<()> {
+    if inputs.is_empty() {
+        anyhow::bail!(tr!(
+            "parse-file には HTML ファイルかディレクトリを指定してください",
+            "parse-file needs HTML files or directories"
+        ));
+    }
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut found: Vec<PathBuf> = fs::read_dir(input)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension() == Some(OsStr::new("html")))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    let (mut passed, mut failed, mut unchecked) = (0, 0, 0);
+    for path in &files {
+        let report = check_fixture(config, path)?;
+        let summary = format!(
+            "{} {}",
+            report.page.result_count,
+            report.page.extraction_strategy.as_deref().unwrap_or("-")
+        );
+        if bless {
+            let expected_path = fixture_expected_path(path);
+            fs::write(
+                &expected_path,
+                serde_json::to_string_pretty(&report.page)? + "\n",
+            )?;
+            println!(
+                "{}",
+                tr!(
+                    "WRITE {}（{}）",
+                    "WRITE {} ({})",
+                    expected_path.display(),
+                    summary
+                )
+            );
+        } else if !report.checked {
+            unchecked += 1;
+            println!(
+                "{}",
+                tr!(
+                    "---- {}（{}、期待値なし）",
+                    "---- {} ({}, no expected file)",
+                    path.display(),
+                    summary
+                )
+            );
+            for result in &report.page.results {
+                println!("  {:>3}. {} <{}>", result.rank, result.title, result.url);
+            }
+        } else if report.differences.is_empty() {
+            passed += 1;
+            println!("PASS {} ({})", path.display(), summary);
+        } else {
+            failed += 1;
+            println!("FAIL {} ({})", path.display(), summary);
+            for difference in &report.differences {
+                println!("  {}", difference);
+            }
+        }
+    }
+
+    if !bless {
+        println!(
+            "{}",
+            tr!(
+                "成功 {} / 失敗 {} / 期待値なし {}",
+                "{} passed / {} failed / {} without expected file",
+                passed,
+                failed,
+                unchecked
+            )
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!(tr!(
+            "{} 件のフィクスチャが期待値と異なります",
+            "{} fixtures differ from the expected output",
+            failed
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================
+// 保存済みHTMLの再解析（reparse サブコマンド）
+// ============================================================
+fn run_reparse(config: &Config, dir: &Path) -> Result<()> {
+    info!("{}", tr!("再解析: {:?}", "Reparsing: {:?}", dir));
+
+    // クエリディレクトリを直接指定された場合と、実行ディレクトリを指定された場合
+    let has_html = |d: &Path| {
+        fs::read_dir(d).is_ok_and(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .any(|e| e.path().extension() == Some(OsStr::new("html")))
+        })
+    };
+    let query_dirs: Vec<PathBuf> = if has_html(dir) {
+        vec![dir.to_path_buf()]
+    } else {
+        list_run_dirs(dir)
+    };
+
+    let mut total_pages = 0;
+    for query_dir in &query_dirs {
+        total_pages += reparse_query_dir(config, query_dir)?;
+    }
+    info!(
+        "{}",
+        tr!(
+            "再解析完了: {} ページ",
+            "Reparse finished: {} pages",
+            total_pages
+        )
+    );
+
+    // 実行ディレクトリならサマリーも作り直す
+    if query_dirs.iter().any(|d| d != dir) {
+        let summary = write_run_summary(dir)?;
+        print_domain_table(&summary, 10);
+        print_dedup_summary(&summary);
+        if let Some(report) = write_rankings(dir, config)? {
+            print_rankings(&report);
+        }
+        if config.report != ReportFormat::Off {
+            info!(
+                "{}",
+                tr!(
+                    "レポート: {:?}",
+                    "Report: {:?}",
+                    write_run_report(dir, config.report)?
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+// ページ番号順（N.html を先に、stepN.html を後に）
+fn reparse_file_order(stem: &str) -> (u32, u32) {
+    match stem.strip_prefix("step") {
+        Some(n) => (1, n.parse().unwrap_or(u32::MAX)),
+        None => (0, stem.parse().unwrap_or(u32::MAX)),
+    }
+}
+
+fn reparse_query_dir(config: &Config, query_dir: &Path) -> Result<usize> {
+    let mut stems: Vec<String> = fs::read_dir(query_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension() == Some(OsStr::new("html")))
+        .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+        // CAPTCHAページ等は対象外
+        .filter(|stem| {
+            stem.parse::<u32>().is_ok()
+                || stem
+                    .strip_prefix("step")
+                    .is_some_and(|n| n.parse::<u32>().is_ok())
+        })
+        .collect();
+    if stems.is_empty() {
+        return Ok(0);
+    }
+    stems.sort_by_key(|stem| reparse_file_order(stem));
+
+    let dir_name = query_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // 保存時のエンジンで解析し直す（クエリごとに engine を上書きしている場合がある）
+    let stored_engine = stems.iter().find_map(|stem| {
+        fs::read_to_string(query_dir.join(format!("{}.json", stem)))
+            .ok()
+            .and_then(|content| serde_json::from_str::<PageResult>(&content).ok())
+            .map(|page| page.engine)
+            .filter(|engine| !engine.is_empty())
+    });
+    let engine = match stored_engine {
+        Some(name) => {
+            let mut engine_config = config.clone();
+            engine_config.engine = engine_kind_from_name(&name)?;
+            create_engine(&engine_config)
+        }
+        None => create_engine(config),
+    };
+    info!(
+        "{}",
+        tr!("  {}（{}）", "  {} ({})", dir_name, engine.name())
+    );
+
+    let mut json_sink = JsonSink {
+        pretty: config.sink_options.json.pretty,
+    };
+    let mut query_sink = query_dir
+        .join("query.json")
+        .exists()
+        .then(|| QueryJsonSink {
+            pretty: config.sink_options.json.pretty,
+            pages: HashMap::new(),
+        });
+
+    let mut already_saved = 0;
+    for stem in &stems {
+        let html = fs::read_to_string(query_dir.join(format!("{}.html", stem)))?;
+        // 既存JSONのメタデータ（クエリ・時刻など）は引き継ぐ
+        let previous: Option<PageResult> =
+            fs::read_to_string(query_dir.join(format!("{}.json", stem)))
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+        let search_type = previous.as_ref().map_or(SearchType::Web, |p| p.search_type);
+        let mut parsed = engine.parse_page(&html, search_type);
+        let is_step = stem.starts_with("step");
+        if !is_step {
+            // 1ページ目は追加読み込み前なので分割不要だが、件数は数えておく
+            if engine.cumulative_results(&html) {
+                parsed.results = parsed
+                    .results
+                    .split_off(already_saved.min(parsed.results.len()));
+            }
+            already_saved += parsed.results.len();
+        }
+        parsed.filtered_count = apply_domain_filters(&mut parsed.results, config);
+        let query = previous
+            .as_ref()
+            .map_or(dir_name.clone(), |p| p.query.clone());
+        let page_num = if is_step {
+            1
+        } else {
+            stem.parse().unwrap_or(1)
+        };
+        let mut page = build_page_result(engine.name(), &query, page_num, &parsed);
+        if let Some(previous) = &previous {
+            page.timestamp = previous.timestamp.clone();
+            page.suggestion_position = previous.suggestion_position;
+            page.refinement = previous.refinement.clone();
+            page.block_state = previous.block_state;
+            page.timing = previous.timing;
+        }
+        info!(
+            "{}",
+            tr!(
+                "    {}.html: {} 件（前回 {}）",
+                "    {}.html: {} results (previously {})",
+                stem,
+                page.result_count,
+                previous.map_or("-".to_string(), |p| p.result_count.to_string())
+            )
+        );
+
+        json_sink.write_page(query_dir, stem, &page)?;
+        if let Some(sink) = &mut query_sink {
+            sink.write_page(query_dir, stem, &page)?;
+        }
+    }
+    Ok(stems.len())
+}
+
+// ============================================================
+// サポートバンドル（support-bundle）
+// ============================================================
+// 秘密情報を伏せた設定
+fn redact_config(config: &Config) -> Config {
+    const REDACTED: &str = "***";
+    let mut config = config.clone();
+    for proxy in config.proxy.iter_mut().chain(config.proxies.iter_mut()) {
+        if proxy.username.is_some() {
+            proxy.username = Some(REDACTED.to_string());
+        }
+        if proxy.password.is_some() {
+            proxy.password = Some(REDACTED.to_string());
+        }
+    }
+    if let Some(solver) = &mut config.captcha_solver {
+        solver.api_key = REDACTED.to_string();
+    }
+    if config.webhook_url.is_some() {
+        config.webhook_url = Some(REDACTED.to_string());
+    }
+    config
+}
+
+fn redact_toml(value: &mut toml::Value) {
+    const SECRET_KEYS: [&str; 4] = ["username", "password", "api_key", "webhook_url"];
+    match value {
+        toml::Value::Table(table) => {
+            for (key, v) in table.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    *v = toml::Value::String("***".to_string());
+                } else {
+                    redact_toml(v);
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact_toml),
+        _ => {}
+    }
+}
+
+// result_dir 直下（collections は1階層下）で最も新しい実行ディレクトリ
+fn find_latest_run(config: &Config) -> Option<PathBuf> {
+    let root = get_base_path(&config.result_dir);
+    let mut runs = list_run_dirs(&root);
+    for collection in &config.collections {
+        runs.extend(list_run_dirs(&root.join(&collection.name)));
+    }
+    runs.into_iter()
+        .filter(|p| run_started_at(p).is_some())
+        .max_by_key(|p| p.file_name().map(|n| n.to_os_string()))
+}
+
+// 保存済みHTMLを現在のセレクタで解析し直し、見出し数と抽出件数を並べる
+fn check_saved_selectors(engine: &dyn SearchEngine, run_dir: &Path) -> Vec<serde_json::Value> {
+    let mut checks = Vec::new();
+    for query_dir in list_run_dirs(run_dir) {
+        let Ok(entries) = fs::read_dir(&query_dir) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension() == Some(OsStr::new("html")))
+            .filter(|p| p.file_name() != Some(OsStr::new("replay.html")))
+            .collect();
+        files.sort();
+        for file in files {
+            let Ok(html) = fs::read_to_string(&file) else {
+                continue;
+            };
+            let parsed = engine.parse_page(&html, SearchType::Web);
+            let headings = engine.result_heading_selector().map(|selector| {
+                Html::parse_document(&html)
+                    .select(&Selector::parse(selector).unwrap())
+                    .count()
+            });
+            checks.push(serde_json::json!({
+                "file": file.strip_prefix(run_dir).unwrap_or(&file).to_string_lossy(),
+                "block_state": engine.detect_block("", &html),
+                "results": parsed.results.len(),
+                "headings": headings,
+                "discrepancy": parsed.discrepancy.is_some(),
+                "ads": parsed.ads.len(),
+                "related_queries": parsed.related_queries.len(),
+            }));
+        }
+    }
+    checks
+}
+
+fn run_support_bundle(config: &Config, output: Option<&Path>) -> Result<()> {
+    // 通常のページスクリーンショットやCSVは大きい割に調査の役に立たないので入れない
+    const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+    let output = match output {
+        Some(path) => path.to_path_buf(),
+        None => get_base_path(&format!(
+            "support-bundle-{}.zip",
+            Local::now().format("%Y-%m-%d-%H-%M-%S")
+        )),
+    };
+    let mut zip = zip::ZipWriter::new(fs::File::create(&output)?);
+    let options = zip::write::SimpleFileOptions::default();
+    let mut add = |name: &str, data: &[u8]| -> Result<()> {
+        zip.start_file(name, options)?;
+        zip.write_all(data)?;
+        Ok(())
+    };
+
+    let version = format!(
+        "gnezdo: {}\nos: {} {}\nfeatures: captcha-solver={}\nchromium_revision: {}\nchromium_path: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH,
+        cfg!(feature = "captcha-solver"),
+        config.chromium_revision.as_deref().unwrap_or("-"),
+        config.chromium_path
+    );
+    add("version.txt", version.as_bytes())?;
+    add(
+        "config.txt",
+        format!("{:#?}\n", redact_config(config)).as_bytes(),
+    )?;
+    if let Ok(content) = fs::read_to_string(get_base_path("Config.toml")) {
+        let redacted = match toml::from_str::<toml::Value>(&content) {
+            Ok(mut value) => {
+                redact_toml(&mut value);
+                toml::to_string(&value)?
+            }
+            Err(e) => tr!("# 読み込みエラー: {}\n", "# read error: {}\n", e),
+        };
+        add("Config.toml", redacted.as_bytes())?;
+    }
+    let status = get_base_path(&config.result_dir).join("status.json");
+    if let Ok(data) = fs::read(&status) {
+        add("status.json", &data)?;
+    }
+
+    let mut file_count = 0;
+    match find_latest_run(config) {
+        Some(run_dir) => {
+            info!("{}", tr!("直近の実行: {:?}", "Latest run: {:?}", run_dir));
+            let run_name = run_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let engine = create_engine(config);
+            let checks = check_saved_selectors(engine.as_ref(), &run_dir);
+            add(
+                "selectors.json",
+                serde_json::to_string_pretty(&checks)?.as_bytes(),
+            )?;
+
+            let wanted = |path: &Path| {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+                let name = name.as_deref().unwrap_or("");
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some("json" | "jsonl" | "html" | "md") => true,
+                    Some("png") => name.starts_with("captcha-"),
+                    _ => false,
+                }
+            };
+            let mut files: Vec<PathBuf> = Vec::new();
+            for dir in std::iter::once(run_dir.clone()).chain(list_run_dirs(&run_dir)) {
+                if let Ok(entries) = fs::read_dir(&dir) {
+                    files.extend(entries.filter_map(|e| e.ok()).map(|e| e.path()));
+                }
+            }
+            files.sort();
+            for file in files {
+                if !file.is_file() || !wanted(&file) {
+                    continue;
+                }
+                if file.metadata().is_ok_and(|m| m.len() > MAX_FILE_BYTES) {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "  大きすぎるため除外: {:?}",
+                            "  Skipped (too large): {:?}",
+                            file
+                        )
+                    );
+                    continue;
+                }
+                let relative = file.strip_prefix(&run_dir).unwrap_or(&file);
+                let name = format!(
+                    "run/{}/{}",
+                    run_name,
+                    relative.to_string_lossy().replace('\\', "/")
+                );
+                add(&name, &fs::read(&file)?)?;
+                file_count += 1;
+            }
+        }
+        None => info!(
+            "{}",
+            tr!(
+                "実行ディレクトリが見つからないため、設定とバージョン情報のみまとめます",
+                "No run directory found; bundling only the settings and version info"
+            )
+        ),
+    }
+    zip.finish()?;
+
+    info!(
+        "{}",
+        tr!(
+            "サポートバンドルを作成しました: {:?}（実行結果 {} ファイル）",
+            "Created support bundle: {:?} ({} run files)",
+            output,
+            file_count
+        )
+    );
+    info!(
+        "{}",
+        tr!(
+            "共有する前に、検索語など公開したくない内容が含まれていないか確認してください",
+            "Before sharing, check that it contains nothing private such as search terms"
+        )
+    );
+    Ok(())
+}
+
+// ============================================================
+// 通知（トースト・ベル音）
+// ============================================================
+fn notify(config: &Config, title: &str, message: &str) {
+    if config.notify.beep {
+        print!("\x07");
+        let _ = std::io::stdout().flush();
+    }
+    if config.notify.toast
+        && let Err(e) = show_toast(title, message)
+    {
+        warn!("{}", tr!("通知エラー: {}", "Notification error: {}", e));
+    }
+}
+
+// ============================================================
+// Webhook（webhook フィーチャー）
+// ============================================================
+// Slack は text、Discord は content を表示するので両方に同じ文面を入れる。
+// 失敗しても実行は止めない
+#[cfg(feature = "webhook")]
+fn send_webhook(config: &Config, event: &str, text: &str, data: serde_json::Value) {
+    let Some(url) = &config.webhook_url else {
+        return;
+    };
+    let payload = serde_json::json!({
+        "event": event,
+        "at": Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "text": text,
+        "content": text,
+        "data": data,
+    });
+    let sent = ureq::post(url)
+        .config()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .send_json(&payload);
+    if let Err(e) = sent {
+        warn!(
+            "{}",
+            tr!(
+                "Webhook 送信エラー（{}）: {}",
+                "Webhook error ({}): {}",
+                event,
+                e
+            )
+        );
+    }
+}
+
+#[cfg(not(feature = "webhook"))]
+fn send_webhook(_config: &Config, _event: &str, _text: &str, _data: serde_json::Value) {}
+
+// ============================================================
+// フック（外部コマンド）
+// ============================================================
+fn run_hook(name: &str, command: Option<&str>, env: &[(&str, String)], config: &Config) {
+    let Some(command) = command else {
+        return;
+    };
+    info!(
+        "{}",
+        tr!("フック {} を実行: {}", "Running hook {}: {}", name, command)
+    );
+    match spawn_hook(command, env, config.hooks.timeout_secs) {
+        Ok(Some(status)) if status.success() => {}
+        Ok(Some(status)) => warn!(
+            "{}",
+            tr!(
+                "フック {} が失敗しました: {}",
+                "Hook {} failed: {}",
+                name,
+                status
+            )
+        ),
+        Ok(None) => info!(
+            "{}",
+            tr!(
+                "フック {} が {}秒以内に終わらなかったため打ち切りました",
+                "Hook {} did not finish within {}s and was killed",
+                name,
+                config.hooks.timeout_secs
+            )
+        ),
+        Err(e) => warn!(
+            "{}",
+            tr!(
+                "フック {} の起動エラー: {}",
+                "Failed to start hook {}: {}",
+                name,
+                e
+            )
+        ),
+    }
+}
+
+// 完了を待ち、タイムアウトしたら kill して None を返す
+fn spawn_hook(
+    command: &str,
+    env: &[(&str, String)],
+    timeout_secs: u64,
+) -> Result<Option<std::process::ExitStatus>> {
+    let mut cmd = if cfg!(windows) {
+        let mut c = std::process::Command::new("cmd");
+        c.args(["/C", command]);
+        c
+    } else {
+        let mut c = std::process::Command::new("sh");
+        c.args(["-c", command]);
+        c
+    };
+    cmd.stdin(std::process::Stdio::null());
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+    let mut child = cmd.spawn()?;
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+// PowerShell経由でWindowsのトーストを出す（完了を待たない）
+fn show_toast(title: &str, message: &str) -> Result<()> {
+    if !cfg!(windows) {
+        return Ok(());
+    }
+    // 未登録のアプリIDでは表示されないため PowerShell のIDを借りる
+    const APP_ID: &str =
+        r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let script = format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $t = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $x = $t.GetElementsByTagName('text'); \
+         $x.Item(0).AppendChild($t.CreateTextNode({})) > $null; \
+         $x.Item(1).AppendChild($t.CreateTextNode({})) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+        quote(title),
+        quote(message),
+        quote(APP_ID)
+    );
+    std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+// ============================================================
+// メイン
+// ============================================================
+fn main() -> Result<()> {
+    let program_start = Local::now();
+    let cli = parse_args()?;
+    // parse は出力をパイプで渡すので、起動メッセージ等は出さない
+    if let Some(Command::Parse { input, search_type }) = &cli.command {
+        let config = load_config_quiet()?;
+        let _ = LANGUAGE.set(config.language);
+        config.selectors.validate()?;
+        return run_parse(&config, input.as_deref(), *search_type);
+    }
+    if let Some(Command::ParseFile { inputs, bless }) = &cli.command {
+        let config = load_config_quiet()?;
+        let _ = LANGUAGE.set(config.language);
+        config.selectors.validate()?;
+        return run_parse_files(&config, inputs, *bless);
+    }
+
+    // 設定読み込み
+    let (config, config_loaded) = load_config();
+    let _ = LANGUAGE.set(config.language);
+    // 端末でないとき（リダイレクト・サービス実行）はバーを出さない
+    if config.progress && std::io::IsTerminal::is_terminal(&std::io::stdout()) {
+        let _ = PROGRESS.set(indicatif::MultiProgress::new());
+    }
+    let _log_guard = init_logging(&config)?;
+
+    info!("{}", tr!("Gnezdo Ver 1.3 起動", "Gnezdo Ver 1.3 starting"));
+    info!(
+        "{}",
+        tr!(
+            "開始時刻: {}",
+            "Started at: {}",
+            program_start.format("%Y-%m-%d %H:%M:%S")
+        )
+    );
+
+    if cfg!(debug_assertions) {
+        info!(
+            "{}",
+            tr!(
+                "モード: デバッグ（カレントディレクトリ基準）",
+                "Mode: debug (paths relative to the current directory)"
+            )
+        );
+    } else {
+        info!(
+            "{}",
+            tr!(
+                "モード: リリース（実行ファイル基準）",
+                "Mode: release (paths relative to the executable)"
+            )
+        );
+    }
+    if config_loaded {
+        info!(
+            "{}",
+            tr!(
+                "設定ファイル読み込み成功: {:?}",
+                "Loaded config file: {:?}",
+                get_base_path("Config.toml")
+            )
+        );
+    } else {
+        warn!(
+            "{}",
+            tr!(
+                "設定ファイル読み込み失敗。デフォルト使用。",
+                "Failed to read the config file. Using defaults."
+            )
+        );
+    }
+
+    // セレクタの書き間違いは検索・再解析の前に気付けるようにする
+    config.selectors.validate()?;
+
+    let mut config = config;
+    if let Some(file) = &config.queries_file {
+        let path = get_base_path(file);
+        let queries = load_queries_file(&path).map_err(|e| {
+            e.context(tr!(
+                "queries_file {:?} の読み込みに失敗しました",
+                "Failed to read queries_file {:?}",
+                path
+            ))
+        })?;
+        info!(
+            "{}",
+            tr!(
+                "queries_file から {} 件のクエリを読み込みました",
+                "Loaded {} queries from queries_file",
+                queries.len()
+            )
+        );
+        config.search_queries.extend(queries);
+    }
+
+    let shutdown = install_ctrlc_handler()?;
+
+    if let Some(command) = &cli.command {
+        return match command {
+            Command::Reparse(dir) => run_reparse(&config, dir),
+            Command::SupportBundle { output } => run_support_bundle(&config, output.as_deref()),
+            Command::Enqueue(queries) => run_enqueue(&config, queries),
+            Command::Profiles(action) => run_profiles(&config, *action, &shutdown),
+            Command::Parse { .. } | Command::ParseFile { .. } => {
+                unreachable!("parse / parse-file は設定表示の前に処理済み")
+            }
+        };
+    }
+
+    // 設定内容表示
+    info!("{}", tr!("--- 設定 ---", "--- Settings ---"));
+    info!("  profile_dir: {}", config.profile_dir);
+    if let Some(pool) = &config.profile_pool {
+        info!(
+            "{}",
+            tr!(
+                "  profile_pool: {}（{} 個）",
+                "  profile_pool: {} ({} profiles)",
+                pool.dir,
+                pool.size
+            )
+        );
+        if config.restart_strategy == RestartStrategy::Clear {
+            info!(
+                "{}",
+                tr!(
+                    "  注意: profile_pool 使用時は restart_strategy = clear を使わず別のプロファイルで起動し直します",
+                    "  Note: with profile_pool, restart_strategy = clear is ignored and the browser relaunches with another profile"
+                )
+            );
+        }
+    }
+    match &config.chromium_revision {
+        Some(revision) => info!(
+            "{}",
+            tr!(
+                "  chromium_revision: {}（cache: {}）",
+                "  chromium_revision: {} (cache: {})",
+                revision,
+                config.chromium_cache_dir
+            )
+        ),
+        None => info!("  chromium_path: {}", config.chromium_path),
+    }
+    info!("  result_dir: {}", config.result_dir);
+    info!("  max_pages: {}", config.max_pages);
+    info!(
+        "  max_consecutive_no_next: {}",
+        config.max_consecutive_no_next
+    );
+    info!(
+        "  search_queries: {:?}",
+        config
+            .search_queries
+            .iter()
+            .map(SearchQuery::query)
+            .collect::<Vec<_>>()
+    );
+    for entry in &config.search_queries {
+        if let SearchQuery::Overridden(overrides) = entry {
+            info!("    {:?}", overrides);
+        }
+    }
+    if let Some(file) = &config.queries_file {
+        info!("  queries_file: {}", file);
+    }
+    if !config.operators.is_empty() {
+        info!("  operators: {}", config.operators.apply("").trim_start());
+    }
+    info!("  search_type: {:?}", config.search_type);
+    if !config.include_domains.is_empty() || !config.exclude_domains.is_empty() {
+        info!(
+            "  domain_filter: include={:?} exclude={:?} mode={:?}",
+            config.include_domains, config.exclude_domains, config.domain_filter_mode
+        );
+    }
+    info!(
+        "  behavior: timing={} focus={}% suggest={}% scroll={}% typo={}% ime={}",
+        config.behavior.timing,
+        config.behavior.keyboard_focus_rate,
+        config.behavior.keyboard_suggest_rate,
+        config.behavior.keyboard_scroll_rate,
+        config.behavior.typo_rate,
+        config.behavior.ime_input
+    );
+    if let Some(click) = &config.click_through {
+        info!(
+            "{}",
+            tr!(
+                "  click_through: {} 件/ページ、滞在 {}〜{}秒",
+                "  click_through: {} per page, dwell {}-{}s",
+                click.clicks_per_page,
+                click.dwell_min_secs,
+                click.dwell_max_secs
+            )
+        );
+    }
+    if let Some(warm_up) = &config.warm_up {
+        info!(
+            "{}",
+            tr!(
+                "  warm_up: {} サイト（候補 {} 件）",
+                "  warm_up: {} sites (from {} candidates)",
+                warm_up.sites.min(warm_up.urls.len()),
+                warm_up.urls.len()
+            )
+        );
+    }
+    match &config.proxy {
+        Some(proxy) => info!(
+            "  proxy: {}{}",
+            proxy.server_arg(),
+            if proxy.has_auth() {
+                tr!("（認証あり）", " (with auth)")
+            } else {
+                String::new()
+            }
+        ),
+        None => info!("{}", tr!("  proxy: なし", "  proxy: none")),
+    }
+    info!("  device: {:?}", config.device);
+    info!("  consent: {:?}", config.consent);
+    if let Some(geo) = &config.geolocation {
+        info!(
+            "{}",
+            tr!(
+                "  geolocation: {}, {}（精度{}m）",
+                "  geolocation: {}, {} (accuracy {}m)",
+                geo.latitude,
+                geo.longitude,
+                geo.accuracy
+            )
+        );
+    }
+    if !config.user_agents.is_empty() {
+        info!(
+            "{}",
+            tr!(
+                "  user_agents: {}件",
+                "  user_agents: {}",
+                config.user_agents.len()
+            )
+        );
+    }
+    if !config.proxies.is_empty() {
+        info!(
+            "{}",
+            tr!(
+                "  proxies: {}件（{:?}、クールダウン{}秒）",
+                "  proxies: {} ({:?}, cooldown {}s)",
+                config.proxies.len(),
+                config.proxy_rotation,
+                config.proxy_cooldown_secs
+            )
+        );
+    }
+    if config.concurrency > 1 {
+        info!("  concurrency: {}", config.concurrency);
+    }
+    if config.tabs > 1 {
+        info!("  tabs: {}", config.tabs);
+    }
+    info!("  engine: {:?}", config.engine);
+    if config.engine == EngineKind::Google {
+        info!(
+            "{}",
+            tr!(
+                "  google_domain: {}（hl={} gl={} num={}）",
+                "  google_domain: {} (hl={} gl={} num={})",
+                config.google_domain,
+                config.hl.as_deref().unwrap_or("-"),
+                config.gl.as_deref().unwrap_or("-"),
+                config
+                    .results_per_page
+                    .map_or("-".to_string(), |n| n.to_string())
+            )
+        );
+    }
+    if let Some(range) = &config.date_range {
+        info!("  date_range: {}", range.label());
+        if config.engine != EngineKind::Google {
+            warn!(
+                "{}",
+                tr!(
+                    "  警告: date_range は google のみ対応しています",
+                    "  Warning: date_range is only supported for google"
+                )
+            );
+        }
+    }
+    if let Some(safe) = config.safe_search {
+        info!("  safe_search: {:?}", safe);
+        if safe == SafeSearch::Moderate {
+            info!(
+                "{}",
+                tr!(
+                    "    （moderate は safe パラメータを送らず既定のまま）",
+                    "    (moderate sends no safe parameter and keeps the default)"
+                )
+            );
+        }
+        if config.engine != EngineKind::Google {
+            warn!(
+                "{}",
+                tr!(
+                    "  警告: safe_search は google のみ対応しています",
+                    "  Warning: safe_search is only supported for google"
+                )
+            );
+        }
+    }
+    info!("  query_entry: {:?}", config.query_entry);
+    info!("  suggestion_capture: {:?}", config.suggestion_capture);
+    if !config.flows.is_empty() {
+        info!(
+            "{}",
+            tr!("  flows: {}件", "  flows: {}", config.flows.len())
+        );
+    }
+    info!(
+        "{}",
+        tr!(
+            "  persona: {} / {} / {}（check: {:?}）",
+            "  persona: {} / {} / {} (check: {:?})",
+            config.persona.locale,
+            config.persona.timezone,
+            config.persona.platform,
+            config.persona.consistency_check
+        )
+    );
+    info!("  seed_preferences: {}", config.seed_preferences);
+    info!("  stealth_check: {:?}", config.stealth_check);
+    info!("  screenshots: {}", config.screenshots);
+    info!("  stealth: {:?}", config.stealth);
+    info!(
+        "  stealth_scripts_dir: {}",
+        config
+            .stealth_scripts_dir
+            .clone()
+            .unwrap_or_else(|| tr!("(なし)", "(none)"))
+    );
+    info!("  stealth_setup: {:?}", config.stealth_setup);
+    info!("  save_html: {}", config.save_html);
+    info!("  session_replay: {}", config.session_replay);
+    info!("  low_memory: {}", config.low_memory);
+    info!("  restart_strategy: {:?}", config.restart_strategy);
+    info!("  headless: {}", config.headless);
+    info!("  progress: {}", config.progress);
+    info!("  report: {:?}", config.report);
+    info!("  language: {:?}", config.language);
+    if config.webhook_url.is_some() {
+        if cfg!(feature = "webhook") {
+            info!("{}", tr!("  webhook_url: 設定済み", "  webhook_url: set"));
+        } else {
+            warn!(
+                "{}",
+                tr!(
+                    "  webhook_url が設定されていますが、webhook フィーチャー無しでビルドされています",
+                    "  webhook_url is set, but this build lacks the webhook feature"
+                )
+            );
+        }
+    }
+    info!(
+        "  log: level={} format={:?} file={} rotation={:?}",
+        config.log.level, config.log.format, config.log.file, config.log.rotation
+    );
+    info!(
+        "  notify: toast={} beep={}",
+        config.notify.toast, config.notify.beep
+    );
+    if let Some(smart) = &config.smart_schedule {
+        info!(
+            "{}",
+            tr!(
+                "  smart_schedule: {}ページ/日（間隔{}〜{}時間）",
+                "  smart_schedule: {} pages/day (interval {}-{} hours)",
+                smart.pages_per_day,
+                smart.min_interval_hours,
+                smart.max_interval_hours
+            )
+        );
+    }
+    if !config.tracked_domains.is_empty() {
+        info!("  tracked_domains: {}", config.tracked_domains.join(", "));
+    }
+    if let Some(command) = &config.hooks.after_query {
+        info!("  hooks.after_query: {}", command);
+    }
+    if let Some(command) = &config.hooks.after_run {
+        info!("  hooks.after_run: {}", command);
+    }
+    if let Some(budget) = &config.perf_budget {
+        info!(
+            "{}",
+            tr!(
+                "  perf_budget: {}ms（{}ページ連続超過で警告）",
+                "  perf_budget: {}ms (warn after {} consecutive pages over budget)",
+                budget.max_ms,
+                budget.consecutive_pages
+            )
+        );
+    }
+    info!("  sinks: {:?}", config.sinks);
+    if !config.writes_result_json() {
+        info!(
+            "{}",
+            tr!(
+                "  注意: json/query シンク無効のため summary/trends は集計されず、smart_schedule も使えません",
+                "  Note: summary/trends are not aggregated and smart_schedule is unavailable because the json/query sinks are disabled"
+            )
+        );
+    }
+    if let Some(solver) = &config.captcha_solver {
+        info!(
+            "{}",
+            tr!(
+                "  captcha_solver: {:?}（タイムアウト{}秒）",
+                "  captcha_solver: {:?} (timeout {}s)",
+                solver.provider,
+                solver.timeout_secs
+            )
+        );
+    }
+    if let Some(schedule) = &config.schedule {
+        info!(
+            "  schedule: cron={} interval_minutes={}",
+            schedule.cron.as_deref().unwrap_or("-"),
+            schedule
+                .interval_minutes
+                .map_or("-".to_string(), |m| m.to_string())
+        );
+    }
+    if let Some(queue) = &config.queue {
+        info!(
+            "{}",
+            tr!(
+                "  queue: {}（ワーカー{}、最大{}回試行）",
+                "  queue: {} ({} workers, up to {} attempts)",
+                queue.dir,
+                queue.workers,
+                queue.max_attempts
+            )
+        );
+    }
+    for collection in &config.collections {
+        info!(
+            "{}",
+            tr!(
+                "  collection {}: {} クエリ / {} フロー / engine={:?} / schedule={:?}",
+                "  collection {}: {} queries / {} flows / engine={:?} / schedule={:?}",
+                collection.name,
+                collection.search_queries.len(),
+                collection.flows.len(),
+                collection.engine.unwrap_or(config.engine),
+                collection.schedule
+            )
+        );
+    }
+    info!("------------");
+
+    // プロファイル名の誤りは実行前に気付けるようにする
+    config.behavior.timing()?;
+    // 日付の書き間違いも同様
+    let per_query_ranges = config
+        .search_queries
+        .iter()
+        .filter_map(|entry| match entry {
+            SearchQuery::Overridden(overrides) => overrides.date_range.as_ref(),
+            SearchQuery::Plain(_) => None,
+        });
+    for range in config.date_range.iter().chain(per_query_ranges) {
+        range.tbs()?;
+    }
+    // 履歴を読めないと予算も間隔も効かず、全クエリを毎回実行してしまう
+    if config.smart_schedule.is_some() && !config.writes_result_json() {
+        anyhow::bail!(tr!(
+            "smart_schedule には json または query シンクが必要です（sinks = {:?}）",
+            "smart_schedule requires the json or query sink (sinks = {:?})",
+            config.sinks
+        ));
+    }
+
+    // 比較先の指定ミスは実行前に気付けるようにする
+    if let Some(dir) = &cli.diff {
+        if cli.daemon {
+            anyhow::bail!(tr!(
+                "--diff と --daemon は併用できません",
+                "--diff cannot be combined with --daemon"
+            ));
+        }
+        if !dir.is_dir() {
+            anyhow::bail!(tr!(
+                "--diff の比較先 {:?} がありません",
+                "--diff target {:?} does not exist",
+                dir
+            ));
+        }
+    }
+
+    if cli.daemon {
+        return run_daemon(&config, cli.resume.as_ref(), &shutdown);
+    }
+
+    let result_base = run_once(&config, cli.resume.as_ref(), &shutdown, program_start)?;
+    if let Some(previous) = &cli.diff {
+        match write_run_diff(&result_base, previous) {
+            Ok(diff) => print_run_diff(&diff),
+            Err(e) => warn!(
+                "{}",
+                tr!(
+                    "差分レポート作成エラー: {}",
+                    "Failed to write diff report: {}",
+                    e
+                )
+            ),
+        }
+    }
+
+    let program_end = Local::now();
+    info!("========================================");
+    info!("{}", tr!("プログラム終了", "Program finished"));
+    info!(
+        "{}",
+        tr!(
+            "終了時刻: {}",
+            "Finished at: {}",
+            program_end.format("%Y-%m-%d %H:%M:%S")
+        )
+    );
+    info!(
+        "{}",
+        tr!(
+            "総実行時間: {}",
+            "Total run time: {}",
+            format_duration(program_start, program_end)
+        )
+    );
+    info!("========================================");
+
+    if shutdown.load(Ordering::SeqCst) {
+        warn!(
+            "{}",
+            tr!(
+                "中断しました。再開するには: --resume {:?}",
+                "Interrupted. To resume: --resume {:?}",
+                result_base
+            )
+        );
+        return Ok(());
+    }
+
+    notify(
+        &config,
+        "Gnezdo",
+        &tr!("全クエリの実行が完了しました", "All queries finished"),
+    );
+    println!(
+        "\n{}",
+        tr!("終了。Enterで閉じる...", "Done. Press Enter to close...")
+    );
+    let _ = std::io::stdin().read_line(&mut String::new());
+
+    Ok(())
+}
+
+// ============================================================
+// 1回分の実行（結果ディレクトリ作成からサマリー出力まで）
+// ============================================================
+fn run_once(
+    config: &Config,
+    resume: Option<&PathBuf>,
+    shutdown: &Arc<AtomicBool>,
+    run_start: DateTime<Local>,
+) -> Result<PathBuf> {
+    let result_base = match resume {
+        Some(dir) => {
+            if !dir.join("checkpoint.json").exists() {
+                anyhow::bail!(tr!(
+                    "{:?} に checkpoint.json がありません",
+                    "{:?} has no checkpoint.json",
+                    dir
+                ));
+            }
+            info!("{}", tr!("再開: {:?}", "Resuming: {:?}", dir));
+            dir.clone()
+        }
+        None => init_result_dir(config, run_start)?,
+    };
+    let Some(_run_lock) = DirLock::try_acquire(&result_base)? else {
+        anyhow::bail!(tr!(
+            "{:?} は別のインスタンスが実行中です",
+            "{:?} is being used by another instance",
+            result_base
+        ));
+    };
+
+    // status.json / PAUSE / SKIP は result_dir 直下に置くが、
+    // 同じ result_dir を別インスタンスが使っている場合は実行ディレクトリに置く
+    let result_root = get_base_path(&config.result_dir);
+    let root_lock = DirLock::try_acquire(&result_root)?;
+    let control_dir = match &root_lock {
+        Some(_) => result_root,
+        None => {
+            info!(
+                "{}",
+                tr!(
+                    "別のインスタンスが {:?} を使用中のため、status.json / PAUSE / SKIP は実行ディレクトリに置きます",
+                    "Another instance is using {:?}, so status.json / PAUSE / SKIP go in the run directory",
+                    result_root
+                )
+            );
+            result_base.clone()
+        }
+    };
+
+    let jobs = match &config.smart_schedule {
+        Some(smart) => {
+            select_scheduled_jobs(build_query_jobs(config), &result_base, smart, config)?
+        }
+        None => build_query_jobs(config),
+    };
+    let tabs = config.tabs.max(1);
+    let workers = (config.concurrency.max(1) * tabs).min(jobs.len().max(1));
+    let browsers = workers.div_ceil(tabs);
+    let worker_configs: Vec<Config> = (0..browsers)
+        .map(|browser| worker_config(config, browser, browsers))
+        .collect();
+    let mut profile_locks = Vec::new();
+    for worker_config in &worker_configs {
+        let profile_dir = get_base_path(&worker_config.profile_dir);
+        fs::create_dir_all(&profile_dir)?;
+        let Some(lock) = DirLock::try_acquire(&profile_dir)? else {
+            anyhow::bail!(tr!(
+                "profile_dir {:?} は別のインスタンスが使用中です。インスタンスごとに別の profile_dir を指定してください",
+                "profile_dir {:?} is in use by another instance. Give each instance its own profile_dir",
+                profile_dir
+            ));
+        };
+        profile_locks.push(lock);
+    }
+
+    let mut managers = worker_configs
+        .iter()
+        .map(|worker_config| BrowserManager::new(worker_config).map(Mutex::new))
+        .collect::<Result<Vec<_>>>()?;
+    let mut manifest = RunManifest::load_or_new(&result_base, config, run_start);
+    match write_browser_info(
+        managers[0].get_mut().unwrap().get_or_create()?,
+        config,
+        &result_base,
+    ) {
+        Ok(info) => manifest.chromium = Some(info),
+        Err(e) => warn!(
+            "{}",
+            tr!(
+                "browser.json 書き込みエラー: {}",
+                "Failed to write browser.json: {}",
+                e
+            )
+        ),
+    }
+    manifest.write(&result_base);
+
+    let mut sinks = OutputSinks::from_config(config, &result_base)?;
+    let mut status = StatusFile::new(&control_dir, &result_base, run_start, config.max_pages);
+    let checkpoint = CheckpointFile::load_or_new(&result_base)?;
+    let mut states: Vec<RunState> = (0..workers)
+        .map(|worker| RunState {
+            consecutive_no_next: 0,
+            status: status.clone(),
+            checkpoint: checkpoint.clone(),
+            shutdown: shutdown.clone(),
+            pause_file: control_dir.join("PAUSE"),
+            skip_file: control_dir.join("SKIP"),
+            skip_current: false,
+            slow_streak: 0,
+            worker,
+            workers,
+        })
+        .collect();
+    send_webhook(
+        config,
+        "run_started",
+        &tr!(
+            "Gnezdo: 実行開始 {:?}",
+            "Gnezdo: run started {:?}",
+            result_base
+        ),
+        serde_json::json!({ "run_dir": result_base }),
+    );
+    info!(
+        "{}",
+        tr!(
+            "一時停止するには {:?} を作成してください",
+            "Create {:?} to pause",
+            states[0].pause_file
+        )
+    );
+    info!(
+        "{}",
+        tr!(
+            "実行中のクエリを飛ばすには {:?} を作成してください",
+            "Create {:?} to skip the running query",
+            states[0].skip_file
+        )
+    );
+    // ブラウザ1つにワーカー1つなら占有させ、クエリごとの再起動で profile をリセットする
+    let mut slots: Vec<BrowserSlot> = if browsers == workers {
+        managers
+            .iter_mut()
+            .map(|manager| BrowserSlot::Owned(manager.get_mut().unwrap()))
+            .collect()
+    } else {
+        (0..workers)
+            .map(|worker| BrowserSlot::shared(&managers[worker / tabs]))
+            .collect()
+    };
+    let results = if workers == 1 {
+        vec![run_all_queries(
+            &mut slots[0],
+            &mut sinks,
+            &mut states[0],
+            &jobs,
+            run_start,
+            &result_base,
+            &worker_configs[0],
+        )]
+    } else {
+        info!(
+            "{}",
+            tr!(
+                "{} 個のブラウザ・{} 個のタブで並列実行します",
+                "Running with {} browsers and {} tabs in parallel",
+                browsers,
+                workers
+            )
+        );
+        thread::scope(|scope| {
+            let mut handles = Vec::new();
+            for (worker, (slot, state)) in slots.iter_mut().zip(&mut states).enumerate() {
+                let mut sinks = sinks.clone();
+                let (jobs, result_base, worker_config) =
+                    (&jobs, &result_base, &worker_configs[worker / tabs]);
+                handles.push(scope.spawn(move || {
+                    let _span = tracing::info_span!("worker", id = worker + 1).entered();
+                    run_all_queries(
+                        slot,
+                        &mut sinks,
+                        state,
+                        jobs,
+                        run_start,
+                        result_base,
+                        worker_config,
+                    )
+                }));
+                // 同時に Chromium を起動しないよう少しずらす
+                sleep_unless_shutdown(shutdown, 2000);
+            }
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(anyhow::anyhow!(tr!(
+                            "ワーカーが異常終了しました",
+                            "A worker terminated abnormally"
+                        )))
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    let mut phase = if shutdown.load(Ordering::SeqCst) {
+        RunPhase::Interrupted
+    } else {
+        RunPhase::Finished
+    };
+    for result in results {
+        if let Err(e) = result {
+            error!("{}", tr!("致命的エラー: {}", "Fatal error: {}", e));
+            phase = RunPhase::Failed;
+        }
+    }
+    status.set_state(phase);
+    manifest.finish(phase);
+    manifest.write(&result_base);
+    sinks.finish();
+    // Chromium を終了させてからサマリーを作る
+    drop(slots);
+    drop(managers);
+
+    let summary = match write_run_summary(&result_base) {
+        Ok(summary) => {
+            print_domain_table(&summary, 10);
+            print_dedup_summary(&summary);
+            print_anomalies(&summary);
+            if summary.soft_warning_pages > 0 {
+                warn!(
+                    "{}",
+                    tr!(
+                        "注意: {} ページで「通常と異なるトラフィック」警告が表示されていました",
+                        "Note: {} pages showed the \"unusual traffic\" warning",
+                        summary.soft_warning_pages
+                    )
+                );
+            }
+            if summary.discrepancy_pages > 0 {
+                info!(
+                    "{}",
+                    tr!(
+                        "注意: {} ページで見出し数と抽出件数が食い違っていました（discrepancy）",
+                        "Note: {} pages had a heading/extraction count mismatch (discrepancy)",
+                        summary.discrepancy_pages
+                    )
+                );
+            }
+            if summary.over_budget_pages > 0 {
+                info!(
+                    "{}",
+                    tr!(
+                        "注意: {} / {} ページで表示時間が perf_budget を超過していました",
+                        "Note: {} / {} pages exceeded perf_budget",
+                        summary.over_budget_pages,
+                        summary.page_count
+                    )
+                );
+            }
+            let looping = checkpoint.looping_queries();
+            if !looping.is_empty() {
+                warn!(
+                    "{}",
+                    tr!(
+                        "注意: {} クエリで前ページと同じ結果が続いたためページ送りを打ち切りました: {}",
+                        "Note: stopped pagination for {} queries that kept returning the same results: {}",
+                        looping.len(),
+                        looping.join(", ")
+                    )
+                );
+            }
+            Some(summary)
+        }
+        Err(e) => {
+            warn!(
+                "{}",
+                tr!("サマリー作成エラー: {}", "Failed to write summary: {}", e)
+            );
+            None
+        }
+    };
+    match write_trend_report(&result_base) {
+        Ok(report) => print_trend_report(&report),
+        Err(e) => warn!(
+            "{}",
+            tr!(
+                "トレンドレポート作成エラー: {}",
+                "Failed to write trend report: {}",
+                e
+            )
+        ),
+    }
+    match write_rankings(&result_base, config) {
+        Ok(Some(report)) => print_rankings(&report),
+        Ok(None) => {}
+        Err(e) => warn!(
+            "{}",
+            tr!(
+                "順位レポート作成エラー: {}",
+                "Failed to write rankings: {}",
+                e
+            )
+        ),
+    }
+    if config.report != ReportFormat::Off {
+        match write_run_report(&result_base, config.report) {
+            Ok(path) => info!("{}", tr!("レポート: {:?}", "Report: {:?}", path)),
+            Err(e) => warn!(
+                "{}",
+                tr!("レポート作成エラー: {}", "Failed to write report: {}", e)
+            ),
+        }
+    }
+    run_hook(
+        "after_run",
+        config.hooks.after_run.as_deref(),
+        &[
+            ("GNEZDO_RUN_DIR", result_base.display().to_string()),
+            (
+                "GNEZDO_SUMMARY",
+                result_base.join("summary.json").display().to_string(),
+            ),
+            ("GNEZDO_STATUS", format!("{:?}", phase).to_lowercase()),
+        ],
+        config,
+    );
+    let run_status = format!("{:?}", phase).to_lowercase();
+    send_webhook(
+        config,
+        "run_finished",
+        &match &summary {
+            Some(summary) => tr!(
+                "Gnezdo: 実行終了（{}）: {} クエリ / {} ページ / {} 件、CAPTCHA {} 回、異常 {} 件",
+                "Gnezdo: run {}: {} queries / {} pages / {} results, {} CAPTCHAs, {} anomalies",
+                run_status,
+                summary.query_count,
+                summary.page_count,
+                summary.result_count,
+                status.captcha_count(),
+                summary.anomalies.len()
+            ),
+            None => tr!("Gnezdo: 実行終了（{}）", "Gnezdo: run {}", run_status),
+        },
+        serde_json::json!({
+            "run_dir": result_base,
+            "status": run_status,
+            "captcha_count": status.captcha_count(),
+            "summary": summary,
+        }),
+    );
+
+    Ok(result_base)
+}
+
+// ============================================================
+// 定期実行（--daemon）
+// ============================================================
+fn run_daemon(config: &Config, resume: Option<&PathBuf>, shutdown: &Arc<AtomicBool>) -> Result<()> {
+    if let Some(queue) = &config.queue {
+        if resume.is_some() {
+            anyhow::bail!(tr!(
+                "queue 使用時は --resume と --daemon を併用できません",
+                "--resume and --daemon cannot be used with queue"
+            ));
+        }
+        return run_queue_daemon(config, queue, shutdown);
+    }
+    if !config.collections.is_empty() {
+        if resume.is_some() {
+            anyhow::bail!(tr!(
+                "collections 使用時は --resume と --daemon を併用できません",
+                "--resume and --daemon cannot be used with collections"
+            ));
+        }
+        return run_collections_daemon(config, shutdown);
+    }
+    let Some(schedule) = &config.schedule else {
+        anyhow::bail!(tr!(
+            "--daemon には Config.toml の [schedule] が必要です",
+            "--daemon needs [schedule] in Config.toml"
+        ));
+    };
+    // 設定ミスは最初の実行前に気付けるようにする
+    schedule.next_run(Local::now())?;
+    info!(
+        "{}",
+        tr!("定期実行モード: {:?}", "Scheduled mode: {:?}", schedule)
+    );
+
+    let mut resume = resume;
+    loop {
+        let run_start = Local::now();
+        info!(
+            "{}",
+            tr!(
+                "######## 定期実行開始: {} ########",
+                "######## Scheduled run started: {} ########",
+                run_start.format("%Y-%m-%d %H:%M:%S")
+            )
+        );
+        if let Err(e) = run_once(config, resume.take(), shutdown, run_start) {
+            error!("{}", tr!("実行エラー: {}", "Run error: {}", e));
+        }
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let next = schedule.next_run(run_start)?;
+        let wait = (next - Local::now()).num_milliseconds().max(0) as u64;
+        info!(
+            "{}",
+            tr!(
+                "次回実行: {}",
+                "Next run: {}",
+                next.format("%Y-%m-%d %H:%M:%S")
+            )
+        );
+        sleep_unless_shutdown(shutdown, wait);
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    info!(
+        "{}",
+        tr!("定期実行を終了しました", "Scheduled runs stopped")
+    );
+    Ok(())
+}
+
+// ============================================================
+// ジョブキュー（[queue] を設定して --daemon）
+// ============================================================
+// pending/ のジョブファイルを running/ へ rename して取り出す。rename は原子的なので
+// 複数のワーカーが同じジョブを取ることはない。終わったら done/ か failed/ へ移す
+#[derive(Serialize, Deserialize, Debug)]
+struct QueuedJob {
+    query: String,
+    enqueued_at: String,
+    #[serde(default)]
+    attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    run_dir: Option<String>,
+}
+
+struct JobQueue {
+    dir: PathBuf,
+}
+
+impl JobQueue {
+    const STATES: [&str; 4] = ["pending", "running", "done", "failed"];
+
+    fn open(config: &Config) -> Result<Self> {
+        let dir = config
+            .queue
+            .as_ref()
+            .map_or_else(default_queue_dir, |q| q.dir.clone());
+        let queue = Self {
+            dir: get_base_path(&dir),
+        };
+        for state in Self::STATES {
+            fs::create_dir_all(queue.dir.join(state))?;
+        }
+        Ok(queue)
+    }
+
+    // 書きかけを取り出されないよう .tmp に書いてから .json にする。
+    // 名前に pid を含めるので、同じ名前を取り合うのは同じプロセス内だけ
+    fn enqueue(&self, query: &str) -> Result<PathBuf> {
+        let job = QueuedJob {
+            query: query.to_string(),
+            enqueued_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+            attempts: 0,
+            last_error: None,
+            run_dir: None,
+        };
+        let stamp = Local::now().format("%Y%m%d-%H%M%S%3f").to_string();
+        let pending = self.dir.join("pending");
+        let path = (1..)
+            .map(|n| pending.join(format!("{}-{}-{}.json", stamp, std::process::id(), n)))
+            .find(|path| !path.exists())
+            .expect("連番は尽きない");
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&job)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(path)
+    }
+
+    // 前回落ちたときに running/ に残ったジョブを pending/ に戻す
+    fn recover(&self) -> Result<usize> {
+        let mut recovered = 0;
+        for entry in fs::read_dir(self.dir.join("running"))? {
+            let path = entry?.path();
+            if let Some(name) = path.file_name() {
+                fs::rename(&path, self.dir.join("pending").join(name))?;
+                recovered += 1;
+            }
+        }
+        Ok(recovered)
+    }
+
+    // 古い順に1つ取り出す（ファイル名、ジョブ）
+    fn claim(&self) -> Result<Option<(String, QueuedJob)>> {
+        let mut names: Vec<String> = fs::read_dir(self.dir.join("pending"))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".json"))
+            .collect();
+        names.sort();
+        for name in names {
+            let running = self.dir.join("running").join(&name);
+            match fs::rename(self.dir.join("pending").join(&name), &running) {
+                Ok(()) => {
+                    let job = serde_json::from_str(&fs::read_to_string(&running)?)?;
+                    return Ok(Some((name, job)));
+                }
+                // 他のワーカーが先に取った
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    // running/ から state（pending / done / failed）へ移す
+    fn finish(&self, name: &str, job: &QueuedJob, state: &str) -> Result<()> {
+        let running = self.dir.join("running").join(name);
+        fs::write(&running, serde_json::to_string_pretty(job)?)?;
+        fs::rename(&running, self.dir.join(state).join(name))?;
+        Ok(())
+    }
+}
+
+fn run_enqueue(config: &Config, queries: &[String]) -> Result<()> {
+    if queries.is_empty() {
+        anyhow::bail!(tr!(
+            "enqueue には追加するクエリを指定してください",
+            "enqueue needs the queries to add"
+        ));
+    }
+    let queue = JobQueue::open(config)?;
+    for query in queries {
+        let path = queue.enqueue(query)?;
+        info!(
+            "{}",
+            tr!(
+                "キューに追加: 「{}」（{:?}）",
+                "Enqueued \"{}\" ({:?})",
+                query,
+                path
+            )
+        );
+    }
+    Ok(())
+}
+
+// 並列実行・キュー処理のワーカーごとの設定。profile は別々にし、proxies は足りていれば分け合う
+fn worker_config(config: &Config, worker: usize, workers: usize) -> Config {
+    let mut worker_config = config.clone();
+    worker_config.concurrency = 1;
+    worker_config.queue = None;
+    worker_config.collections = Vec::new();
+    worker_config.smart_schedule = None;
+    if workers > 1 {
+        worker_config.profile_dir = format!("{}-{}", config.profile_dir, worker + 1);
+        if config.proxies.len() >= workers {
+            worker_config.proxies = config
+                .proxies
+                .iter()
+                .skip(worker)
+                .step_by(workers)
+                .cloned()
+                .collect();
+        }
+    }
+    worker_config
+}
+
+fn run_queue_daemon(
+    config: &Config,
+    queue_config: &QueueConfig,
+    shutdown: &Arc<AtomicBool>,
+) -> Result<()> {
+    let queue = JobQueue::open(config)?;
+    // running/ の回収は他のデーモンが動いていないときだけ行える
+    let Some(_queue_lock) = DirLock::try_acquire(&queue.dir)? else {
+        anyhow::bail!(tr!(
+            "キュー {:?} は別のインスタンスが処理中です",
+            "Queue {:?} is being processed by another instance",
+            queue.dir
+        ));
+    };
+    let recovered = queue.recover()?;
+    let workers = queue_config.workers.max(1);
+    info!(
+        "{}",
+        tr!(
+            "キュー処理モード: {:?}（ワーカー{}、中断されていたジョブ {} 件を再投入）",
+            "Queue mode: {:?} ({} workers, {} interrupted jobs requeued)",
+            queue.dir,
+            workers,
+            recovered
+        )
+    );
+
+    thread::scope(|scope| {
+        for worker in 0..workers {
+            let worker_config = worker_config(config, worker, workers);
+            let queue = &queue;
+            scope.spawn(move || {
+                let _span = tracing::info_span!("worker", id = worker + 1).entered();
+                run_queue_worker(queue, queue_config, &worker_config, shutdown);
+            });
+            // 同時に Chromium を起動しないよう少しずらす
+            sleep_unless_shutdown(shutdown, 2000);
+        }
+    });
+    info!(
+        "{}",
+        tr!("キュー処理を終了しました", "Queue processing stopped")
+    );
+    Ok(())
+}
+
+// 実行ディレクトリの checkpoint.json で完了済みになっていないジョブ（エラーの内容付き）
+fn incomplete_queries(result_base: &Path, jobs: &[QueryJob]) -> Result<Vec<String>> {
+    let checkpoint: Checkpoint =
+        serde_json::from_str(&fs::read_to_string(result_base.join("checkpoint.json"))?)?;
+    Ok(jobs
+        .iter()
+        .map(|job| job.key())
+        .filter(|key| !checkpoint.completed_queries.contains(key))
+        .map(
+            |key| match checkpoint.errors.iter().rev().find(|e| e.query == key) {
+                Some(error) => format!("{}（{}）", key, error.message),
+                None => key,
+            },
+        )
+        .collect())
+}
+
+fn run_queue_worker(
+    queue: &JobQueue,
+    queue_config: &QueueConfig,
+    worker_config: &Config,
+    shutdown: &Arc<AtomicBool>,
+) {
+    while !shutdown.load(Ordering::SeqCst) {
+        let (name, mut job) = match queue.claim() {
+            Ok(Some(claimed)) => claimed,
+            Ok(None) => {
+                sleep_unless_shutdown(shutdown, queue_config.poll_secs * 1000);
+                continue;
+            }
+            Err(e) => {
+                error!(
+                    "{}",
+                    tr!("キュー読み込みエラー: {}", "Queue read error: {}", e)
+                );
+                sleep_unless_shutdown(shutdown, queue_config.poll_secs * 1000);
+                continue;
+            }
+        };
+        info!(
+            "{}",
+            tr!(
+                "######## ジョブ開始: 「{}」（{}回目） ########",
+                "######## Job started: \"{}\" (attempt {}) ########",
+                job.query,
+                job.attempts + 1
+            )
+        );
+
+        let mut job_config = worker_config.clone();
+        job_config.search_queries = vec![SearchQuery::Plain(job.query.clone())];
+        job_config.flows = Vec::new();
+        let state = match run_once(&job_config, None, shutdown, Local::now()) {
+            // 中断された実行は次回の起動時にやり直す
+            Ok(_) if shutdown.load(Ordering::SeqCst) => "pending",
+            Ok(result_base) => {
+                job.run_dir = Some(result_base.to_string_lossy().to_string());
+                // リトライ上限でスキップされたクエリがあれば失敗として扱う
+                match incomplete_queries(&result_base, &build_query_jobs(&job_config)) {
+                    Ok(incomplete) if incomplete.is_empty() => "done",
+                    result => {
+                        let message = match result {
+                            Ok(incomplete) => tr!(
+                                "完了しなかったクエリがあります: {}",
+                                "Some queries did not complete: {}",
+                                incomplete.join(", ")
+                            ),
+                            Err(e) => tr!(
+                                "checkpoint.json を読めません: {}",
+                                "Cannot read checkpoint.json: {}",
+                                e
+                            ),
+                        };
+                        error!("{}", message);
+                        job.attempts += 1;
+                        job.last_error = Some(message);
+                        if job.attempts >= queue_config.max_attempts {
+                            "failed"
+                        } else {
+                            "pending"
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                error!("{}", tr!("実行エラー: {}", "Run error: {}", e));
+                job.attempts += 1;
+                job.last_error = Some(e.to_string());
+                if job.attempts >= queue_config.max_attempts {
+                    "failed"
+                } else {
+                    "pending"
+                }
+            }
+        };
+        if let Err(e) = queue.finish(&name, &job, state) {
+            error!(
+                "{}",
+                tr!(
+                    "ジョブの移動エラー（{}）: {}",
+                    "Failed to move job ({}): {}",
+                    name,
+                    e
+                )
+            );
+        }
+    }
+}
+
+// 各クエリ集を次回実行時刻の早い順に1つずつ実行する（ブラウザは同時に1つ）
+fn run_collections_daemon(config: &Config, shutdown: &Arc<AtomicBool>) -> Result<()> {
+    let mut names = HashSet::new();
+    for collection in &config.collections {
+        if collection.name.is_empty()
+            || collection.name.contains(['/', '\\', ':'])
+            || collection.name.starts_with('.')
+        {
+            anyhow::bail!(tr!(
+                "collections の name {:?} はディレクトリ名に使えません",
+                "collections name {:?} cannot be used as a directory name",
+                collection.name
+            ));
+        }
+        if !names.insert(collection.name.as_str()) {
+            anyhow::bail!(tr!(
+                "collections の name {:?} が重複しています",
+                "collections name {:?} is duplicated",
+                collection.name
+            ));
+        }
+        collection
+            .schedule
+            .next_run(Local::now())
+            .map_err(|e| e.context(format!("collection {}", collection.name)))?;
+    }
+    info!(
+        "{}",
+        tr!(
+            "定期実行モード: {} 個のクエリ集",
+            "Scheduled mode: {} collections",
+            config.collections.len()
+        )
+    );
+
+    // 起動直後に全クエリ集を一巡させ、以降は各自のスケジュールに従う
+    let start = Local::now();
+    let mut next_runs: Vec<DateTime<Local>> = vec![start; config.collections.len()];
+    loop {
+        let (index, next) = next_runs
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, t)| **t)
+            .map(|(i, t)| (i, *t))
+            .expect("collections は空でない");
+        let collection = &config.collections[index];
+        let wait = (next - Local::now()).num_milliseconds().max(0) as u64;
+        if wait > 0 {
+            info!(
+                "{}",
+                tr!(
+                    "次回実行: {}（{}）",
+                    "Next run: {} ({})",
+                    next.format("%Y-%m-%d %H:%M:%S"),
+                    collection.name
+                )
+            );
+            sleep_unless_shutdown(shutdown, wait);
+        }
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let run_start = Local::now();
+        info!(
+            "{}",
+            tr!(
+                "######## 定期実行開始: {}（{}） ########",
+                "######## Scheduled run started: {} ({}) ########",
+                run_start.format("%Y-%m-%d %H:%M:%S"),
+                collection.name
+            )
+        );
+        if let Err(e) = run_once(&collection.apply(config), None, shutdown, run_start) {
+            error!(
+                "{}",
+                tr!(
+                    "実行エラー（{}）: {}",
+                    "Run error ({}): {}",
+                    collection.name,
+                    e
+                )
+            );
+        }
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        next_runs[index] = collection.schedule.next_run(run_start)?;
+    }
+    info!(
+        "{}",
+        tr!("定期実行を終了しました", "Scheduled runs stopped")
+    );
+    Ok(())
+}
+
+// ============================================================
+// 全クエリ実行
+// ============================================================
+fn run_all_queries(
+    slot: &mut BrowserSlot,
+    sinks: &mut OutputSinks,
+    state: &mut RunState,
+    jobs: &[QueryJob],
+    program_start: DateTime<Local>,
+    result_base: &PathBuf,
+    config: &Config,
+) -> Result<()> {
+    let mut rng = WyRand::new();
+    let mut query_index = state.worker;
+    let mut retry_count = 0;
+    let mut consecutive_captchas = 0;
+    const MAX_RETRIES: u32 = 3;
+
+    let engine = create_engine(config);
+    TOUCH_INPUT.set(config.device == DeviceKind::Mobile);
+
+    run_startup_consistency_check(slot, engine.as_ref(), config)?;
+    // クエリごとの再起動に加え、チェックで開いたページも最初のクエリへ持ち越さない
+    if config.low_memory && config.persona.consistency_check != ConsistencyCheckMode::Off {
+        state.status.set_state(RunPhase::Restarting);
+        if let Err(e) = slot.restart() {
+            warn!("{}", tr!("再起動エラー: {}", "Restart error: {}", e));
+        }
+    }
+
+    while query_index < jobs.len() {
+        if state.shutdown_requested() {
+            break;
+        }
+        let job = &jobs[query_index];
+        let query = &job.query;
+        let query_start = Local::now();
+        // search_queries の表で上書きされたクエリは専用の設定とエンジンで実行する
+        let config = job.config.as_deref().unwrap_or(config);
+        let job_engine = job.config.as_deref().map(create_engine);
+        let engine = job_engine.as_deref().unwrap_or(engine.as_ref());
+        sinks.engine = engine.name();
+
+        if state.checkpoint.is_completed(&job.key()) {
+            info!(
+                "{}",
+                tr!(
+                    "検索 {}/{}: 「{}」は完了済みのためスキップ",
+                    "Search {}/{}: \"{}\" already done, skipping",
+                    query_index + 1,
+                    jobs.len(),
+                    query
+                )
+            );
+            query_index += state.workers;
+            continue;
+        }
+
+        info!("========================================");
+        info!(
+            "{}",
+            tr!(
+                "検索 {}/{}: 「{}」（{}）",
+                "Search {}/{}: \"{}\" ({})",
+                query_index + 1,
+                jobs.len(),
+                query,
+                engine.name()
+            )
+        );
+        info!(
+            "{}",
+            tr!("開始: {}", "Started: {}", query_start.format("%H:%M:%S"))
+        );
+        info!("========================================");
+        state.status.set_query(query_index + 1, jobs.len(), query);
+        state.checkpoint.start_query(&job.key());
+
+        let query_dir = init_query_result_dir(result_base, &job.dir_name())?;
+
+        let tab = match slot.tab() {
+            Ok(t) => t,
+            Err(e) => {
+                warn!(
+                    "{}",
+                    tr!(
+                        "タブ取得エラー: {}。ブラウザ再起動。",
+                        "Failed to get a tab: {}. Restarting the browser.",
+                        e
+                    )
+                );
+                slot.mark_current_proxy_failed();
+                if let Err(restart_err) = slot.restart() {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "再起動失敗: {}。スキップ。",
+                            "Restart failed: {}. Skipping.",
+                            restart_err
+                        )
+                    );
+                    query_index += state.workers;
+                    retry_count = 0;
+                    continue;
+                }
+                match slot.tab() {
+                    Ok(t) => t,
+                    Err(e) => {
+                        warn!(
+                            "{}",
+                            tr!(
+                                "再起動後もタブ取得失敗: {}。スキップ。",
+                                "Still failed to get a tab after restarting: {}. Skipping.",
+                                e
+                            )
+                        );
+                        query_index += state.workers;
+                        retry_count = 0;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        if let Err(e) = setup_proxy_auth(&tab, slot.current_proxy().as_ref()) {
+            warn!(
+                "{}",
+                tr!(
+                    "プロキシ認証設定エラー: {}",
+                    "Proxy authentication setup error: {}",
+                    e
+                )
+            );
+        }
+        if config.perf_budget.is_some()
+            && let Err(e) = tab.call_method(Performance::Enable { time_domain: None })
+        {
+            warn!(
+                "{}",
+                tr!(
+                    "Performance計測の有効化エラー: {}",
+                    "Failed to enable Performance metrics: {}",
+                    e
+                )
+            );
+        }
+        let mut stealth_error = None;
+        if let Err(e) = setup_stealth_cdp(&tab, &config.persona, &slot.user_agent()) {
+            warn!(
+                "{}",
+                tr!(
+                    "CDPステルス設定エラー: {}",
+                    "CDP stealth setup error: {}",
+                    e
+                )
+            );
+            stealth_error.get_or_insert(e);
+        }
+        if let Err(e) = deny_permissions_via_cdp(&tab, &engine.home_url(), job.geolocation.as_ref())
+        {
+            warn!(
+                "{}",
+                tr!(
+                    "CDP権限拒否の設定エラー: {}",
+                    "CDP permission setup error: {}",
+                    e
+                )
+            );
+        }
+        if let Err(e) = inject_stealth_scripts(&tab, config, job.geolocation.is_none()) {
+            warn!(
+                "{}",
+                tr!(
+                    "ステルススクリプト注入エラー: {}",
+                    "Stealth script injection error: {}",
+                    e
+                )
+            );
+            stealth_error.get_or_insert(e);
+        }
+
+        if let Err(e) = wait_while_paused(&tab, state) {
+            warn!(
+                "{}",
+                tr!("一時停止中のエラー: {}", "Error while paused: {}", e)
+            );
+        }
+        if state.shutdown_requested() {
+            break;
+        }
+        // ブラウザを起動し直すたびにセッションは新しくなるので、クエリごとに行う
+        if let Some(warm_up) = &config.warm_up {
+            let mut urls = warm_up.urls.clone();
+            rng.shuffle(&mut urls);
+            urls.truncate(warm_up.sites);
+            info!(
+                "{}",
+                tr!(
+                    "ウォームアップ: {} サイトを閲覧します",
+                    "Warm-up: browsing {} sites",
+                    urls.len()
+                )
+            );
+            state.status.set_state(RunPhase::WarmingUp);
+            browse_sites(&tab, &urls, &state.shutdown);
+            if state.shutdown_requested() {
+                break;
+            }
+        }
+        // 開始前に SKIP が置かれていれば実行せずに放棄扱いにする
+        state.skip_current = false;
+        let outcome = if state.take_skip_request() {
+            Ok(())
+        } else if let Some(e) = stealth_error
+            && config.stealth_setup == StealthSetupMode::FailQuery
+        {
+            // 無防備なまま検索するよりは、このクエリを失敗させて再起動からやり直す
+            Err(e.context(tr!(
+                "ステルス設定に失敗したためクエリを実行しません",
+                "Not running the query because stealth setup failed"
+            )))
+        } else {
+            start_action_log(&query_dir);
+            let result = execute_single_query(&tab, engine, sinks, job, &query_dir, state, config);
+            stop_action_log();
+            if config.session_replay
+                && let Err(e) = write_session_replay(&query_dir)
+            {
+                warn!(
+                    "{}",
+                    tr!(
+                        "セッションリプレイ生成エラー: {}",
+                        "Failed to generate session replay: {}",
+                        e
+                    )
+                );
+            }
+            result
+        };
+
+        match outcome {
+            Ok(_) if state.shutdown_requested() => {
+                warn!(
+                    "{}",
+                    tr!(
+                        "「{}」の途中で中断しました",
+                        "Interrupted during \"{}\"",
+                        query
+                    )
+                );
+                break;
+            }
+            Ok(_) if state.skip_current => {
+                info!(
+                    "{}",
+                    tr!("「{}」をスキップしました", "Skipped \"{}\"", query)
+                );
+                state.checkpoint.skip_query(&job.key());
+                query_index += state.workers;
+                retry_count = 0;
+                if query_index < jobs.len() {
+                    info!(
+                        "{}",
+                        tr!(
+                            "ブラウザ再起動して profile リセット...",
+                            "Restarting the browser and resetting the profile..."
+                        )
+                    );
+                    state.status.set_state(RunPhase::Restarting);
+                    let _ = slot.restart();
+                }
+            }
+            Ok(_) => {
+                consecutive_captchas = 0;
+                let query_end = Local::now();
+                state
+                    .checkpoint
+                    .complete_query(&job.key(), query_end - query_start);
+                let total_now = Local::now();
+
+                info!("----------------------------------------");
+                info!("{}", tr!("「{}」完了", "Finished \"{}\"", query));
+                info!(
+                    "{}",
+                    tr!(
+                        "  クエリ所要時間: {}",
+                        "  Query time: {}",
+                        format_duration(query_start, query_end)
+                    )
+                );
+                info!(
+                    "{}",
+                    tr!(
+                        "  累計経過時間: {}",
+                        "  Total elapsed: {}",
+                        format_duration(program_start, total_now)
+                    )
+                );
+                info!("----------------------------------------");
+
+                run_hook(
+                    "after_query",
+                    config.hooks.after_query.as_deref(),
+                    &[
+                        ("GNEZDO_RUN_DIR", result_base.display().to_string()),
+                        ("GNEZDO_QUERY_DIR", query_dir.display().to_string()),
+                        ("GNEZDO_QUERY", query.to_string()),
+                        ("GNEZDO_QUERY_INDEX", (query_index + 1).to_string()),
+                        ("GNEZDO_QUERY_TOTAL", jobs.len().to_string()),
+                    ],
+                    config,
+                );
+                send_webhook(
+                    config,
+                    "query_completed",
+                    &tr!(
+                        "Gnezdo: 「{}」完了（{}/{}）",
+                        "Gnezdo: finished \"{}\" ({}/{})",
+                        query,
+                        query_index + 1,
+                        jobs.len()
+                    ),
+                    serde_json::json!({
+                        "query": query,
+                        "index": query_index + 1,
+                        "total": jobs.len(),
+                        "duration_ms": (query_end - query_start).num_milliseconds(),
+                        "query_dir": query_dir,
+                    }),
+                );
+
+                query_index += state.workers;
+                retry_count = 0;
+
+                if query_index < jobs.len() {
+                    let rest = random_in(&mut rng, config.behavior.timing()?.query_rest_ms);
+                    info!(
+                        "{}",
+                        tr!(
+                            "次のクエリまで {}ms 休憩...",
+                            "Resting {}ms before the next query...",
+                            rest
+                        )
+                    );
+                    state.status.set_state(RunPhase::Resting);
+                    state.sleep_unless_shutdown(rest);
+                    if state.shutdown_requested() {
+                        break;
+                    }
+
+                    info!(
+                        "{}",
+                        tr!(
+                            "再起動して profile リセット...",
+                            "Restarting and resetting the profile..."
+                        )
+                    );
+                    state.status.set_state(RunPhase::Restarting);
+                    let _ = slot.restart();
+                }
+            }
+            Err(e) if state.shutdown_requested() => {
+                warn!(
+                    "{}",
+                    tr!("中断処理中のエラー: {}", "Error while interrupting: {}", e)
+                );
+                break;
+            }
+            Err(e) if e.downcast_ref::<StealthCheckError>().is_some() => {
+                // 設定や環境の問題なのでリトライしても直らない
+                return Err(e);
+            }
+            Err(e) if e.downcast_ref::<CaptchaError>().is_some() => {
+                state.checkpoint.record_error(&job.key(), &e);
+                retry_count += 1;
+                consecutive_captchas += 1;
+                slot.mark_current_proxy_failed();
+                state.status.record_captcha();
+                let backoff = captcha_backoff_secs(consecutive_captchas);
+                warn!("{}", tr!("【CAPTCHA】{}", "[CAPTCHA] {}", e));
+                notify(
+                    config,
+                    "Gnezdo: CAPTCHA",
+                    &tr!(
+                        "「{}」でCAPTCHAが表示されました（連続{}回目）",
+                        "CAPTCHA shown for \"{}\" ({} in a row)",
+                        query,
+                        consecutive_captchas
+                    ),
+                );
+                send_webhook(
+                    config,
+                    "captcha_detected",
+                    &tr!(
+                        "Gnezdo: 「{}」でCAPTCHAが表示されました（連続{}回目）",
+                        "Gnezdo: CAPTCHA on \"{}\" ({} in a row)",
+                        query,
+                        consecutive_captchas
+                    ),
+                    serde_json::json!({
+                        "query": query,
+                        "consecutive": consecutive_captchas,
+                        "retry": retry_count,
+                        "message": e.to_string(),
+                    }),
+                );
+                info!(
+                    "{}",
+                    tr!(
+                        "連続{}回目。{}秒待機してからプロキシ/プロファイルを切り替えます（リトライ {}/{}）",
+                        "{} in a row. Waiting {}s, then switching proxy/profile (retry {}/{})",
+                        consecutive_captchas,
+                        backoff,
+                        retry_count,
+                        MAX_RETRIES
+                    )
+                );
+                state.sleep_unless_shutdown(backoff * 1000);
+                if state.shutdown_requested() {
+                    break;
+                }
+                if retry_count >= MAX_RETRIES {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "リトライ上限。次のクエリへスキップ。",
+                            "Retry limit reached. Skipping to the next query."
+                        )
+                    );
+                    query_index += state.workers;
+                    retry_count = 0;
+                }
+                state.status.set_state(RunPhase::Restarting);
+                let _ = slot.restart();
+                continue;
+            }
+            Err(e) => {
+                state.checkpoint.record_error(&job.key(), &e);
+                retry_count += 1;
+                slot.mark_current_proxy_failed();
+                warn!(
+                    "{}",
+                    tr!(
+                        "検索エラー: {}。リトライ {}/{}",
+                        "Search error: {}. Retry {}/{}",
+                        e,
+                        retry_count,
+                        MAX_RETRIES
+                    )
+                );
+
+                if retry_count >= MAX_RETRIES {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "リトライ上限。次のクエリへスキップ。",
+                            "Retry limit reached. Skipping to the next query."
+                        )
+                    );
+                    query_index += state.workers;
+                    retry_count = 0;
+                } else {
+                    info!(
+                        "{}",
+                        tr!(
+                            "ブラウザ再起動して profile リセット...",
+                            "Restarting the browser and resetting the profile..."
+                        )
+                    );
+                    state.status.set_state(RunPhase::Restarting);
+                    let _ = slot.restart();
+                }
+                continue;
+            }
+        }
+    }
+
+    if state.shutdown_requested() {
+        return Ok(());
+    }
+
+    info!("========================================");
+    info!("{}", tr!("全クエリ巡回完了！", "All queries finished!"));
+    info!("========================================");
+
+    Ok(())
+}
+
+// ============================================================
+// アクティブタブ取得
+// ============================================================
+fn get_active_tab(manager: &mut BrowserManager) -> Result<Arc<Tab>> {
+    let browser = manager.get_or_create()?;
+    thread::sleep(Duration::from_millis(500));
+
+    let tab = {
+        let tabs = browser.get_tabs().lock().unwrap();
+        let first_tab = tabs.first().cloned();
+        for tab in tabs.iter().skip(1) {
+            let _ = tab.close(false);
+        }
+        first_tab
+    };
+
+    match tab {
+        Some(t) => Ok(t),
+        None => {
+            let browser = manager.get_or_create()?;
+            Ok(browser.new_tab()?)
+        }
+    }
+}
+
+// ============================================================
+// ブロック検知
+// ============================================================
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum BlockState {
+    // 通常の検索結果
+    Clear,
+    // 結果は表示されているが「通常と異なるトラフィック」の警告バナーが出ている
+    SoftWarning,
+    // JavaScript確認の中間ページ（待てば通常は解除される）
+    JsChallenge,
+    // CAPTCHAページ（結果なし）
+    Captcha,
+}
+
+impl BlockState {
+    fn label(&self) -> &'static str {
+        match (self, english()) {
+            (BlockState::Clear, false) => "通常",
+            (BlockState::Clear, true) => "clear",
+            (BlockState::SoftWarning, false) => "トラフィック警告",
+            (BlockState::SoftWarning, true) => "traffic warning",
+            (BlockState::JsChallenge, false) => "JSチャレンジ",
+            (BlockState::JsChallenge, true) => "JS challenge",
+            (BlockState::Captcha, _) => "CAPTCHA",
+        }
+    }
+}
+
+const UNUSUAL_TRAFFIC_MARKERS: &[&str] = &[
+    "unusual traffic",
+    "通常と異なるトラフィック",
+    "異常なトラフィック",
+    "automated queries",
+];
+
+fn detect_google_block(url: &str, html: &str) -> BlockState {
+    if url.contains("/sorry/")
+        || html.contains("id=\"captcha-form\"")
+        || html.contains("g-recaptcha")
+    {
+        return BlockState::Captcha;
+    }
+    if html.contains("/httpservice/retry/enablejs")
+        || html.contains("if you are not redirected within a few seconds")
+        || html.contains("数秒経っても移動しない場合は")
+    {
+        return BlockState::JsChallenge;
+    }
+    let lower = html.to_lowercase();
+    if UNUSUAL_TRAFFIC_MARKERS.iter().any(|m| lower.contains(m)) {
+        return BlockState::SoftWarning;
+    }
+    BlockState::Clear
+}
+
+// CAPTCHAページに当たったことを示すエラー（通常のエラーと区別してバックオフする）
+#[derive(Debug)]
+struct CaptchaError {
+    url: String,
+    screenshot: Option<PathBuf>,
+}
+
+impl std::fmt::Display for CaptchaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                "CAPTCHAページを検出しました（{}）",
+                "Detected a CAPTCHA page ({})",
+                self.url
+            )
+        )?;
+        if let Some(path) = &self.screenshot {
+            write!(
+                f,
+                "{}",
+                tr!(" スクリーンショット: {:?}", " Screenshot: {:?}", path)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CaptchaError {}
+
+// 現在のページのスクリーンショットを撮って CaptchaError を作る
+fn captcha_error(tab: &Arc<Tab>, query_dir: &Path) -> anyhow::Error {
+    use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+
+    let path = query_dir.join(format!(
+        "captcha-{}.png",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let screenshot = match tab
+        .capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true)
+        .and_then(|png| Ok(fs::write(&path, png)?))
+    {
+        Ok(_) => {
+            let file = path.file_name().map(|n| n.to_string_lossy().to_string());
+            log_action(
+                "screenshot",
+                serde_json::json!({ "file": file, "captcha": true }),
+            );
+            Some(path)
+        }
+        Err(e) => {
+            warn!(
+                "{}",
+                tr!(
+                    "  スクリーンショット保存エラー: {}",
+                    "  Failed to save screenshot: {}",
+                    e
+                )
+            );
+            None
+        }
+    };
+    CaptchaError {
+        url: tab.get_url(),
+        screenshot,
+    }
+    .into()
+}
+
+// ページ全体（スクロール外も含む）のスクリーンショットを query_dir/<file_stem>.png に保存する
+fn save_page_screenshot(
+    tab: &Tab,
+    query_dir: &Path,
+    file_stem: &str,
+    config: &Config,
+) -> Result<()> {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use headless_chrome::protocol::cdp::Page::{
+        CaptureScreenshot, CaptureScreenshotFormatOption, GetLayoutMetrics, Viewport,
+    };
+
+    // low_memory では縦の長さと解像度を抑える（全体を撮るとデコード時に数百MBになることがある）
+    const LOW_MEMORY_MAX_HEIGHT: f64 = 4000.0;
+    let size = tab.call_method(GetLayoutMetrics(None))?.css_content_size;
+    let (height, scale) = if config.low_memory {
+        (size.height.min(LOW_MEMORY_MAX_HEIGHT), 0.5)
+    } else {
+        (size.height, 1.0)
+    };
+    let data = tab
+        .call_method(CaptureScreenshot {
+            format: Some(CaptureScreenshotFormatOption::Png),
+            quality: None,
+            clip: Some(Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: size.width,
+                height,
+                scale,
+            }),
+            from_surface: Some(true),
+            capture_beyond_viewport: Some(true),
+            optimize_for_speed: None,
+        })?
+        .data;
+    fs::write(
+        query_dir.join(format!("{}.png", file_stem)),
+        STANDARD.decode(data)?,
+    )?;
+    log_action(
+        "screenshot",
+        serde_json::json!({ "file": format!("{}.png", file_stem) }),
+    );
+    Ok(())
+}
+
+// ============================================================
+// 操作ログ（actions.jsonl）
+// ============================================================
+// 実行中クエリの操作ログ。操作する関数へ引数で回さずに済むようスレッドごとに1つ持つ
+thread_local! {
+    static ACTION_LOG: RefCell<Option<fs::File>> = const { RefCell::new(None) };
+}
+
+fn start_action_log(query_dir: &Path) {
+    let path = query_dir.join("actions.jsonl");
+    match fs::OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => ACTION_LOG.with(|log| *log.borrow_mut() = Some(file)),
+        Err(e) => info!(
+            "{}",
+            tr!(
+                "操作ログを開けません ({}): {}",
+                "Cannot open action log ({}): {}",
+                path.display(),
+                e
+            )
+        ),
+    }
+}
+
+fn stop_action_log() {
+    ACTION_LOG.with(|log| *log.borrow_mut() = None);
+}
+
+// 1操作1行で追記する。ログが開かれていなければ何もしない
+fn log_action(action: &str, detail: serde_json::Value) {
+    ACTION_LOG.with(|log| {
+        if let Some(file) = log.borrow_mut().as_mut() {
+            let entry = serde_json::json!({
+                "timestamp": Local::now().to_rfc3339(),
+                "action": action,
+                "detail": detail,
             });
-            
-            // 6. ページ遷移系イベント
-            window.addEventListener('popstate', dismiss);
-            window.addEventListener('hashchange', dismiss);
-            
-            // 7. requestAnimationFrame監視（最初の30秒間のみ）
-            let rafActive = true;
-            const rafCheck = () => {
-                if (!rafActive) return;
-                dismiss();
-                setTimeout(() => requestAnimationFrame(rafCheck), 200);
+            if let Err(e) = writeln!(file, "{}", entry) {
+                warn!(
+                    "{}",
+                    tr!(
+                        "  操作ログ書き込みエラー: {}",
+                        "  Failed to write action log: {}",
+                        e
+                    )
+                );
+            }
+        }
+    });
+}
+
+// 直近の遷移の表示時間を計測する。perf_budget 未設定なら計測しない
+fn measure_page_timing(tab: &Tab, config: &Config) -> Option<PageTiming> {
+    let budget = config.perf_budget.as_ref()?;
+    let metrics = match tab.call_method(Performance::GetMetrics(None)) {
+        Ok(r) => r.metrics,
+        Err(e) => {
+            warn!(
+                "{}",
+                tr!(
+                    "  表示時間の計測エラー: {}",
+                    "  Failed to measure load time: {}",
+                    e
+                )
+            );
+            return None;
+        }
+    };
+    // 値は秒単位の単調時刻
+    let metric = |name: &str| {
+        metrics
+            .iter()
+            .find(|m| m.name == name)
+            .map(|m| m.value)
+            .filter(|v| *v > 0.0)
+    };
+    let navigation_start = metric("NavigationStart")?;
+    let since_start =
+        |name: &str| metric(name).map(|v| ((v - navigation_start) * 1000.0).max(0.0) as u64);
+    let dom_content_loaded_ms = since_start("DomContentLoaded")?;
+    Some(PageTiming {
+        dom_content_loaded_ms,
+        first_meaningful_paint_ms: since_start("FirstMeaningfulPaint"),
+        over_budget: dom_content_loaded_ms > budget.max_ms,
+    })
+}
+
+// 予算超過が続いたら警告する（一時的な遅延ではなく減速・優先度低下の兆候）
+fn track_slow_pages(state: &mut RunState, timing: &PageTiming, config: &Config) {
+    let Some(budget) = &config.perf_budget else {
+        return;
+    };
+    if !timing.over_budget {
+        state.slow_streak = 0;
+        return;
+    }
+    state.slow_streak += 1;
+    info!(
+        "{}",
+        tr!(
+            "  表示時間 {}ms（予算 {}ms 超過、{}ページ連続）",
+            "  Load time {}ms (over the {}ms budget, {} pages in a row)",
+            timing.dom_content_loaded_ms,
+            budget.max_ms,
+            state.slow_streak
+        )
+    );
+    if state.slow_streak == budget.consecutive_pages {
+        warn!(
+            "{}",
+            tr!(
+                "  警告: {}ページ連続で表示時間が予算を超過。IPが減速・優先度低下されている可能性があります",
+                "  Warning: load time over budget for {} pages in a row. The IP may be throttled or deprioritized",
+                state.slow_streak
+            )
+        );
+        log_action(
+            "perf_budget_exceeded",
+            serde_json::json!({ "consecutive_pages": state.slow_streak, "last": timing }),
+        );
+    }
+}
+
+// actions.jsonl を時系列の表にし、スクリーンショットはサムネイルとして差し込む
+fn write_session_replay(query_dir: &Path) -> Result<()> {
+    let content = fs::read_to_string(query_dir.join("actions.jsonl"))?;
+    let entries: Vec<serde_json::Value> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let timestamp_of = |entry: &serde_json::Value| {
+        entry["timestamp"]
+            .as_str()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+    };
+    let first = entries.iter().find_map(timestamp_of);
+
+    let mut rows = String::new();
+    for entry in &entries {
+        let timestamp = timestamp_of(entry);
+        let time = timestamp
+            .map(|t| t.format("%H:%M:%S%.3f").to_string())
+            .unwrap_or_default();
+        let elapsed = match (timestamp, first) {
+            (Some(t), Some(f)) => format!("+{:.1}s", (t - f).num_milliseconds() as f64 / 1000.0),
+            _ => String::new(),
+        };
+        let action = entry["action"].as_str().unwrap_or("");
+        let detail = &entry["detail"];
+        let cell = match detail["file"].as_str() {
+            Some(file) if action == "screenshot" => format!(
+                r#"<a href="{0}"><img src="{0}" loading="lazy"></a><br>{0}"#,
+                escape_html(file)
+            ),
+            _ => escape_html(&detail.to_string()),
+        };
+        rows.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(action),
+            time,
+            elapsed,
+            escape_html(action),
+            cell
+        ));
+    }
+
+    let title = query_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>replay: {title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 1em; }}
+table {{ border-collapse: collapse; }}
+td {{ border-bottom: 1px solid #ddd; padding: 4px 8px; vertical-align: top; font-size: 13px; }}
+td:nth-child(4) {{ font-family: monospace; word-break: break-all; }}
+tr.navigate, tr.landed, tr.reload {{ background: #eef5ff; }}
+tr.screenshot {{ background: #f6f6f6; }}
+img {{ width: 240px; border: 1px solid #ccc; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>{count} 操作</p>
+<table>
+<tr><th>時刻</th><th>経過</th><th>操作</th><th>内容</th></tr>
+{rows}</table>
+</body>
+</html>
+"#,
+        title = escape_html(&title),
+        count = entries.len(),
+        rows = rows
+    );
+    fs::write(query_dir.join("replay.html"), html)?;
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn save_page_html(query_dir: &Path, file_stem: &str, html: &str) {
+    if let Err(e) = fs::write(query_dir.join(format!("{}.html", file_stem)), html) {
+        warn!(
+            "{}",
+            tr!("  HTML保存エラー: {}", "  Failed to save HTML: {}", e)
+        );
+    }
+}
+
+// 連続CAPTCHA回数に応じた待機秒数（60秒から倍々、最大30分）
+fn captcha_backoff_secs(consecutive: u32) -> u64 {
+    (60_u64 << consecutive.saturating_sub(1).min(5)).min(1800)
+}
+
+// ============================================================
+// 外部CAPTCHA解決サービス（captcha-solver フィーチャー）
+// ============================================================
+// reCAPTCHAを外部サービスで解いてトークンを送信する。送信できたら true
+#[cfg(feature = "captcha-solver")]
+fn try_solve_captcha(tab: &Arc<Tab>, config: &Config) -> Result<bool> {
+    let Some(solver) = &config.captcha_solver else {
+        return Ok(false);
+    };
+    let page_url = tab.get_url();
+    let Some((site_key, data_s)) = extract_recaptcha_params(&tab.get_content()?) else {
+        warn!(
+            "{}",
+            tr!(
+                "  reCAPTCHAのsitekeyが見つからないため自動解決できません",
+                "  reCAPTCHA sitekey not found; cannot solve automatically"
+            )
+        );
+        return Ok(false);
+    };
+
+    info!(
+        "{}",
+        tr!(
+            "  CAPTCHA解決サービス（{:?}）に送信中...",
+            "  Sending to CAPTCHA solving service ({:?})...",
+            solver.provider
+        )
+    );
+    let token = match solver.provider {
+        CaptchaProvider::TwoCaptcha => {
+            solve_with_2captcha(tab, solver, &site_key, data_s.as_deref(), &page_url)?
+        }
+        CaptchaProvider::AntiCaptcha => {
+            solve_with_anticaptcha(tab, solver, &site_key, data_s.as_deref(), &page_url)?
+        }
+    };
+    info!(
+        "{}",
+        tr!("  トークン取得。送信します", "  Token received. Submitting")
+    );
+
+    let script = format!(
+        r#"(() => {{
+            const token = {};
+            const area = document.getElementById('g-recaptcha-response');
+            if (!area) return false;
+            area.value = token;
+            const form = document.getElementById('captcha-form') || area.closest('form');
+            if (!form) return false;
+            form.submit();
+            return true;
+        }})()"#,
+        serde_json::to_string(&token)?
+    );
+    let submitted = tab.evaluate(&script, false)?;
+    if !submitted.value.and_then(|v| v.as_bool()).unwrap_or(false) {
+        anyhow::bail!(tr!(
+            "トークンの送信先フォームが見つかりません",
+            "Form for submitting the token not found"
+        ));
+    }
+    tab.wait_until_navigated()?;
+    human_pause_with_keepalive(tab, 1500)?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "captcha-solver"))]
+fn try_solve_captcha(_tab: &Arc<Tab>, config: &Config) -> Result<bool> {
+    if config.captcha_solver.is_some() {
+        info!(
+            "{}",
+            tr!(
+                "  captcha_solver が設定されていますが、captcha-solver フィーチャー無しでビルドされています",
+                "  captcha_solver is configured, but this build lacks the captcha-solver feature"
+            )
+        );
+    }
+    Ok(false)
+}
+
+// sorryページの reCAPTCHA から (sitekey, data-s) を取り出す
+#[cfg(feature = "captcha-solver")]
+fn extract_recaptcha_params(html: &str) -> Option<(String, Option<String>)> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("[data-sitekey]").unwrap();
+    let element = document.select(&selector).next()?;
+    let site_key = element.value().attr("data-sitekey")?.to_string();
+    let data_s = element.value().attr("data-s").map(|s| s.to_string());
+    Some((site_key, data_s))
+}
+
+#[cfg(feature = "captcha-solver")]
+fn solve_with_2captcha(
+    tab: &Arc<Tab>,
+    solver: &CaptchaSolverConfig,
+    site_key: &str,
+    data_s: Option<&str>,
+    page_url: &str,
+) -> Result<String> {
+    let mut request = ureq::get("https://2captcha.com/in.php")
+        .query("key", &solver.api_key)
+        .query("method", "userrecaptcha")
+        .query("googlekey", site_key)
+        .query("pageurl", page_url)
+        .query("json", "1");
+    if let Some(data_s) = data_s {
+        request = request.query("data-s", data_s);
+    }
+    let submitted: serde_json::Value = request.call()?.body_mut().read_json()?;
+    if submitted["status"] != 1 {
+        anyhow::bail!(tr!(
+            "2captcha 送信エラー: {}",
+            "2captcha submit error: {}",
+            submitted["request"]
+        ));
+    }
+    let id = submitted["request"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let deadline = Instant::now() + Duration::from_secs(solver.timeout_secs);
+    while Instant::now() < deadline {
+        human_pause_with_keepalive(tab, 5000)?;
+        let result: serde_json::Value = ureq::get("https://2captcha.com/res.php")
+            .query("key", &solver.api_key)
+            .query("action", "get")
+            .query("id", &id)
+            .query("json", "1")
+            .call()?
+            .body_mut()
+            .read_json()?;
+        match (result["status"].as_i64(), result["request"].as_str()) {
+            (Some(1), Some(token)) => return Ok(token.to_string()),
+            (_, Some("CAPCHA_NOT_READY")) => continue,
+            _ => anyhow::bail!(tr!(
+                "2captcha 結果エラー: {}",
+                "2captcha result error: {}",
+                result["request"]
+            )),
+        }
+    }
+    anyhow::bail!(tr!(
+        "2captcha が {}秒以内に応答しませんでした",
+        "2captcha did not respond within {} seconds",
+        solver.timeout_secs
+    ))
+}
+
+#[cfg(feature = "captcha-solver")]
+fn solve_with_anticaptcha(
+    tab: &Arc<Tab>,
+    solver: &CaptchaSolverConfig,
+    site_key: &str,
+    data_s: Option<&str>,
+    page_url: &str,
+) -> Result<String> {
+    let mut task = serde_json::json!({
+        "type": "RecaptchaV2TaskProxyless",
+        "websiteURL": page_url,
+        "websiteKey": site_key,
+    });
+    if let Some(data_s) = data_s {
+        task["recaptchaDataSValue"] = data_s.into();
+    }
+    let created: serde_json::Value = ureq::post("https://api.anti-captcha.com/createTask")
+        .send_json(serde_json::json!({ "clientKey": solver.api_key, "task": task }))?
+        .body_mut()
+        .read_json()?;
+    if created["errorId"] != 0 {
+        anyhow::bail!(tr!(
+            "anti-captcha 送信エラー: {}",
+            "anti-captcha submit error: {}",
+            created["errorDescription"]
+        ));
+    }
+    let task_id = created["taskId"].clone();
+
+    let deadline = Instant::now() + Duration::from_secs(solver.timeout_secs);
+    while Instant::now() < deadline {
+        human_pause_with_keepalive(tab, 5000)?;
+        let result: serde_json::Value = ureq::post("https://api.anti-captcha.com/getTaskResult")
+            .send_json(serde_json::json!({ "clientKey": solver.api_key, "taskId": task_id }))?
+            .body_mut()
+            .read_json()?;
+        if result["errorId"] != 0 {
+            anyhow::bail!(tr!(
+                "anti-captcha 結果エラー: {}",
+                "anti-captcha result error: {}",
+                result["errorDescription"]
+            ));
+        }
+        if result["status"] == "ready" {
+            if let Some(token) = result["solution"]["gRecaptchaResponse"].as_str() {
+                return Ok(token.to_string());
+            }
+            anyhow::bail!(tr!(
+                "anti-captcha の応答にトークンがありません",
+                "anti-captcha response has no token"
+            ));
+        }
+    }
+    anyhow::bail!(tr!(
+        "anti-captcha が {}秒以内に応答しませんでした",
+        "anti-captcha did not respond within {} seconds",
+        solver.timeout_secs
+    ))
+}
+
+// 結果ページのブロック状態を確認し、軽い回復処理を行ったうえでHTMLを返す。
+// JSチャレンジは待機後に再読み込み、トラフィック警告は長めに休むだけで
+// ブラウザの再起動やプロファイルの作り直しはしない。CAPTCHAはエラーとして返す
+fn check_block_state(
+    tab: &Arc<Tab>,
+    engine: &dyn SearchEngine,
+    query_dir: &Path,
+    config: &Config,
+) -> Result<(String, Option<BlockState>)> {
+    let mut rng = WyRand::new();
+    let mut html = tab.get_content()?;
+    let mut state = engine.detect_block(&tab.get_url(), &html);
+
+    if state == BlockState::JsChallenge {
+        let wait = rng.generate_range(8000_u64..=15000);
+        info!(
+            "{}",
+            tr!(
+                "  {}を検出: {}ms 待機して再読み込みします",
+                "  Detected {}: waiting {}ms before reloading",
+                state.label(),
+                wait
+            )
+        );
+        human_pause_with_keepalive(tab, wait)?;
+        log_action("reload", serde_json::json!({ "url": tab.get_url() }));
+        tab.reload(false, None)?;
+        tab.wait_until_navigated()?;
+        human_pause_with_keepalive(tab, 960)?;
+        html = tab.get_content()?;
+        state = engine.detect_block(&tab.get_url(), &html);
+        if state == BlockState::JsChallenge {
+            anyhow::bail!(tr!(
+                "JSチャレンジが解除されませんでした",
+                "The JS challenge was not cleared"
+            ));
+        }
+    }
+
+    match state {
+        BlockState::Clear => Ok((html, None)),
+        BlockState::SoftWarning => {
+            let wait = rng.generate_range(20000_u64..=40000);
+            info!(
+                "{}",
+                tr!(
+                    "  {}を検出: {}ms 休止して続行します",
+                    "  Detected {}: pausing {}ms before continuing",
+                    state.label(),
+                    wait
+                )
+            );
+            human_pause_with_keepalive(tab, wait)?;
+            Ok((html, Some(state)))
+        }
+        BlockState::Captcha => {
+            if config.save_html {
+                let stem = format!("captcha-{}", Local::now().format("%Y%m%d-%H%M%S"));
+                save_page_html(query_dir, &stem, &html);
+            }
+            let error = captcha_error(tab, query_dir);
+            match try_solve_captcha(tab, config) {
+                Ok(true) => {
+                    let html = tab.get_content()?;
+                    if engine.detect_block(&tab.get_url(), &html) == BlockState::Clear {
+                        info!(
+                            "{}",
+                            tr!(
+                                "  CAPTCHAを自動解決しました",
+                                "  CAPTCHA solved automatically"
+                            )
+                        );
+                        return Ok((html, None));
+                    }
+                    warn!(
+                        "{}",
+                        tr!(
+                            "  CAPTCHA自動解決後も通常ページに戻りませんでした",
+                            "  Page did not return to normal after solving the CAPTCHA"
+                        )
+                    );
+                }
+                Ok(false) => {}
+                Err(e) => warn!(
+                    "{}",
+                    tr!(
+                        "  CAPTCHA自動解決エラー: {}",
+                        "  CAPTCHA auto-solve error: {}",
+                        e
+                    )
+                ),
+            }
+            Err(error)
+        }
+        BlockState::JsChallenge => anyhow::bail!(tr!(
+            "{}ページを検出しました",
+            "Detected a {} page",
+            state.label()
+        )),
+    }
+}
+
+// ============================================================
+// 検索エンジン抽象化
+// ============================================================
+trait SearchEngine {
+    fn name(&self) -> &'static str;
+
+    // 検索開始時に開くトップページ
+    fn home_url(&self) -> String;
+
+    // 検索語を入力するテキストボックス
+    fn search_box_selector(&self) -> &'static str;
+
+    // 検索語を入力して送信する。サジェストから送信した場合はその位置（1始まり）を返す
+    fn submit_query(&self, tab: &Arc<Tab>, query: &str, config: &Config) -> Result<SubmitOutcome>;
+
+    // 入力中に表示されるサジェストの各候補
+    fn suggestion_selector(&self) -> Option<&'static str> {
+        None
+    }
+
+    // 検索結果ページのHTMLから (タイトル, URL) を上位順に取り出す
+    fn parse_results(&self, html: &str) -> Vec<SearchResult>;
+
+    // parse_results と、結果を取り出せた抽出方法の名前（方法が1つしかなければ None）
+    fn parse_results_with_strategy(&self, html: &str) -> (Vec<SearchResult>, Option<&'static str>) {
+        (self.parse_results(html), None)
+    }
+
+    // 強調スニペット・他の人はこちらも質問・ナレッジパネル等
+    fn parse_serp_features(&self, _html: &str) -> Option<SerpFeatures> {
+        None
+    }
+
+    // 広告枠（自然検索結果とは別に上から順に）
+    fn parse_ads(&self, _html: &str) -> Vec<AdResult> {
+        Vec::new()
+    }
+
+    // 地図付きのローカル検索枠（ローカルパック）
+    fn parse_local_results(&self, _html: &str) -> Vec<LocalResult> {
+        Vec::new()
+    }
+
+    // 自然検索結果の見出し。抽出件数との突き合わせに使う
+    fn result_heading_selector(&self) -> Option<&'static str> {
+        None
+    }
+
+    // 「関連する検索」「他の人はこちらも検索」のリンク
+    fn related_queries_selector(&self) -> Option<&'static str> {
+        None
+    }
+
+    // 「約 1,234,000 件 （0.42 秒）」の行
+    fn result_stats_selector(&self) -> Option<&'static str> {
+        None
+    }
+
+    // 検索結果ページのURLから、同じ検索語のニュース・画像・動画タブのURLを作る。未対応なら None
+    fn vertical_url(&self, _current_url: &str, _search_type: SearchType) -> Option<String> {
+        None
+    }
+
+    // ニュース・画像・動画タブ専用のパーサー。None ならウェブ用のパーサーで代用する
+    fn parse_vertical_results(
+        &self,
+        _search_type: SearchType,
+        _html: &str,
+    ) -> Option<Vec<SearchResult>> {
+        None
+    }
+
+    // 1ページ分をまとめて解析する
+    fn parse_page(&self, html: &str, search_type: SearchType) -> ParsedPage {
+        if !search_type.is_web() {
+            // 強調スニペットや広告などはウェブ検索のみ
+            return ParsedPage {
+                search_type,
+                results: self
+                    .parse_vertical_results(search_type, html)
+                    .unwrap_or_else(|| self.parse_results(html)),
+                ..Default::default()
             };
-            requestAnimationFrame(rafCheck);
-            setTimeout(() => { rafActive = false; }, 30000);
-            
-            // 開始
-            if (document.body) {
-                startObserver();
-            } else {
-                document.addEventListener('DOMContentLoaded', () => {
-                    startObserver();
-                    dismiss();
-                });
+        }
+        let (results, extraction_strategy) = self.parse_results_with_strategy(html);
+        let discrepancy = self
+            .result_heading_selector()
+            .and_then(|selector| check_extraction_count(html, selector, results.len()));
+        let (total_results, search_time_ms) = self
+            .result_stats_selector()
+            .and_then(|selector| {
+                let document = Html::parse_document(html);
+                let selector = Selector::parse(selector).unwrap();
+                document.select(&selector).next().map(element_text)
+            })
+            .map_or((None, None), |text| parse_result_stats(&text));
+        ParsedPage {
+            search_type,
+            results,
+            serp_features: self.parse_serp_features(html),
+            ads: self.parse_ads(html),
+            local_results: self.parse_local_results(html),
+            related_queries: self
+                .related_queries_selector()
+                .map(|selector| extract_related_queries(html, selector))
+                .unwrap_or_default(),
+            discrepancy,
+            total_results,
+            search_time_ms,
+            timing: None,
+            extraction_strategy,
+            filtered_count: 0,
+        }
+    }
+
+    // 次のページへ遷移する。次ページが無ければ false
+    fn next_page(&self, tab: &Arc<Tab>) -> Result<bool>;
+
+    // 「もっと見る」型で、ページHTMLに前ページまでの結果も含まれる場合は true
+    fn cumulative_results(&self, _html: &str) -> bool {
+        false
+    }
+
+    // 結果ページがブロック・警告状態かどうか
+    fn detect_block(&self, _url: &str, _html: &str) -> BlockState {
+        BlockState::Clear
+    }
+}
+
+// 検索ボックスへのフォーカスと入力（エンジン共通）
+// suggestion_capture に応じて入力中のサジェストも記録して返す
+fn type_into_search_box(
+    tab: &Arc<Tab>,
+    engine: &dyn SearchEngine,
+    query: &str,
+    config: &Config,
+) -> Result<Vec<SuggestionSnapshot>> {
+    let behavior = &config.behavior;
+    let selector = engine.search_box_selector();
+    let mut rng = WyRand::new();
+    let search_box = tab.wait_for_element(selector)?;
+    let focused_by_keyboard = rng.generate_range(0_u32..100) < behavior.keyboard_focus_rate
+        && focus_search_box_by_keyboard(tab)?;
+    if !focused_by_keyboard {
+        log_action("click", serde_json::json!({ "target": selector }));
+        human_click(tab, &search_box)?;
+    }
+
+    let suggestion_selector = engine
+        .suggestion_selector()
+        .filter(|_| config.suggestion_capture != SuggestionCaptureMode::Off);
+    let mut snapshots = Vec::new();
+    let mut capture = |typed: &str| {
+        if let Some(selector) = suggestion_selector {
+            match read_suggestions(tab, selector) {
+                Ok(suggestions) if !suggestions.is_empty() => snapshots.push(SuggestionSnapshot {
+                    typed: typed.to_string(),
+                    suggestions,
+                }),
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "{}",
+                    tr!(
+                        "  サジェスト取得エラー: {}",
+                        "  Failed to capture suggestions: {}",
+                        e
+                    )
+                ),
+            }
+        }
+    };
+    if config.suggestion_capture == SuggestionCaptureMode::Keystroke {
+        human_type_observed(tab, query, behavior, &mut capture)?;
+    } else {
+        human_type_medium(tab, query, behavior)?;
+    }
+    thread::sleep(Duration::from_millis(450));
+    // 入力し終えた時点（keystroke でも最後の1文字の直後は表示が間に合わないことがある）
+    capture(query);
+    if snapshots.len() >= 2 && snapshots[snapshots.len() - 1] == snapshots[snapshots.len() - 2] {
+        snapshots.pop();
+    }
+
+    if rng.generate_range(0_u32..100) < behavior.keyboard_suggest_rate {
+        browse_suggestions_by_keyboard(tab)?;
+    }
+    Ok(snapshots)
+}
+
+// 表示中のサジェスト候補（各候補の1行目）
+fn read_suggestions(tab: &Tab, selector: &str) -> Result<Vec<String>> {
+    let script = format!(
+        r#"JSON.stringify([...document.querySelectorAll({})]
+            .map(e => (e.innerText || '').split('\n')[0].trim())
+            .filter(t => t))"#,
+        serde_json::to_string(selector)?
+    );
+    let json = tab
+        .evaluate(&script, false)?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "[]".to_string());
+    Ok(serde_json::from_str(&json)?)
+}
+
+// 検索語の送信結果
+struct SubmitOutcome {
+    // サジェストから送信した場合はその位置（1始まり）
+    suggestion_position: Option<usize>,
+    suggestions: Vec<SuggestionSnapshot>,
+}
+
+// ある入力途中の状態で表示されていたサジェスト
+#[derive(Serialize, Debug, PartialEq)]
+struct SuggestionSnapshot {
+    typed: String,
+    suggestions: Vec<String>,
+}
+
+fn save_suggestions(
+    query_dir: &Path,
+    engine: &str,
+    query: &str,
+    snapshots: &[SuggestionSnapshot],
+) -> Result<()> {
+    let json = serde_json::json!({
+        "query": query,
+        "engine": engine,
+        "captured_at": Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "snapshots": snapshots,
+    });
+    fs::write(
+        query_dir.join("suggestions.json"),
+        serde_json::to_string_pretty(&json)?,
+    )?;
+    Ok(())
+}
+
+// 連続スクロール型の「もっと見る」（デスクトップ・モバイル共通）
+const GOOGLE_MORE_RESULTS_SELECTOR: &str =
+    "a[aria-label='もっと見る'], a[aria-label='More results'], a[aria-label='More search results']";
+// 連続スクロールで追加された結果のまとまり
+const GOOGLE_MORE_BATCH_SELECTOR: &str = "[id^='arc-srp_']";
+
+struct GoogleEngine {
+    domain: String,
+    hl: Option<String>,
+    gl: Option<String>,
+    num: Option<String>,
+    tbs: Option<String>,
+    safe: Option<&'static str>,
+    selectors: SelectorsConfig,
+    // モバイル版の検索結果（見出しが h3 ではなく role="heading"）
+    mobile: bool,
+}
+
+impl GoogleEngine {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            domain: config.google_domain.clone(),
+            hl: config.hl.clone(),
+            gl: config.gl.clone(),
+            num: config
+                .results_per_page
+                .map(|n| n.clamp(10, 100).to_string()),
+            // 不正な値は起動時の検証で止まっている
+            tbs: config.date_range.as_ref().and_then(|r| r.tbs().ok()),
+            safe: config.safe_search.and_then(SafeSearch::google_param),
+            selectors: config.selectors.clone(),
+            mobile: config.device == DeviceKind::Mobile,
+        }
+    }
+
+    fn locale_params(&self) -> Vec<(&'static str, &str)> {
+        let mut params = Vec::new();
+        if let Some(hl) = &self.hl {
+            params.push(("hl", hl.as_str()));
+        }
+        if let Some(gl) = &self.gl {
+            params.push(("gl", gl.as_str()));
+        }
+        params
+    }
+
+    // 検索フォームに足すパラメータ（言語・地域、1ページの件数、期間とセーフサーチ）
+    fn form_params(&self) -> Vec<(&'static str, &str)> {
+        let mut params = self.locale_params();
+        if let Some(num) = &self.num {
+            params.push(("num", num.as_str()));
+        }
+        if let Some(tbs) = &self.tbs {
+            params.push(("tbs", tbs.as_str()));
+        }
+        if let Some(safe) = self.safe {
+            params.push(("safe", safe));
+        }
+        params
+    }
+
+    // 検索フォームに hl/gl/num/tbs/safe の hidden input を追加し、送信されるURLに確実に含める
+    fn inject_form_params(&self, tab: &Arc<Tab>) -> Result<()> {
+        for (name, value) in self.form_params() {
+            let script = format!(
+                r#"(() => {{
+                    const form = document.querySelector('form[action="/search"]');
+                    if (!form) return false;
+                    let input = form.querySelector('input[name="{name}"]');
+                    if (!input) {{
+                        input = document.createElement('input');
+                        input.type = 'hidden';
+                        input.name = '{name}';
+                        form.appendChild(input);
+                    }}
+                    input.value = {value};
+                    return true;
+                }})()"#,
+                name = name,
+                value = serde_json::to_string(value)?
+            );
+            tab.evaluate(&script, false)?;
+        }
+        Ok(())
+    }
+
+    // 連続スクロール型の結果ページで次のまとまりを読み込む
+    // 「もっと見る」ボタンがあれば押し、無ければ下端までスクロールして自動読み込みを待つ
+    // 連続スクロール型でなければ None
+    fn load_more_results(&self, tab: &Arc<Tab>) -> Result<Option<bool>> {
+        const RESULT_COUNT_JS: &str =
+            "document.querySelectorAll('a h3, a [role=\"heading\"][aria-level=\"3\"]').length";
+
+        let count_before = tab.evaluate(RESULT_COUNT_JS, false)?.value;
+        match tab.wait_for_element_with_custom_timeout(
+            GOOGLE_MORE_RESULTS_SELECTOR,
+            Duration::from_secs(2),
+        ) {
+            Ok(more_button) => {
+                log_action("click", serde_json::json!({ "target": "more_results" }));
+                human_click(tab, &more_button)?;
+            }
+            Err(_) => {
+                if tab.find_element(GOOGLE_MORE_BATCH_SELECTOR).is_err() {
+                    return Ok(None);
+                }
+                log_action("scroll", serde_json::json!({ "to": "bottom" }));
+                tab.evaluate(
+                    "window.scrollTo({ top: document.body.scrollHeight, behavior: 'smooth' })",
+                    false,
+                )?;
+            }
+        }
+
+        for _ in 0..20 {
+            thread::sleep(Duration::from_millis(250));
+            if tab.evaluate(RESULT_COUNT_JS, false)?.value != count_before {
+                return Ok(Some(true));
+            }
+        }
+        Ok(Some(false))
+    }
+
+    // 「次へ」のリンクが無いときは start= を進めたURLを直接開く
+    // 結果が無いページ（最終ページの先）では遷移しない
+    fn next_page_by_start(&self, tab: &Arc<Tab>) -> Result<bool> {
+        if self.parse_results(&tab.get_content()?).is_empty() {
+            return Ok(false);
+        }
+        let Ok(mut url) = url::Url::parse(&tab.get_url()) else {
+            return Ok(false);
+        };
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .and_then(|(_, v)| v.parse::<usize>().ok())
+        };
+        let start = param("start").unwrap_or(0);
+        let next_start = start + param("num").unwrap_or(10);
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(k, _)| k != "start")
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair("start", &next_start.to_string());
+
+        info!(
+            "{}",
+            tr!(
+                "  「次へ」が無いため start={} を直接開きます",
+                "  No \"Next\" link; opening start={} directly",
+                next_start
+            )
+        );
+        log_action(
+            "navigate",
+            serde_json::json!({ "url": url.as_str(), "fallback": "start" }),
+        );
+        tab.navigate_to(url.as_str())?;
+        tab.wait_until_navigated()?;
+        if self.parse_results(&tab.get_content()?).is_empty() {
+            info!(
+                "{}",
+                tr!(
+                    "  start={} に結果がありません",
+                    "  No results at start={}",
+                    next_start
+                )
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+impl SearchEngine for GoogleEngine {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn home_url(&self) -> String {
+        let base = format!("https://{}/", self.domain);
+        let params = self.locale_params();
+        if params.is_empty() {
+            return base;
+        }
+        url::Url::parse_with_params(&base, &params)
+            .map(|u| u.to_string())
+            .unwrap_or(base)
+    }
+
+    fn search_box_selector(&self) -> &'static str {
+        "textarea[name='q']"
+    }
+
+    fn submit_query(&self, tab: &Arc<Tab>, query: &str, config: &Config) -> Result<SubmitOutcome> {
+        self.inject_form_params(tab)?;
+        let suggestions = type_into_search_box(tab, self, query, config)?;
+
+        let suggestion_position = match config.query_entry {
+            QueryEntryMode::Suggestion => click_matching_suggestion(tab, query)?,
+            QueryEntryMode::Enter => None,
+        };
+        if suggestion_position.is_none() {
+            log_action("key", serde_json::json!({ "keys": ["Enter"] }));
+            tab.press_key("Enter")?;
+        }
+        Ok(SubmitOutcome {
+            suggestion_position,
+            suggestions,
+        })
+    }
+
+    fn suggestion_selector(&self) -> Option<&'static str> {
+        Some("ul[role='listbox'] li div[role='option']")
+    }
+
+    fn parse_results(&self, html: &str) -> Vec<SearchResult> {
+        self.parse_results_with_strategy(html).0
+    }
+
+    fn parse_results_with_strategy(&self, html: &str) -> (Vec<SearchResult>, Option<&'static str>) {
+        if self.mobile {
+            (extract_google_mobile_results(html), None)
+        } else {
+            extract_search_results(html, &self.selectors)
+        }
+    }
+
+    fn parse_serp_features(&self, html: &str) -> Option<SerpFeatures> {
+        extract_google_serp_features(html)
+    }
+
+    fn result_heading_selector(&self) -> Option<&'static str> {
+        if self.mobile {
+            Some(r#"#search div[role="heading"][aria-level="3"]"#)
+        } else {
+            Some("#search h3")
+        }
+    }
+
+    fn result_stats_selector(&self) -> Option<&'static str> {
+        Some("#result-stats")
+    }
+
+    fn vertical_url(&self, current_url: &str, search_type: SearchType) -> Option<String> {
+        let tbm = match search_type {
+            SearchType::Web => return None,
+            SearchType::News => "nws",
+            SearchType::Images => "isch",
+            SearchType::Videos => "vid",
+        };
+        let mut url = url::Url::parse(current_url).ok()?;
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(k, _)| k != "tbm" && k != "start")
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair("tbm", tbm);
+        Some(url.to_string())
+    }
+
+    fn parse_vertical_results(
+        &self,
+        search_type: SearchType,
+        html: &str,
+    ) -> Option<Vec<SearchResult>> {
+        match search_type {
+            SearchType::News => Some(extract_google_news_results(html)),
+            SearchType::Images => Some(extract_google_image_results(html)),
+            // 動画タブはウェブと同じ結果ブロック
+            SearchType::Videos | SearchType::Web => None,
+        }
+    }
+
+    fn related_queries_selector(&self) -> Option<&'static str> {
+        Some("#bres a, div.s75CSd a, a.k8XOCe, div[data-initq] a")
+    }
+
+    fn parse_local_results(&self, html: &str) -> Vec<LocalResult> {
+        extract_google_local_results(html)
+    }
+
+    fn parse_ads(&self, html: &str) -> Vec<AdResult> {
+        extract_ads(
+            html,
+            &[
+                ("#tads", AdPlacement::Top),
+                ("#tadsb, #bottomads", AdPlacement::Bottom),
+            ],
+            &AdSelectors {
+                item: "div[data-text-ad]",
+                link: "a[href]",
+                title: "div[role='heading'], h3",
+                display_url: "span[role='text'], cite",
+                domain_attr: Some("data-dtld"),
+            },
+        )
+    }
+
+    fn detect_block(&self, url: &str, html: &str) -> BlockState {
+        detect_google_block(url, html)
+    }
+
+    fn next_page(&self, tab: &Arc<Tab>) -> Result<bool> {
+        match tab
+            .wait_for_element_with_custom_timeout(&self.selectors.next_page, Duration::from_secs(3))
+        {
+            Ok(next_button) => {
+                log_action("click", serde_json::json!({ "target": "next_page" }));
+                human_click(tab, &next_button)?;
+                tab.wait_until_navigated()?;
+                Ok(true)
             }
-            
-            console.log('[Gnezdo] 位置情報ポップアップ監視開始 (Ver 1.3)');
-        })();
-        "#,
-    ];
+            Err(_) => match self.load_more_results(tab)? {
+                Some(loaded) => Ok(loaded),
+                None => self.next_page_by_start(tab),
+            },
+        }
+    }
 
-    for script in scripts {
-        tab.call_method(AddScriptToEvaluateOnNewDocument {
-            source: script.to_string(),
-            world_name: None,
-            include_command_line_api: None,
-            run_immediately: None,
-        })?;
+    // 2回目以降に読み込まれた結果のまとまり（arc-srp_*）があれば連続スクロール型
+    fn cumulative_results(&self, html: &str) -> bool {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse(GOOGLE_MORE_BATCH_SELECTOR).unwrap();
+        document.select(&selector).next().is_some()
     }
+}
 
-    Ok(())
+struct BingEngine;
+
+impl SearchEngine for BingEngine {
+    fn name(&self) -> &'static str {
+        "bing"
+    }
+
+    fn home_url(&self) -> String {
+        "https://www.bing.com".to_string()
+    }
+
+    fn search_box_selector(&self) -> &'static str {
+        "input[name='q'], textarea[name='q']"
+    }
+
+    fn suggestion_selector(&self) -> Option<&'static str> {
+        Some("#sa_ul li.sa_sg, #sa_ul li[role='option']")
+    }
+
+    fn submit_query(&self, tab: &Arc<Tab>, query: &str, config: &Config) -> Result<SubmitOutcome> {
+        let suggestions = type_into_search_box(tab, self, query, config)?;
+        log_action("key", serde_json::json!({ "keys": ["Enter"] }));
+        tab.press_key("Enter")?;
+        Ok(SubmitOutcome {
+            suggestion_position: None,
+            suggestions,
+        })
+    }
+
+    fn parse_results(&self, html: &str) -> Vec<SearchResult> {
+        extract_bing_results(html)
+    }
+
+    fn result_heading_selector(&self) -> Option<&'static str> {
+        Some("#b_results > li:not(.b_ad) h2")
+    }
+
+    fn result_stats_selector(&self) -> Option<&'static str> {
+        Some(".sb_count")
+    }
+
+    fn vertical_url(&self, current_url: &str, search_type: SearchType) -> Option<String> {
+        let path = match search_type {
+            SearchType::Web => return None,
+            SearchType::News => "/news/search",
+            SearchType::Images => "/images/search",
+            SearchType::Videos => "/videos/search",
+        };
+        let current = url::Url::parse(current_url).ok()?;
+        let query = current
+            .query_pairs()
+            .find(|(k, _)| k == "q")
+            .map(|(_, v)| v.into_owned())?;
+        let mut url = current.join(path).ok()?;
+        url.query_pairs_mut().clear().append_pair("q", &query);
+        Some(url.to_string())
+    }
+
+    fn parse_vertical_results(
+        &self,
+        search_type: SearchType,
+        html: &str,
+    ) -> Option<Vec<SearchResult>> {
+        match search_type {
+            SearchType::News => Some(extract_bing_news_results(html)),
+            SearchType::Images => Some(extract_bing_image_results(html)),
+            SearchType::Videos | SearchType::Web => None,
+        }
+    }
+
+    fn related_queries_selector(&self) -> Option<&'static str> {
+        Some("li.b_ans .b_rs a, #brsv3 a, .b_rrsr a")
+    }
+
+    fn parse_ads(&self, html: &str) -> Vec<AdResult> {
+        extract_ads(
+            html,
+            &[
+                ("li.b_ad.b_adTop", AdPlacement::Top),
+                ("li.b_ad.b_adBottom", AdPlacement::Bottom),
+            ],
+            &AdSelectors {
+                item: "ul > li",
+                link: "h2 a",
+                title: "h2 a",
+                display_url: "cite",
+                domain_attr: None,
+            },
+        )
+    }
+
+    fn next_page(&self, tab: &Arc<Tab>) -> Result<bool> {
+        match tab.wait_for_element_with_custom_timeout("a.sb_pagN", Duration::from_secs(3)) {
+            Ok(next_button) => {
+                log_action("click", serde_json::json!({ "target": "next_page" }));
+                human_click(tab, &next_button)?;
+                tab.wait_until_navigated()?;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
 }
 
-// ============================================================
-// メイン
-// ============================================================
-fn main() -> Result<()> {
-    let program_start = Local::now();
-    println!("Gnezdo Ver 1.3 起動");
-    println!("開始時刻: {}", program_start.format("%Y-%m-%d %H:%M:%S"));
+struct DuckDuckGoEngine;
 
-    if cfg!(debug_assertions) {
-        println!("モード: デバッグ（カレントディレクトリ基準）");
-    } else {
-        println!("モード: リリース（実行ファイル基準）");
+impl SearchEngine for DuckDuckGoEngine {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
     }
 
-    // 設定読み込み
-    let config = load_config();
+    fn home_url(&self) -> String {
+        "https://duckduckgo.com".to_string()
+    }
 
-    // 設定内容表示
-    println!("\n--- 設定 ---");
-    println!("  profile_dir: {}", config.profile_dir);
-    println!("  chromium_path: {}", config.chromium_path);
-    println!("  result_dir: {}", config.result_dir);
-    println!("  max_pages: {}", config.max_pages);
-    println!(
-        "  max_consecutive_no_next: {}",
-        config.max_consecutive_no_next
-    );
-    println!("  search_queries: {:?}", config.search_queries);
-    println!("------------\n");
+    fn search_box_selector(&self) -> &'static str {
+        "input[name='q']"
+    }
+
+    fn suggestion_selector(&self) -> Option<&'static str> {
+        Some("ul[role='listbox'] li[role='option']")
+    }
 
-    let result_base = init_result_dir(&config, program_start)?;
+    fn submit_query(&self, tab: &Arc<Tab>, query: &str, config: &Config) -> Result<SubmitOutcome> {
+        let suggestions = type_into_search_box(tab, self, query, config)?;
+        log_action("key", serde_json::json!({ "keys": ["Enter"] }));
+        tab.press_key("Enter")?;
+        Ok(SubmitOutcome {
+            suggestion_position: None,
+            suggestions,
+        })
+    }
 
-    let mut manager = BrowserManager::new(&config);
-    manager.get_or_create()?;
+    fn parse_results(&self, html: &str) -> Vec<SearchResult> {
+        extract_duckduckgo_results(html)
+    }
 
-    if let Err(e) = run_all_queries(&mut manager, program_start, &result_base, &config) {
-        println!("致命的エラー: {}", e);
+    fn result_heading_selector(&self) -> Option<&'static str> {
+        Some("article[data-testid='result'] h2")
     }
 
-    let program_end = Local::now();
-    println!("\n========================================");
-    println!("プログラム終了");
-    println!("終了時刻: {}", program_end.format("%Y-%m-%d %H:%M:%S"));
-    println!(
-        "総実行時間: {}",
-        format_duration(program_start, program_end)
-    );
-    println!("========================================");
+    fn related_queries_selector(&self) -> Option<&'static str> {
+        Some("[data-testid='related-searches'] a, div.related-searches a")
+    }
 
-    println!("\n終了。Enterで閉じる...");
-    let _ = std::io::stdin().read_line(&mut String::new());
+    // 番号付きページは無いので「More results」ボタンで次の結果群を読み込む
+    fn next_page(&self, tab: &Arc<Tab>) -> Result<bool> {
+        const RESULT_COUNT_JS: &str =
+            "document.querySelectorAll('article[data-testid=\"result\"]').length";
 
-    Ok(())
+        let Ok(more_button) =
+            tab.wait_for_element_with_custom_timeout("#more-results", Duration::from_secs(3))
+        else {
+            return Ok(false);
+        };
+        let count_before = tab.evaluate(RESULT_COUNT_JS, false)?.value;
+
+        more_button.scroll_into_view()?;
+        thread::sleep(Duration::from_millis(300));
+        log_action("click", serde_json::json!({ "target": "more_results" }));
+        human_click(tab, &more_button)?;
+
+        for _ in 0..20 {
+            thread::sleep(Duration::from_millis(250));
+            if tab.evaluate(RESULT_COUNT_JS, false)?.value != count_before {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn cumulative_results(&self, _html: &str) -> bool {
+        true
+    }
+}
+
+// Yahoo! JAPAN（検索語パラメータは q ではなく p）
+struct YahooJapanEngine;
+
+impl SearchEngine for YahooJapanEngine {
+    fn name(&self) -> &'static str {
+        "yahoo"
+    }
+
+    fn home_url(&self) -> String {
+        "https://www.yahoo.co.jp".to_string()
+    }
+
+    fn search_box_selector(&self) -> &'static str {
+        "input[name='p']"
+    }
+
+    fn suggestion_selector(&self) -> Option<&'static str> {
+        Some("ul[role='listbox'] li[role='option'], .SearchBoxAssist li")
+    }
+
+    fn submit_query(&self, tab: &Arc<Tab>, query: &str, config: &Config) -> Result<SubmitOutcome> {
+        let suggestions = type_into_search_box(tab, self, query, config)?;
+        log_action("key", serde_json::json!({ "keys": ["Enter"] }));
+        tab.press_key("Enter")?;
+        Ok(SubmitOutcome {
+            suggestion_position: None,
+            suggestions,
+        })
+    }
+
+    fn parse_results(&self, html: &str) -> Vec<SearchResult> {
+        extract_yahoo_japan_results(html)
+    }
+
+    fn result_heading_selector(&self) -> Option<&'static str> {
+        Some("div.Algo h3")
+    }
+
+    fn result_stats_selector(&self) -> Option<&'static str> {
+        Some(".SearchResultsCount, #inf")
+    }
+
+    fn related_queries_selector(&self) -> Option<&'static str> {
+        Some("#RelatedQuery a, .sw-RelatedQuery a")
+    }
+
+    fn next_page(&self, tab: &Arc<Tab>) -> Result<bool> {
+        match tab.wait_for_element_with_custom_timeout(
+            ".Pagenation__next a, a.Pagenation__next",
+            Duration::from_secs(3),
+        ) {
+            Ok(next_button) => {
+                log_action("click", serde_json::json!({ "target": "next_page" }));
+                human_click(tab, &next_button)?;
+                tab.wait_until_navigated()?;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+fn create_engine(config: &Config) -> Box<dyn SearchEngine> {
+    match config.engine {
+        EngineKind::Google => Box::new(GoogleEngine::from_config(config)),
+        EngineKind::Bing => Box::new(BingEngine),
+        EngineKind::Duckduckgo => Box::new(DuckDuckGoEngine),
+        EngineKind::Yahoo => Box::new(YahooJapanEngine),
+    }
 }
 
 // ============================================================
-// 全クエリ実行
+// 単一クエリ実行
 // ============================================================
-fn run_all_queries(
-    manager: &mut BrowserManager,
-    program_start: DateTime<Local>,
-    result_base: &PathBuf,
+fn execute_single_query(
+    tab: &Arc<Tab>,
+    engine: &dyn SearchEngine,
+    sinks: &mut OutputSinks,
+    job: &QueryJob,
+    query_dir: &PathBuf,
+    state: &mut RunState,
     config: &Config,
 ) -> Result<()> {
-    let mut rng = WyRand::new();
-    let mut query_index = 0;
-    let mut retry_count = 0;
-    let mut consecutive_no_next = 0;
-    const MAX_RETRIES: u32 = 3;
-
-    let queries = &config.search_queries;
+    let query = job.query.as_str();
 
-    while query_index < queries.len() {
-        let query = &queries[query_index];
-        let query_start = Local::now();
+    // ===== 初期化 =====
+    log_action("navigate", serde_json::json!({ "url": "about:blank" }));
+    tab.navigate_to("about:blank")?;
+    thread::sleep(Duration::from_millis(300));
+    tab.evaluate("1", false)?;
 
-        println!("\n========================================");
-        println!("検索 {}/{}: 「{}」", query_index + 1, queries.len(), query);
-        println!("開始: {}", query_start.format("%H:%M:%S"));
-        println!("========================================");
+    // ===== トップページ =====
+    log_action("navigate", serde_json::json!({ "url": engine.home_url() }));
+    tab.navigate_to(&engine.home_url())?;
+    tab.wait_until_navigated()?;
+    verify_stealth_layers(tab, config)?;
+    human_pause_with_keepalive(tab, 960)?;
 
-        let query_dir = init_query_result_dir(result_base, query)?;
+    // ===== 検索ボックス入力・送信 =====
+    let submitted = engine.submit_query(tab, query, config)?;
+    let suggestion_position = submitted.suggestion_position;
+    tab.wait_until_navigated()?;
+    if !submitted.suggestions.is_empty()
+        && let Err(e) = save_suggestions(query_dir, engine.name(), query, &submitted.suggestions)
+    {
+        warn!(
+            "{}",
+            tr!(
+                "  suggestions.json 保存エラー: {}",
+                "  Failed to save suggestions.json: {}",
+                e
+            )
+        );
+    }
+    human_pause_with_keepalive(tab, 600)?;
 
-        let tab = match get_active_tab(manager) {
-            Ok(t) => t,
-            Err(e) => {
-                println!("タブ取得エラー: {}。ブラウザ再起動。", e);
-                if let Err(restart_err) = manager.restart() {
-                    println!("再起動失敗: {}。スキップ。", restart_err);
-                    query_index += 1;
-                    retry_count = 0;
-                    continue;
-                }
-                match get_active_tab(manager) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        println!("再起動後もタブ取得失敗: {}。スキップ。", e);
-                        query_index += 1;
-                        retry_count = 0;
-                        continue;
-                    }
-                }
+    // ===== ニュース・画像・動画タブへ移動 =====
+    let mut search_type = job.search_type;
+    if !search_type.is_web() {
+        match engine.vertical_url(&tab.get_url(), search_type) {
+            Some(url) => {
+                info!(
+                    "{}",
+                    tr!(
+                        "  {:?} タブへ移動",
+                        "  Switching to the {:?} tab",
+                        search_type
+                    )
+                );
+                log_action("navigate", serde_json::json!({ "url": url }));
+                tab.navigate_to(&url)?;
+                tab.wait_until_navigated()?;
+                human_pause_with_keepalive(tab, 600)?;
             }
-        };
+            None => {
+                warn!(
+                    "{}",
+                    tr!(
+                        "  警告: {} は search_type {:?} に未対応。ウェブ検索結果を記録します",
+                        "  Warning: {} does not support search_type {:?}. Recording web results instead",
+                        engine.name(),
+                        search_type
+                    )
+                );
+                search_type = SearchType::Web;
+            }
+        }
+    }
 
-        let _ = setup_stealth_cdp(&tab);
-        let _ = inject_stealth_scripts(&tab);
+    // ===== 検索結果ページループ =====
+    let mut already_saved = 0;
+    let mut previous_urls: HashSet<String> = HashSet::new();
+    for page in 0..config.max_pages {
+        let page_num = page + 1;
+        wait_while_paused(tab, state)?;
+        if state.shutdown_requested() || state.skip_current || state.take_skip_request() {
+            return Ok(());
+        }
+        info!(
+            "{}",
+            tr!("  ページ {}/{}", "  Page {}/{}", page_num, config.max_pages)
+        );
+        state.status.set_page(page_num);
+        // クリックやEnterの結果として実際に到達したURL
+        log_action(
+            "landed",
+            serde_json::json!({ "page": page_num, "url": tab.get_url() }),
+        );
 
-        match execute_single_query(&tab, query, &query_dir, &mut consecutive_no_next, config) {
-            Ok(_) => {
-                let query_end = Local::now();
-                let total_now = Local::now();
+        tab.evaluate("1", false)?;
+        human_pause_with_keepalive(tab, 960)?;
 
-                println!("----------------------------------------");
-                println!("「{}」完了", query);
-                println!(
-                    "  クエリ所要時間: {}",
-                    format_duration(query_start, query_end)
-                );
-                println!(
-                    "  累計経過時間: {}",
-                    format_duration(program_start, total_now)
-                );
-                println!("----------------------------------------");
+        let (html, block_state) = check_block_state(tab, engine, query_dir, config)?;
+        if config.screenshots
+            && let Err(e) = save_page_screenshot(tab, query_dir, &page_num.to_string(), config)
+        {
+            warn!(
+                "{}",
+                tr!(
+                    "  スクリーンショット保存エラー: {}",
+                    "  Failed to save screenshot: {}",
+                    e
+                )
+            );
+        }
+        if config.save_html {
+            save_page_html(query_dir, &page_num.to_string(), &html);
+        }
+        let cumulative = engine.cumulative_results(&html);
+        let mut parsed = parse_fetched_page(engine, html, search_type, config);
+        if let Some(discrepancy) = &parsed.discrepancy {
+            print_discrepancy(discrepancy);
+        }
+        parsed.timing = measure_page_timing(tab, config);
+        if config.low_memory {
+            release_page_memory(tab);
+        }
+        if let Some(timing) = &parsed.timing {
+            track_slow_pages(state, timing, config);
+        }
 
-                query_index += 1;
-                retry_count = 0;
+        // 「もっと見る」型は前回までの結果を除いて論理ページに分割する
+        // 1ページ目は追加読み込み前なので分割不要だが、件数は数えておく
+        if cumulative {
+            parsed.results = parsed
+                .results
+                .split_off(already_saved.min(parsed.results.len()));
+        }
+        already_saved += parsed.results.len();
 
-                if query_index < queries.len() {
-                    let rest = rng.generate_range(3600..=7200);
-                    println!("次のクエリまで {}ms 休憩...", rest);
-                    thread::sleep(Duration::from_millis(rest));
+        // 前ページとほぼ同じ結果ならループ（ソフトブロック）とみなして打ち切る
+        let urls = result_url_set(&parsed.results);
+        if page_num > 1 && is_looping_serp(&previous_urls, &urls) {
+            warn!(
+                "{}",
+                tr!(
+                    "  警告: 前ページとほぼ同じ検索結果です。ループとみなしてこのクエリのページ送りを終了します",
+                    "  Warning: results are nearly identical to the previous page. Treating it as a loop and stopping pagination for this query"
+                )
+            );
+            log_action("serp_loop", serde_json::json!({ "page": page_num }));
+            state.checkpoint.mark_looping(&job.key());
+            break;
+        }
+        previous_urls = urls;
+        parsed.filtered_count = apply_domain_filters(&mut parsed.results, config);
+        log_filtered_or_empty(&parsed);
 
-                    println!("再起動して profile リセット...");
-                    let _ = manager.restart();
-                }
+        if !parsed.results.is_empty() || parsed.filtered_count > 0 {
+            let position = if page_num == 1 {
+                suggestion_position
+            } else {
+                None
+            };
+            save_search_results(
+                sinks,
+                query_dir,
+                query,
+                page_num,
+                &parsed,
+                position,
+                block_state,
+            )?;
+            state.status.add_results(parsed.results.len());
+            state.checkpoint.complete_page(page_num);
+        }
+
+        if state.shutdown_requested() {
+            return Ok(());
+        }
+
+        human_scroll_to_bottom_medium(tab, &config.behavior)?;
+        human_pause_with_keepalive(tab, 750)?;
+
+        // 「もっと見る」型は戻ると読み込んだ結果が消えるのでクリックしない
+        if let Some(click) = &config.click_through
+            && search_type.is_web()
+            && !cumulative
+        {
+            click_through_results(tab, &parsed.results, page_num, query_dir, click, state)?;
+            if state.shutdown_requested() {
+                return Ok(());
             }
-            Err(e) => {
-                retry_count += 1;
-                println!(
-                    "検索エラー: {}。リトライ {}/{}",
-                    e, retry_count, MAX_RETRIES
+        }
+
+        if page_num >= config.max_pages {
+            info!("{}", tr!("  最終ページ到達。", "  Reached the last page."));
+            break;
+        }
+
+        match engine.next_page(tab)? {
+            true => {
+                state.consecutive_no_next = 0;
+                human_pause_with_keepalive(tab, 480)?;
+            }
+            false => {
+                // 「次へ」が無いのがCAPTCHAのせいなら入力待ちにせずバックオフへ回す
+                let html = tab.get_content()?;
+                if engine.detect_block(&tab.get_url(), &html) == BlockState::Captcha {
+                    return Err(captcha_error(tab, query_dir));
+                }
+                state.consecutive_no_next += 1;
+                warn!(
+                    "{}",
+                    tr!(
+                        "  「次へ」が見つかりません（連続{}回目）",
+                        "  \"Next\" not found ({} in a row)",
+                        state.consecutive_no_next
+                    )
                 );
 
-                if retry_count >= MAX_RETRIES {
-                    println!("リトライ上限。次のクエリへスキップ。");
-                    query_index += 1;
-                    retry_count = 0;
-                } else {
-                    println!("ブラウザ再起動して profile リセット...");
-                    let _ = manager.restart();
+                if state.consecutive_no_next >= config.max_consecutive_no_next {
+                    info!("========================================");
+                    warn!(
+                        "{}",
+                        tr!(
+                            "警告: 「次へ」が連続{}回見つかりませんでした",
+                            "Warning: \"Next\" not found {} times in a row",
+                            state.consecutive_no_next
+                        )
+                    );
+                    warn!(
+                        "{}",
+                        tr!("Bot検出の可能性があります。", "Possible bot detection.")
+                    );
+                    println!(
+                        "{}",
+                        tr!("Enterを押すと続行します...", "Press Enter to continue...")
+                    );
+                    info!("========================================");
+                    notify(
+                        config,
+                        &tr!("Gnezdo: 入力待ち", "Gnezdo: waiting for input"),
+                        &tr!(
+                            "「{}」で「次へ」が見つかりません。Bot検出の可能性",
+                            "\"Next\" not found for \"{}\". Possible bot detection",
+                            query
+                        ),
+                    );
+                    state.status.set_state(RunPhase::WaitingInput);
+                    let _ = std::io::stdin().read_line(&mut String::new());
+                    state.status.set_state(RunPhase::Searching);
+                    state.consecutive_no_next = 0;
                 }
-                continue;
+                break;
             }
         }
     }
 
-    println!("\n========================================");
-    println!("全クエリ巡回完了！");
-    println!("========================================");
+    // ===== 絞り込みステップ =====
+    if state.shutdown_requested() || state.skip_current || state.take_skip_request() {
+        return Ok(());
+    }
+    execute_refinement_steps(tab, engine, sinks, job, query_dir, config)?;
 
     Ok(())
 }
 
 // ============================================================
-// アクティブタブ取得
+// 結果のクリックと滞在（[click_through]）
 // ============================================================
-fn get_active_tab(manager: &mut BrowserManager) -> Result<Arc<Tab>> {
-    let browser = manager.get_or_create()?;
-    thread::sleep(Duration::from_millis(500));
+// 結果を上から順に開いて読み、そのたびに検索結果ページへ戻る
+fn click_through_results(
+    tab: &Arc<Tab>,
+    results: &[SearchResult],
+    page_num: u32,
+    query_dir: &Path,
+    click: &ClickThroughConfig,
+    state: &RunState,
+) -> Result<()> {
+    let mut rng = WyRand::new();
+    let serp_url = tab.get_url();
+    let mut picks: Vec<&SearchResult> = results.iter().collect();
+    rng.shuffle(&mut picks);
+    picks.truncate(click.clicks_per_page);
+    picks.sort_by_key(|r| r.rank);
 
-    let tab = {
-        let tabs = browser.get_tabs().lock().unwrap();
-        let first_tab = tabs.first().cloned();
-        for tab in tabs.iter().skip(1) {
-            let _ = tab.close(false);
+    for result in picks {
+        if state.shutdown_requested() {
+            break;
         }
-        first_tab
-    };
-
-    match tab {
-        Some(t) => Ok(t),
-        None => {
-            let browser = manager.get_or_create()?;
-            Ok(browser.new_tab()?)
+        info!(
+            "{}",
+            tr!(
+                "  クリック: {}位 {}",
+                "  Clicking #{} {}",
+                result.rank,
+                result.url
+            )
+        );
+        if let Err(e) = visit_result(tab, result, page_num, query_dir, click, &mut rng) {
+            warn!(
+                "{}",
+                tr!(
+                    "  結果を開けませんでした（{}位）: {}",
+                    "  Could not open result #{}: {}",
+                    result.rank,
+                    e
+                )
+            );
         }
+        return_to_serp(tab, &serp_url)?;
     }
+    Ok(())
 }
 
-// ============================================================
-// 単一クエリ実行
-// ============================================================
-fn execute_single_query(
+fn visit_result(
     tab: &Arc<Tab>,
-    query: &str,
-    query_dir: &PathBuf,
-    consecutive_no_next: &mut u32,
-    config: &Config,
+    result: &SearchResult,
+    page_num: u32,
+    query_dir: &Path,
+    click: &ClickThroughConfig,
+    rng: &mut WyRand,
 ) -> Result<()> {
-    // ===== 初期化 =====
-    tab.navigate_to("about:blank")?;
-    thread::sleep(Duration::from_millis(300));
-    tab.evaluate("1", false)?;
-
-    // ===== Googleトップ =====
-    tab.navigate_to("https://www.google.com")?;
-    tab.wait_until_navigated()?;
-    human_pause_with_keepalive(tab, 960)?;
-
-    // ===== 検索ボックス =====
-    let search_box = tab.wait_for_element("textarea[name='q']")?;
-    search_box.click()?;
-    human_type_medium(tab, query)?;
-    thread::sleep(Duration::from_millis(450));
-
-    tab.press_key("Enter")?;
+    // 抽出時のURL（リダイレクト前の href も含む）と一致するリンクに印を付け、同じタブで開かせる
+    let candidates = serde_json::json!([result.url, result.raw_url]);
+    let marked = tab
+        .evaluate(
+            &format!(
+                r#"(() => {{
+                    document.querySelectorAll('[data-gnezdo-click]').forEach(e => e.removeAttribute('data-gnezdo-click'));
+                    const urls = {}.filter(u => u).map(u => new URL(u, location.href).href);
+                    const link = Array.from(document.querySelectorAll('a[href]')).find(a => urls.includes(a.href));
+                    if (!link) return false;
+                    link.removeAttribute('target');
+                    link.setAttribute('data-gnezdo-click', '1');
+                    return true;
+                }})()"#,
+                candidates
+            ),
+            false,
+        )?
+        .value
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !marked {
+        anyhow::bail!(tr!("リンクが見つかりません", "Link not found"));
+    }
+    let link = tab.find_element("a[data-gnezdo-click]")?;
+    link.scroll_into_view()?;
+    human_pause_with_keepalive(tab, rng.generate_range(400_u64..=1200))?;
+    log_action(
+        "click",
+        serde_json::json!({ "target": "result", "rank": result.rank, "url": result.url }),
+    );
+    human_click(tab, &link)?;
     tab.wait_until_navigated()?;
-    human_pause_with_keepalive(tab, 600)?;
-
-    // ===== 検索結果ページループ =====
-    for page in 0..config.max_pages {
-        let page_num = page + 1;
-        println!("  ページ {}/{}", page_num, config.max_pages);
 
-        tab.evaluate("1", false)?;
-        human_pause_with_keepalive(tab, 960)?;
+    let dwell_max = click.dwell_max_secs.max(click.dwell_min_secs);
+    let dwell = rng.generate_range(click.dwell_min_secs * 1000..=dwell_max * 1000);
+    dwell_on_page(tab, dwell, rng)?;
+    if click.save_html {
+        let landing_dir = query_dir.join("landing");
+        fs::create_dir_all(&landing_dir)?;
+        save_page_html(
+            &landing_dir,
+            &format!("{}-{}", page_num, result.rank),
+            &tab.get_content()?,
+        );
+    }
+    Ok(())
+}
 
-        let html = tab.get_content()?;
-        let results = extract_search_results(&html);
+// ブラウザの「戻る」で検索結果へ戻る。戻れなかったらURLを開き直す
+fn return_to_serp(tab: &Arc<Tab>, serp_url: &str) -> Result<()> {
+    use headless_chrome::protocol::cdp::Page::{GetNavigationHistory, NavigateToHistoryEntry};
 
-        if !results.is_empty() {
-            save_search_results_json(query_dir, query, page_num, &results)?;
-        } else {
-            println!("  警告: 検索結果が見つかりませんでした");
+    if tab.get_url() == serp_url {
+        return Ok(());
+    }
+    let history = tab.call_method(GetNavigationHistory(None))?;
+    let previous = (history.current_index as usize)
+        .checked_sub(1)
+        .and_then(|i| history.entries.get(i));
+    match previous {
+        Some(entry) if entry.url == serp_url => {
+            log_action(
+                "navigate",
+                serde_json::json!({ "url": serp_url, "back": true }),
+            );
+            tab.call_method(NavigateToHistoryEntry { entry_id: entry.id })?;
+            tab.wait_until_navigated()?;
         }
+        _ => {}
+    }
+    if tab.get_url() != serp_url {
+        log_action("navigate", serde_json::json!({ "url": serp_url }));
+        tab.navigate_to(serp_url)?;
+        tab.wait_until_navigated()?;
+    }
+    human_pause_with_keepalive(tab, 800)?;
+    Ok(())
+}
 
-        human_scroll_to_bottom_medium(tab)?;
-        human_pause_with_keepalive(tab, 750)?;
+// ============================================================
+// 絞り込みステップ実行（各ステップの1ページ目を stepN.json に保存）
+// ============================================================
+fn execute_refinement_steps(
+    tab: &Arc<Tab>,
+    engine: &dyn SearchEngine,
+    sinks: &mut OutputSinks,
+    job: &QueryJob,
+    query_dir: &Path,
+    config: &Config,
+) -> Result<()> {
+    let steps = &job.steps;
+    let mut current_query = job.query.clone();
 
-        if page_num >= config.max_pages {
-            println!("  最終ページ到達。");
-            break;
-        }
+    for (i, step) in steps.iter().enumerate() {
+        let step_num = i + 1;
+        info!(
+            "{}",
+            tr!(
+                "  絞り込み {}/{}: {}",
+                "  Refinement {}/{}: {}",
+                step_num,
+                steps.len(),
+                step.describe()
+            )
+        );
 
-        match tab.wait_for_element_with_custom_timeout("#pnnext", Duration::from_secs(3)) {
-            Ok(next_button) => {
-                *consecutive_no_next = 0;
-                next_button.click()?;
-                tab.wait_until_navigated()?;
-                human_pause_with_keepalive(tab, 480)?;
+        match step {
+            FlowStep::AddTerms { terms } => {
+                let search_box = tab.wait_for_element(engine.search_box_selector())?;
+                log_action(
+                    "click",
+                    serde_json::json!({ "target": engine.search_box_selector() }),
+                );
+                human_click(tab, &search_box)?;
+                log_action("key", serde_json::json!({ "keys": ["End"] }));
+                tab.press_key("End")?;
+                thread::sleep(Duration::from_millis(300));
+                human_type_medium(tab, &format!(" {}", terms), &config.behavior)?;
+                thread::sleep(Duration::from_millis(450));
+                log_action("key", serde_json::json!({ "keys": ["Enter"] }));
+                tab.press_key("Enter")?;
+                current_query = format!("{} {}", current_query, terms);
             }
-            Err(_) => {
-                *consecutive_no_next += 1;
-                println!(
-                    "  「次へ」が見つかりません（連続{}回目）",
-                    consecutive_no_next
+            FlowStep::ClickTab { label } => {
+                let script = format!(
+                    r#"(() => {{
+                        const label = {};
+                        const el = [...document.querySelectorAll('a, div[role="link"]')]
+                            .find(e => e.innerText && e.innerText.trim() === label);
+                        if (!el) return false;
+                        el.setAttribute('data-gnezdo-target', '1');
+                        return true;
+                    }})()"#,
+                    serde_json::to_string(label)?
                 );
-
-                if *consecutive_no_next >= config.max_consecutive_no_next {
-                    println!("\n========================================");
-                    println!(
-                        "警告: 「次へ」が連続{}回見つかりませんでした",
-                        consecutive_no_next
+                let found = tab.evaluate(&script, false)?;
+                if !found.value.and_then(|v| v.as_bool()).unwrap_or(false) {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "  警告: タブ「{}」が見つかりません。残りのステップを中止。",
+                            "  Warning: tab \"{}\" not found. Aborting the remaining steps.",
+                            label
+                        )
                     );
-                    println!("Bot検出の可能性があります。");
-                    println!("Enterを押すと続行します...");
-                    println!("========================================");
-                    let _ = std::io::stdin().read_line(&mut String::new());
-                    *consecutive_no_next = 0;
+                    break;
                 }
-                break;
+                log_action(
+                    "click",
+                    serde_json::json!({ "target": "tab", "label": label }),
+                );
+                human_click(tab, &tab.find_element("[data-gnezdo-target='1']")?)?;
             }
         }
+
+        tab.wait_until_navigated()?;
+        human_pause_with_keepalive(tab, 960)?;
+
+        let (html, block_state) = check_block_state(tab, engine, query_dir, config)?;
+        if config.screenshots
+            && let Err(e) =
+                save_page_screenshot(tab, query_dir, &format!("step{}", step_num), config)
+        {
+            warn!(
+                "{}",
+                tr!(
+                    "  スクリーンショット保存エラー: {}",
+                    "  Failed to save screenshot: {}",
+                    e
+                )
+            );
+        }
+        if config.save_html {
+            save_page_html(query_dir, &format!("step{}", step_num), &html);
+        }
+        let mut parsed = parse_fetched_page(engine, html, job.search_type, config);
+        parsed.filtered_count = apply_domain_filters(&mut parsed.results, config);
+        if let Some(discrepancy) = &parsed.discrepancy {
+            print_discrepancy(discrepancy);
+        }
+        parsed.timing = measure_page_timing(tab, config);
+        if config.low_memory {
+            release_page_memory(tab);
+        }
+        log_filtered_or_empty(&parsed);
+        if !parsed.results.is_empty() || parsed.filtered_count > 0 {
+            save_refinement_results(
+                sinks,
+                query_dir,
+                step_num,
+                &current_query,
+                step,
+                &parsed,
+                block_state,
+            )?;
+        }
+
+        human_scroll_to_bottom_medium(tab, &config.behavior)?;
+        human_pause_with_keepalive(tab, 750)?;
     }
 
     Ok(())
@@ -965,8 +12798,17 @@ fn execute_single_query(
 // ============================================================
 // 人間らしいスクロール
 // ============================================================
-fn human_scroll_to_bottom_medium(tab: &Arc<Tab>) -> Result<()> {
+fn human_scroll_to_bottom_medium(tab: &Arc<Tab>, behavior: &BehaviorConfig) -> Result<()> {
     let mut rng = WyRand::new();
+    let timing = behavior.timing()?;
+
+    // PageDownが検索ボックスに吸われないようフォーカスを外す
+    if behavior.keyboard_scroll_rate > 0 {
+        tab.evaluate(
+            "document.activeElement && document.activeElement.blur()",
+            false,
+        )?;
+    }
 
     let mut mode_steps_remaining = 0;
     let mut current_mode: u8 = 0;
@@ -995,44 +12837,44 @@ fn human_scroll_to_bottom_medium(tab: &Arc<Tab>) -> Result<()> {
         }
         mode_steps_remaining -= 1;
 
-        let (scroll_amount, base_delay) = match current_mode {
-            0 => (
-                rng.generate_range(250_i32..=275),
-                rng.generate_range(20_u64..=25),
-            ),
-            1 => (
-                rng.generate_range(275_i32..=300),
-                rng.generate_range(15_u64..=20),
-            ),
-            _ => (
-                rng.generate_range(300_i32..=325),
-                rng.generate_range(10_u64..=15),
-            ),
-        };
+        // モードが大きいほど1ステップが大きく、間が短い
+        let mode = current_mode as u64;
+        let scroll_amount = random_in(&mut rng, range_third(timing.scroll_step_px, mode)) as i32;
+        let base_delay = random_in(&mut rng, range_third(timing.scroll_delay_ms, 2 - mode));
 
-        tab.evaluate(
-            &format!(
-                "window.scrollBy({{ top: {}, behavior: 'auto' }})",
-                scroll_amount
-            ),
-            false,
-        )?;
+        if rng.generate_range(0_u32..100) < behavior.keyboard_scroll_rate {
+            log_action("key", serde_json::json!({ "keys": ["PageDown"] }));
+            tab.press_key("PageDown")?;
+        } else {
+            log_action("scroll", serde_json::json!({ "dy": scroll_amount }));
+            tab.evaluate(
+                &format!(
+                    "window.scrollBy({{ top: {}, behavior: 'auto' }})",
+                    scroll_amount
+                ),
+                false,
+            )?;
+        }
 
         thread::sleep(Duration::from_millis(base_delay));
 
-        if rng.generate_range(0_u32..100) < 30 {
-            let pause = rng.generate_range(240..=720);
+        if rng.generate_range(0_u32..100) < timing.short_pause_rate {
+            let pause = random_in(&mut rng, timing.short_pause_ms);
             human_pause_with_keepalive(tab, pause)?;
         }
 
-        if rng.generate_range(0_u32..100) < 20 {
-            let pause = rng.generate_range(1200..=2400);
-            println!("  ...{}ms 閲覧中", pause);
+        if rng.generate_range(0_u32..100) < timing.long_pause_rate {
+            let pause = random_in(&mut rng, timing.long_pause_ms);
+            info!(
+                "{}",
+                tr!("  ...{}ms 閲覧中", "  ...browsing for {}ms", pause)
+            );
             human_pause_with_keepalive(tab, pause)?;
         }
 
-        if rng.generate_range(0_u32..100) < 10 {
+        if rng.generate_range(0_u32..100) < timing.scroll_back_rate {
             let back_amount = rng.generate_range(38_i32..=112);
+            log_action("scroll", serde_json::json!({ "dy": -back_amount }));
             tab.evaluate(
                 &format!(
                     "window.scrollBy({{ top: -{}, behavior: 'auto' }})",
@@ -1040,7 +12882,7 @@ fn human_scroll_to_bottom_medium(tab: &Arc<Tab>) -> Result<()> {
                 ),
                 false,
             )?;
-            let pause = rng.generate_range(360..=960);
+            let pause = random_in(&mut rng, timing.scroll_back_pause_ms);
             human_pause_with_keepalive(tab, pause)?;
         }
     }
@@ -1048,6 +12890,136 @@ fn human_scroll_to_bottom_medium(tab: &Arc<Tab>) -> Result<()> {
     Ok(())
 }
 
+// ============================================================
+// キーボード操作（入力イベントの多様化）
+// ============================================================
+fn focus_search_box_by_keyboard(tab: &Arc<Tab>) -> Result<bool> {
+    const MAX_TAB_PRESSES: u32 = 20;
+    let mut rng = WyRand::new();
+
+    tab.evaluate(
+        "document.activeElement && document.activeElement.blur()",
+        false,
+    )?;
+
+    for presses in 1..=MAX_TAB_PRESSES {
+        tab.press_key("Tab")?;
+        thread::sleep(Duration::from_millis(rng.generate_range(120_u64..=320)));
+
+        let focused = tab.evaluate(
+            "!!document.activeElement && document.activeElement.name === 'q'",
+            false,
+        )?;
+        if focused.value.and_then(|v| v.as_bool()).unwrap_or(false) {
+            log_action(
+                "key",
+                serde_json::json!({ "keys": ["Tab"], "count": presses }),
+            );
+            return Ok(true);
+        }
+    }
+
+    log_action(
+        "key",
+        serde_json::json!({ "keys": ["Tab"], "count": MAX_TAB_PRESSES }),
+    );
+    info!(
+        "{}",
+        tr!(
+            "  Tabで検索ボックスに到達できず。クリックで代替。",
+            "  Could not reach the search box with Tab. Clicking instead."
+        )
+    );
+    Ok(false)
+}
+
+fn browse_suggestions_by_keyboard(tab: &Arc<Tab>) -> Result<()> {
+    let mut rng = WyRand::new();
+
+    let visible = tab.evaluate("!!document.querySelector('ul[role=\"listbox\"] li')", false)?;
+    if !visible.value.and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Ok(());
+    }
+
+    // 下へ巡回した分だけ上へ戻し、入力したクエリのまま送信する
+    let steps = rng.generate_range(1_u32..=3);
+    for key in ["ArrowDown", "ArrowUp"] {
+        for _ in 0..steps {
+            tab.press_key(key)?;
+            thread::sleep(Duration::from_millis(rng.generate_range(180_u64..=480)));
+        }
+    }
+    log_action(
+        "key",
+        serde_json::json!({ "keys": ["ArrowDown", "ArrowUp"], "count": steps }),
+    );
+
+    Ok(())
+}
+
+// ============================================================
+// サジェストクリックによる送信
+// ============================================================
+fn click_matching_suggestion(tab: &Arc<Tab>, query: &str) -> Result<Option<usize>> {
+    const OPTION_SELECTOR: &str = "ul[role='listbox'] li div[role='option']";
+
+    if tab
+        .wait_for_element_with_custom_timeout(OPTION_SELECTOR, Duration::from_millis(1500))
+        .is_err()
+    {
+        info!(
+            "{}",
+            tr!(
+                "  サジェスト非表示。Enterで送信。",
+                "  No suggestions shown. Submitting with Enter."
+            )
+        );
+        return Ok(None);
+    }
+
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    let wanted = normalize(query);
+
+    let options = tab.find_elements(OPTION_SELECTOR)?;
+    for (i, option) in options.iter().enumerate() {
+        let text = option.get_inner_text().unwrap_or_default();
+        let first_line = text.lines().next().unwrap_or("");
+        if normalize(first_line) == wanted {
+            thread::sleep(Duration::from_millis(
+                WyRand::new().generate_range(240_u64..=600),
+            ));
+            log_action(
+                "click",
+                serde_json::json!({ "target": "suggestion", "position": i + 1 }),
+            );
+            human_click(tab, option)?;
+            info!(
+                "{}",
+                tr!(
+                    "  サジェスト{}番目をクリック",
+                    "  Clicking suggestion #{}",
+                    i + 1
+                )
+            );
+            return Ok(Some(i + 1));
+        }
+    }
+
+    info!(
+        "{}",
+        tr!(
+            "  一致するサジェストなし。Enterで送信。",
+            "  No matching suggestion. Submitting with Enter."
+        )
+    );
+    Ok(None)
+}
+
 // ============================================================
 // Keep-Alive付き停止
 // ============================================================
@@ -1065,17 +13037,495 @@ fn human_pause_with_keepalive(tab: &Arc<Tab>, total_ms: u64) -> Result<()> {
     Ok(())
 }
 
+// ============================================================
+// 人間らしいマウス操作
+// ============================================================
+thread_local! {
+    // このスレッドのタブで最後にマウスを置いた位置（未操作なら None）
+    static MOUSE_POSITION: Cell<Option<(f64, f64)>> = const { Cell::new(None) };
+    // device = "mobile" のワーカーではマウスの代わりにタッチで操作する
+    static TOUCH_INPUT: Cell<bool> = const { Cell::new(false) };
+}
+
+// 要素内のランダムな点まで曲線を描いてマウスを動かし、少し止まってからクリックする
+// タッチ操作のときは軌跡を送らず、その点をタップする
+fn human_click(tab: &Arc<Tab>, element: &Element<'_>) -> Result<()> {
+    let mut rng = WyRand::new();
+    element.scroll_into_view()?;
+    let size = element.get_box_model()?;
+    let center = element.get_midpoint()?;
+    let x = center.x + (unit_random(&mut rng) - 0.5) * size.width * 0.5;
+    let y = center.y + (unit_random(&mut rng) - 0.5) * size.height * 0.5;
+
+    if TOUCH_INPUT.get() {
+        thread::sleep(Duration::from_millis(rng.generate_range(150_u64..=500)));
+        log_action("tap", serde_json::json!({ "at": [x, y] }));
+        dispatch_touch(tab, Input::DispatchTouchEventTypeOption::TouchStart, x, y)?;
+        thread::sleep(Duration::from_millis(rng.generate_range(50_u64..=150)));
+        dispatch_touch(tab, Input::DispatchTouchEventTypeOption::TouchEnd, x, y)?;
+        return Ok(());
+    }
+
+    human_move_mouse(tab, x, y, &mut rng)?;
+    thread::sleep(Duration::from_millis(rng.generate_range(80_u64..=300)));
+    dispatch_mouse(tab, Input::DispatchMouseEventTypeOption::MousePressed, x, y)?;
+    thread::sleep(Duration::from_millis(rng.generate_range(40_u64..=140)));
+    dispatch_mouse(
+        tab,
+        Input::DispatchMouseEventTypeOption::MouseReleased,
+        x,
+        y,
+    )?;
+    Ok(())
+}
+
+// 前回の位置から (x, y) まで3次ベジェ曲線に沿って mouseMoved を送る
+fn human_move_mouse(tab: &Arc<Tab>, x: f64, y: f64, rng: &mut WyRand) -> Result<()> {
+    let (sx, sy) = MOUSE_POSITION.get().unwrap_or_else(|| {
+        (
+            100.0 + unit_random(rng) * 600.0,
+            100.0 + unit_random(rng) * 300.0,
+        )
+    });
+    let (dx, dy) = (x - sx, y - sy);
+    let distance = dx.hypot(dy);
+
+    // 制御点を進行方向と垂直にずらして弧にする
+    let (nx, ny) = if distance > 0.0 {
+        (-dy / distance, dx / distance)
+    } else {
+        (0.0, 0.0)
+    };
+    let bend1 = (unit_random(rng) - 0.5) * distance * 0.5;
+    let bend2 = (unit_random(rng) - 0.5) * distance * 0.5;
+    let c1 = (sx + dx * 0.3 + nx * bend1, sy + dy * 0.3 + ny * bend1);
+    let c2 = (sx + dx * 0.7 + nx * bend2, sy + dy * 0.7 + ny * bend2);
+    let steps = ((distance / 25.0) as u32).clamp(8, 40);
+
+    log_action(
+        "mouse_move",
+        serde_json::json!({ "from": [sx, sy], "to": [x, y], "steps": steps }),
+    );
+    for i in 1..=steps {
+        // 動き出しと止まり際をゆっくりにする
+        let t = i as f64 / steps as f64;
+        let t = t * t * (3.0 - 2.0 * t);
+        let u = 1.0 - t;
+        let px = u * u * u * sx + 3.0 * u * u * t * c1.0 + 3.0 * u * t * t * c2.0 + t * t * t * x;
+        let py = u * u * u * sy + 3.0 * u * u * t * c1.1 + 3.0 * u * t * t * c2.1 + t * t * t * y;
+        dispatch_mouse(tab, Input::DispatchMouseEventTypeOption::MouseMoved, px, py)?;
+        thread::sleep(Duration::from_millis(rng.generate_range(8_u64..=22)));
+    }
+    MOUSE_POSITION.set(Some((x, y)));
+    Ok(())
+}
+
+// touchEnd には触れている指を含めない
+fn dispatch_touch(
+    tab: &Arc<Tab>,
+    kind: Input::DispatchTouchEventTypeOption,
+    x: f64,
+    y: f64,
+) -> Result<()> {
+    let touch_points = match kind {
+        Input::DispatchTouchEventTypeOption::TouchStart
+        | Input::DispatchTouchEventTypeOption::TouchMove => vec![Input::TouchPoint {
+            x,
+            y,
+            radius_x: None,
+            radius_y: None,
+            rotation_angle: None,
+            force: None,
+            tangential_pressure: None,
+            tilt_x: None,
+            tilt_y: None,
+            twist: None,
+            id: None,
+        }],
+        _ => Vec::new(),
+    };
+    tab.call_method(Input::DispatchTouchEvent {
+        Type: kind,
+        touch_points,
+        modifiers: None,
+        timestamp: None,
+    })?;
+    Ok(())
+}
+
+fn dispatch_mouse(
+    tab: &Arc<Tab>,
+    kind: Input::DispatchMouseEventTypeOption,
+    x: f64,
+    y: f64,
+) -> Result<()> {
+    let (button, buttons, click_count) = match kind {
+        Input::DispatchMouseEventTypeOption::MouseMoved => (None, None, None),
+        Input::DispatchMouseEventTypeOption::MousePressed => {
+            (Some(Input::MouseButton::Left), Some(1), Some(1))
+        }
+        _ => (Some(Input::MouseButton::Left), Some(0), Some(1)),
+    };
+    tab.call_method(Input::DispatchMouseEvent {
+        Type: kind,
+        x,
+        y,
+        modifiers: None,
+        timestamp: None,
+        button,
+        buttons,
+        click_count,
+        force: None,
+        tangential_pressure: None,
+        tilt_x: None,
+        tilt_y: None,
+        twist: None,
+        delta_x: None,
+        delta_y: None,
+        pointer_Type: None,
+    })?;
+    Ok(())
+}
+
+// 0.0〜1.0 の一様乱数
+fn unit_random(rng: &mut WyRand) -> f64 {
+    rng.generate_range(0_u32..=10_000) as f64 / 10_000.0
+}
+
 // ============================================================
 // 人間らしいタイピング
 // ============================================================
-fn human_type_medium(tab: &Arc<Tab>, text: &str) -> Result<()> {
+fn human_type_medium(tab: &Arc<Tab>, text: &str, behavior: &BehaviorConfig) -> Result<()> {
+    human_type_observed(tab, text, behavior, &mut |_| {})
+}
+
+// 1文字入力して待つたびに、それまでに入力した文字列で after_key を呼ぶ
+// 数文字ずつのまとまり（バースト）で打ち、バーストの間は長めに止まる
+fn human_type_observed(
+    tab: &Arc<Tab>,
+    text: &str,
+    behavior: &BehaviorConfig,
+    after_key: &mut dyn FnMut(&str),
+) -> Result<()> {
     let mut rng = WyRand::new();
+    let timing = behavior.timing()?;
+
+    // 1文字ずつではなく入力全体を1件として記録する
+    log_action("type", serde_json::json!({ "text": text }));
+    let chars: Vec<char> = text.chars().collect();
+    let mut typed = String::new();
+    let mut burst = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        if burst == 0 {
+            burst = rng.generate_range(2_u32..=6);
+            thread::sleep(Duration::from_millis(random_in(
+                &mut rng,
+                timing.burst_pause_ms,
+            )));
+        }
+        burst -= 1;
+
+        // 非ASCIIの連続部分はまとめて変換入力する
+        if behavior.ime_input && !chars[i].is_ascii() {
+            let end = chars[i..]
+                .iter()
+                .position(|c| c.is_ascii())
+                .map_or(chars.len(), |n| i + n);
+            let segment: String = chars[i..end].iter().collect();
+            ime_compose(tab, &segment, &typed, &mut rng, after_key)?;
+            typed.push_str(&segment);
+            after_key(&typed);
+            i = end;
+            continue;
+        }
 
-    for c in text.chars() {
+        let c = chars[i];
+        if let Some(wrong) = neighbor_key(c, &mut rng)
+            && rng.generate_range(0_u32..100) < behavior.typo_rate
+        {
+            log_action(
+                "typo",
+                serde_json::json!({ "typed": wrong.to_string(), "intended": c.to_string() }),
+            );
+            tab.send_character(&wrong.to_string())?;
+            // 打ち間違いに気づくまでの間
+            thread::sleep(Duration::from_millis(rng.generate_range(200_u64..=600)));
+            tab.press_key("Backspace")?;
+            thread::sleep(Duration::from_millis(rng.generate_range(100_u64..=250)));
+        }
         tab.send_character(&c.to_string())?;
-        let delay = rng.generate_range(75_u64..=300);
+        typed.push(c);
+        let delay = random_in(&mut rng, timing.type_delay_ms);
         thread::sleep(Duration::from_millis(delay));
+        after_key(&typed);
+        i += 1;
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// 変換中の文字列を1文字ずつ伸ばしてから確定する
+// 読みは分からないので、変換後の文字列の先頭部分をそのまま未確定文字列として見せる
+fn ime_compose(
+    tab: &Arc<Tab>,
+    segment: &str,
+    typed: &str,
+    rng: &mut WyRand,
+    after_key: &mut dyn FnMut(&str),
+) -> Result<()> {
+    let mut composing = String::new();
+    for c in segment.chars() {
+        composing.push(c);
+        let len = composing.chars().count() as u32;
+        tab.call_method(Input::ImeSetComposition {
+            text: composing.clone(),
+            selection_start: len,
+            selection_end: len,
+            replacement_start: None,
+            replacement_end: None,
+        })?;
+        thread::sleep(Duration::from_millis(rng.generate_range(90_u64..=260)));
+        after_key(&format!("{}{}", typed, composing));
+    }
+    // 変換キーを押して候補を選ぶまでの間
+    thread::sleep(Duration::from_millis(rng.generate_range(300_u64..=900)));
+    tab.call_method(Input::InsertText {
+        text: segment.to_string(),
+    })?;
+    thread::sleep(Duration::from_millis(rng.generate_range(150_u64..=400)));
+    Ok(())
+}
+
+// QWERTY配列で同じ段の隣にあるキー（英数字以外は None）
+fn neighbor_key(c: char, rng: &mut WyRand) -> Option<char> {
+    const ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+    let lower = c.to_ascii_lowercase();
+    let row: Vec<char> = ROWS.iter().find(|r| r.contains(lower))?.chars().collect();
+    let pos = row.iter().position(|&k| k == lower)?;
+    let neighbors: Vec<char> = [pos.checked_sub(1), Some(pos + 1)]
+        .into_iter()
+        .flatten()
+        .filter_map(|p| row.get(p).copied())
+        .collect();
+    let wrong = neighbors[rng.generate_range(0..neighbors.len())];
+    Some(if c.is_ascii_uppercase() {
+        wrong.to_ascii_uppercase()
+    } else {
+        wrong
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    // tests/fixtures の各HTMLが <名前>.expected.json どおりに解析されること
+    #[test]
+    fn fixtures_match_expected() {
+        let config = Config::default();
+        let mut checked = 0;
+        for entry in fs::read_dir(fixtures_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some(OsStr::new("html")) {
+                continue;
+            }
+            let report = check_fixture(&config, &path).unwrap();
+            assert!(report.checked, "{:?} に期待値がありません", path);
+            assert!(
+                report.differences.is_empty(),
+                "{:?}: {:#?}",
+                path,
+                report.differences
+            );
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn falls_back_to_containers_when_result_selector_matches_nothing() {
+        let html = r#"<div class="g"><a href="https://example.com/"><h3>Example</h3></a></div>
+            <div class="g"><a href="https://www.google.com/maps"><h3>Maps</h3></a></div>"#;
+        let (results, strategy) = extract_search_results(html, &SelectorsConfig::default());
+        assert_eq!(strategy, Some("container"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url, "https://example.com/");
+    }
+
+    #[test]
+    fn heuristic_keeps_only_external_links_with_headings() {
+        let html = r#"<a href="/search?q=next"><h3>Next</h3></a>
+            <a href="https://example.org/a"><h3>A</h3></a>
+            <a href="https://example.org/b">no heading</a>"#;
+        let (results, strategy) = extract_search_results(html, &SelectorsConfig::default());
+        assert_eq!(strategy, Some("heuristic"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "A");
+    }
+
+    #[test]
+    fn configured_selectors_are_used_first() {
+        let selectors = SelectorsConfig {
+            result_link: "a.result".to_string(),
+            result_title: "span.title".to_string(),
+            ..SelectorsConfig::default()
+        };
+        let html = r#"<a class="result" href="https://example.com/"><span class="title">Custom</span></a>"#;
+        let (results, strategy) = extract_search_results(html, &selectors);
+        assert_eq!(strategy, Some("jsname"));
+        assert_eq!(results[0].title, "Custom");
+    }
+
+    #[test]
+    fn normalize_url_strips_tracking_and_trailing_slash() {
+        assert_eq!(
+            normalize_url("https://Example.COM/docs/?utm_source=x&id=3&gclid=y#top"),
+            "https://example.com/docs?id=3"
+        );
+        assert_eq!(normalize_url("https://example.com"), "https://example.com/");
+        assert_eq!(normalize_url("not a url"), "not a url");
+    }
+
+    #[test]
+    fn compare_reports_changed_results() {
+        let parsed = |title: &str| ParsedPage {
+            results: vec![SearchResult::new(
+                title.to_string(),
+                "https://example.com/".to_string(),
+            )],
+            ..Default::default()
+        };
+        let expected = build_page_result("google", "", 1, &parsed("Before"));
+        let actual = build_page_result("google", "", 1, &parsed("After"));
+        assert!(compare_page_results(&expected, &expected.clone()).is_empty());
+        assert_eq!(compare_page_results(&expected, &actual).len(), 1);
+    }
+
+    #[test]
+    fn looping_serp_needs_ninety_percent_overlap() {
+        let set = |range: std::ops::Range<u32>| -> HashSet<String> {
+            range
+                .map(|i| format!("https://example.com/{}", i))
+                .collect()
+        };
+        assert!(is_looping_serp(&set(0..10), &set(0..10)));
+        assert!(is_looping_serp(&set(0..10), &set(1..10)));
+        assert!(!is_looping_serp(&set(0..10), &set(2..12)));
+        assert!(!is_looping_serp(&HashSet::new(), &set(0..10)));
+    }
+
+    #[test]
+    fn search_operators_quote_phrases_and_exclusions() {
+        let operators = SearchOperators {
+            site: Some("example.com".to_string()),
+            intitle: Some("release notes".to_string()),
+            filetype: Some(".pdf".to_string()),
+            exact_phrases: vec!["async runtime".to_string()],
+            exclude_terms: vec!["java".to_string(), "java script".to_string()],
+        };
+        assert_eq!(
+            operators.apply("rust"),
+            "rust \"async runtime\" site:example.com intitle:\"release notes\" filetype:pdf -java -\"java script\""
+        );
+        assert_eq!(SearchOperators::default().apply(" rust "), "rust");
+    }
+
+    #[test]
+    fn date_range_maps_to_tbs() {
+        let preset: DateRange = toml::from_str::<BTreeMap<String, DateRange>>("r = \"past_week\"")
+            .unwrap()
+            .remove("r")
+            .unwrap();
+        assert_eq!(preset.tbs().unwrap(), "qdr:w");
+        let custom = DateRange::Custom {
+            from: Some("2024-01-05".to_string()),
+            to: Some("2024-06-30".to_string()),
+        };
+        assert_eq!(
+            custom.tbs().unwrap(),
+            "cdr:1,cd_min:1/5/2024,cd_max:6/30/2024"
+        );
+        let reversed = DateRange::Custom {
+            from: Some("2024-06-30".to_string()),
+            to: Some("2024-01-05".to_string()),
+        };
+        assert!(reversed.tbs().is_err());
+    }
+
+    #[test]
+    fn locale_drives_language_headers_and_hl_gl() {
+        assert_eq!(
+            accept_language("ja-JP"),
+            "ja-JP,ja;q=0.9,en-US;q=0.8,en;q=0.7"
+        );
+        assert_eq!(accept_language("en-US"), "en-US,en;q=0.9");
+        assert_eq!(launch_languages("de-DE"), "de-DE,de");
+        assert_eq!(
+            locale_hl_gl("de-DE"),
+            ("de".to_string(), Some("de".to_string()))
+        );
+        assert_eq!(locale_hl_gl("fr"), ("fr".to_string(), None));
+    }
+
+    #[test]
+    fn local_pack_address_keeps_digits_matching_rating() {
+        let html = r#"<div class="VkpGBb"><div class="rllt__details">
+            <div role="heading">Cafe Ginza</div>
+            <div><span class="yi40Hd">4.0</span><span class="RDApEe">(120)</span> · ¥1,000～2,000 · Cafe</div>
+            <div>Cafe · Ginza 4-4-1 (1F)</div>
+        </div></div>"#;
+        let results = extract_google_local_results(html);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].rating, Some(4.0));
+        assert_eq!(results[0].review_count, Some(120));
+        assert_eq!(results[0].address.as_deref(), Some("Ginza 4-4-1 (1F)"));
+    }
+
+    #[test]
+    fn smart_schedule_finds_history_of_queries_with_operators() {
+        let root = std::env::temp_dir().join(format!("gnezdo-smart-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let past = (Local::now() - chrono::Duration::hours(1)).format("%Y-%m-%d-%H-%M-%S");
+        let query = "rust site:example.com \"async runtime\"";
+        let query_dir = root.join(past.to_string()).join(safe_dir_name(query));
+        fs::create_dir_all(&query_dir).unwrap();
+        let parsed = ParsedPage {
+            results: vec![SearchResult::new(
+                "Rust".to_string(),
+                "https://example.com/".to_string(),
+            )],
+            ..Default::default()
+        };
+        let page = build_page_result("google", query, 1, &parsed);
+        fs::write(
+            query_dir.join("page_1.json"),
+            serde_json::to_string(&page).unwrap(),
+        )
+        .unwrap();
+
+        let config = Config {
+            search_queries: vec![SearchQuery::Plain(query.to_string())],
+            ..Default::default()
+        };
+        let smart = SmartScheduleConfig {
+            pages_per_day: 100,
+            min_interval_hours: 12,
+            max_interval_hours: 168,
+        };
+        let selected = select_scheduled_jobs(
+            build_query_jobs(&config),
+            &root.join("current"),
+            &smart,
+            &config,
+        )
+        .unwrap();
+        let _ = fs::remove_dir_all(&root);
+        // 1時間前に実行済みなので、最短間隔（12時間）に達するまで見送られる
+        assert!(selected.is_empty());
+    }
+}